@@ -0,0 +1,42 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Records the commit the binary is built from as QPS_BENCH_GIT_SHA, for the
+//! run metadata in results. A value already in the environment (e.g. set by
+//! CI for a source tarball) wins; outside a git checkout it stays unset.
+
+use std::process::Command;
+
+fn main() {
+    println!("cargo:rerun-if-env-changed=QPS_BENCH_GIT_SHA");
+    if std::env::var_os("QPS_BENCH_GIT_SHA").is_some() {
+        return;
+    }
+    let Ok(output) = Command::new("git").args(["rev-parse", "HEAD"]).output() else {
+        return;
+    };
+    if !output.status.success() {
+        return;
+    }
+    let sha = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    println!("cargo:rustc-env=QPS_BENCH_GIT_SHA={}", sha);
+    if let Ok(output) = Command::new("git").args(["rev-parse", "--git-dir"]).output() {
+        let git_dir = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        println!("cargo:rerun-if-changed={}/HEAD", git_dir);
+        println!("cargo:rerun-if-changed={}/refs", git_dir);
+    }
+}
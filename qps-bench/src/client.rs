@@ -0,0 +1,1289 @@
+//! The clients a benchmark drives: the `BenchClient` trait, the OpenDAL
+//! operator and the layers wrapped around it, and the alternative S3 clients.
+
+use super::*;
+
+/// Object-store client the workloads are driven through. Errors use OpenDAL's
+/// type for every client so NotFound retries and error accounting stay uniform.
+pub(crate) trait BenchClient: Send + Sync {
+    fn stat<'a>(&'a self, path: &'a str) -> BoxFuture<'a, opendal::Result<()>>;
+    fn read<'a>(&'a self, path: &'a str) -> BoxFuture<'a, opendal::Result<usize>>;
+    /// Reads the whole object like `read`, returning the content.
+    fn read_content<'a>(&'a self, path: &'a str) -> BoxFuture<'a, opendal::Result<Vec<u8>>>;
+    fn write<'a>(&'a self, path: &'a str, data: Vec<u8>) -> BoxFuture<'a, opendal::Result<()>>;
+    fn delete<'a>(&'a self, path: &'a str) -> BoxFuture<'a, opendal::Result<()>>;
+    /// Lists the direct children of `path`, streaming and counting them and
+    /// stopping once more than `max_entries` have been seen.
+    fn list<'a>(&'a self, path: &'a str, max_entries: usize) -> BoxFuture<'a, opendal::Result<ListOutcome>>;
+
+    /// Reads `length` bytes of `path` from `offset` with a Range request,
+    /// returning the content.
+    fn read_range<'a>(&'a self, _path: &'a str, _offset: u64, _length: u64) -> BoxFuture<'a, opendal::Result<Vec<u8>>> {
+        Box::pin(async { Err(opendal::Error::new(opendal::ErrorKind::Unsupported, "range reads need --client opendal")) })
+    }
+
+    /// Most keys one `delete_batch` call removes in a single request.
+    fn delete_batch_size(&self) -> usize {
+        1
+    }
+
+    /// Deletes `paths`, in as few requests as the client supports.
+    fn delete_batch<'a>(&'a self, paths: &'a [String]) -> BoxFuture<'a, opendal::Result<()>> {
+        Box::pin(async move {
+            for path in paths {
+                self.delete(path).await?;
+            }
+            Ok(())
+        })
+    }
+
+    /// Stat cache counters since the last call, if this client caches stats.
+    fn take_stat_cache_report(&self) -> Option<StatCacheReport> {
+        None
+    }
+
+    /// What the backend supports, as far as the client knows; None when it
+    /// doesn't say (the S3-only clients). Runs probe the endpoint either way.
+    fn capability(&self) -> Option<opendal::Capability> {
+        None
+    }
+}
+
+impl BenchClient for Operator {
+    fn stat<'a>(&'a self, path: &'a str) -> BoxFuture<'a, opendal::Result<()>> {
+        Box::pin(attempt_scope(async move { Operator::stat(self, path).await.map(|_| ()) }))
+    }
+
+    fn read<'a>(&'a self, path: &'a str) -> BoxFuture<'a, opendal::Result<usize>> {
+        Box::pin(attempt_scope(async move { Operator::read(self, path).await.map(|buf| buf.len()) }))
+    }
+
+    fn read_content<'a>(&'a self, path: &'a str) -> BoxFuture<'a, opendal::Result<Vec<u8>>> {
+        Box::pin(attempt_scope(async move { Operator::read(self, path).await.map(|buf| buf.to_vec()) }))
+    }
+
+    fn read_range<'a>(&'a self, path: &'a str, offset: u64, length: u64) -> BoxFuture<'a, opendal::Result<Vec<u8>>> {
+        Box::pin(attempt_scope(async move { Operator::read_with(self, path).range(offset..offset + length).await.map(|buf| buf.to_vec()) }))
+    }
+
+    fn write<'a>(&'a self, path: &'a str, data: Vec<u8>) -> BoxFuture<'a, opendal::Result<()>> {
+        Box::pin(attempt_scope(async move { Operator::write(self, path, data).await.map(|_| ()) }))
+    }
+
+    fn delete<'a>(&'a self, path: &'a str) -> BoxFuture<'a, opendal::Result<()>> {
+        Box::pin(attempt_scope(Operator::delete(self, path)))
+    }
+
+    fn delete_batch_size(&self) -> usize {
+        self.info().full_capability().delete_max_size.unwrap_or(1)
+    }
+
+    fn capability(&self) -> Option<opendal::Capability> {
+        Some(self.info().full_capability())
+    }
+
+    fn delete_batch<'a>(&'a self, paths: &'a [String]) -> BoxFuture<'a, opendal::Result<()>> {
+        Box::pin(attempt_scope(Operator::delete_iter(self, paths.iter().map(String::as_str))))
+    }
+
+    fn list<'a>(&'a self, path: &'a str, max_entries: usize) -> BoxFuture<'a, opendal::Result<ListOutcome>> {
+        Box::pin(attempt_scope(async move {
+            let mut lister = Operator::lister(self, path).await?;
+            let mut entries = 0;
+            while let Some(entry) = lister.next().await {
+                entry?;
+                entries += 1;
+                if entries > max_entries {
+                    return Ok(ListOutcome { truncated: true });
+                }
+            }
+            Ok(ListOutcome { truncated: false })
+        }))
+    }
+}
+
+/// Result of a bounded list: whether the listing was cut short at the limit.
+pub(crate) struct ListOutcome {
+    pub(crate) truncated: bool,
+}
+
+pub(crate) struct VerbStats {
+    pub(crate) histogram: Histogram<u64>,
+    pub(crate) err_ops: u64,
+}
+
+impl VerbStats {
+    pub(crate) fn new() -> Self {
+        VerbStats { histogram: Histogram::<u64>::new(3).unwrap(), err_ops: 0 }
+    }
+
+    pub(crate) fn latency(&self) -> VerbLatency {
+        VerbLatency {
+            ok_ops: self.histogram.len(),
+            err_ops: self.err_ops,
+            latency_us_p50: self.histogram.value_at_quantile(0.5),
+            latency_us_p95: self.histogram.value_at_quantile(0.95),
+            latency_us_p99: self.histogram.value_at_quantile(0.99),
+            latency_us_mean: self.histogram.mean() as u64,
+        }
+    }
+}
+
+pub(crate) type VerbStatsMap = Arc<std::sync::Mutex<BTreeMap<&'static str, VerbStats>>>;
+
+/// Wraps a client and records every request under the HTTP method it maps to.
+/// A LIST covers all of its pages. Stats answered by --stat-cache-ttl-ms never
+/// reach the backend, so they aren't counted as HEADs.
+pub(crate) struct MeteredClient {
+    pub(crate) inner: Arc<dyn BenchClient>,
+    pub(crate) verbs: VerbStatsMap,
+}
+
+impl MeteredClient {
+    /// Times `fut`, a `verb` request for `key` that moves `bytes(&output)` bytes.
+    fn metered<'a, T: Send + 'a>(
+        &'a self,
+        verb: &'static str,
+        key: &'a str,
+        bytes: impl Fn(&T) -> u64 + Send + 'a,
+        fut: BoxFuture<'a, opendal::Result<T>>,
+    ) -> BoxFuture<'a, opendal::Result<T>> {
+        Box::pin(async move {
+            if with_run(|run| run.op_events.get().is_some()) {
+                let _ = OP_CONTEXT.try_with(|context| {
+                    context.request.borrow_mut().get_or_insert_with(|| (verb, key.to_string()));
+                });
+            }
+            let log_entry = OpLogEntry::start(verb, key);
+            let throttled = op_throttled();
+            let start = Instant::now();
+            let (result, cache_hit) = STAT_CACHE_HIT
+                .scope(std::cell::Cell::new(false), async move { (fut.await, STAT_CACHE_HIT.with(std::cell::Cell::get)) })
+                .await;
+            if cache_hit {
+                if let Some(entry) = log_entry {
+                    entry.discard();
+                }
+                return result;
+            }
+            let latency_us = start.elapsed().saturating_sub(op_throttled() - throttled).as_micros() as u64;
+            if let Some(entry) = log_entry {
+                entry.finish(latency_us, result.as_ref().map_or(0, &bytes), result.as_ref().err());
+            }
+            match &result {
+                Ok(_) => note_first_success(start.elapsed()),
+                Err(e) => capture_debug_error(verb, key, latency_us, e),
+            }
+            let mut verbs = self.verbs.lock().unwrap();
+            let stats = verbs.entry(verb).or_insert_with(VerbStats::new);
+            match &result {
+                Ok(_) => {
+                    stats.histogram.record(latency_us).ok();
+                }
+                Err(_) => stats.err_ops += 1,
+            }
+            result
+        })
+    }
+}
+
+impl BenchClient for MeteredClient {
+    fn stat<'a>(&'a self, path: &'a str) -> BoxFuture<'a, opendal::Result<()>> {
+        self.metered("HEAD", path, |_| 0, self.inner.stat(path))
+    }
+
+    fn read<'a>(&'a self, path: &'a str) -> BoxFuture<'a, opendal::Result<usize>> {
+        self.metered("GET", path, |len: &usize| *len as u64, self.inner.read(path))
+    }
+
+    fn read_content<'a>(&'a self, path: &'a str) -> BoxFuture<'a, opendal::Result<Vec<u8>>> {
+        self.metered("GET", path, |content: &Vec<u8>| content.len() as u64, self.inner.read_content(path))
+    }
+
+    fn read_range<'a>(&'a self, path: &'a str, offset: u64, length: u64) -> BoxFuture<'a, opendal::Result<Vec<u8>>> {
+        self.metered("GET", path, |content: &Vec<u8>| content.len() as u64, self.inner.read_range(path, offset, length))
+    }
+
+    fn write<'a>(&'a self, path: &'a str, data: Vec<u8>) -> BoxFuture<'a, opendal::Result<()>> {
+        let len = data.len() as u64;
+        self.metered("PUT", path, move |_| len, self.inner.write(path, data))
+    }
+
+    fn delete<'a>(&'a self, path: &'a str) -> BoxFuture<'a, opendal::Result<()>> {
+        self.metered("DELETE", path, |_| 0, self.inner.delete(path))
+    }
+
+    fn delete_batch_size(&self) -> usize {
+        self.inner.delete_batch_size()
+    }
+
+    fn capability(&self) -> Option<opendal::Capability> {
+        self.inner.capability()
+    }
+
+    fn delete_batch<'a>(&'a self, paths: &'a [String]) -> BoxFuture<'a, opendal::Result<()>> {
+        // S3 batch deletes are POST /?delete (DeleteObjects); logged under the first key
+        let key = paths.first().map_or("", String::as_str);
+        self.metered("POST", key, |_| 0, self.inner.delete_batch(paths))
+    }
+
+    fn take_stat_cache_report(&self) -> Option<StatCacheReport> {
+        self.inner.take_stat_cache_report()
+    }
+
+    fn list<'a>(&'a self, path: &'a str, max_entries: usize) -> BoxFuture<'a, opendal::Result<ListOutcome>> {
+        self.metered("LIST", path, |_| 0, self.inner.list(path, max_entries))
+    }
+}
+
+#[cfg(feature = "http-options")]
+pub(crate) fn parse_header(raw: &str) -> Result<(reqwest::header::HeaderName, reqwest::header::HeaderValue)> {
+    let (name, value) = raw
+        .split_once(':')
+        .ok_or_else(|| anyhow::anyhow!("Invalid header '{}': expected Name:Value", raw))?;
+    let name = reqwest::header::HeaderName::from_bytes(name.trim().as_bytes())
+        .map_err(|e| anyhow::anyhow!("Invalid header name in '{}': {}", raw, e))?;
+    let value = reqwest::header::HeaderValue::from_str(value.trim())
+        .map_err(|e| anyhow::anyhow!("Invalid header value in '{}': {}", raw, e))?;
+    Ok((name, value))
+}
+
+pub(crate) fn env_proxy_configured() -> bool {
+    ["HTTPS_PROXY", "https_proxy", "HTTP_PROXY", "http_proxy", "ALL_PROXY", "all_proxy"]
+        .iter()
+        .any(|name| std::env::var_os(name).is_some_and(|v| !v.is_empty()))
+}
+
+/// Builds a dedicated HTTP client when any client-level option is set.
+///
+/// Returns `None` otherwise so OpenDAL keeps its default client.
+#[cfg(feature = "http-options")]
+pub(crate) fn build_http_client(backend: &BackendArgs) -> Result<Option<reqwest::Client>> {
+    let use_env_proxy = backend.proxy.is_none() && env_proxy_configured();
+    if backend.headers.is_empty()
+        && backend.proxy.is_none()
+        && !use_env_proxy
+        && backend.ca_cert.is_none()
+        && !backend.insecure_skip_tls_verify
+    {
+        return Ok(None);
+    }
+    
+    let mut headers = reqwest::header::HeaderMap::new();
+    for raw in &backend.headers {
+        let (name, value) = parse_header(raw)?;
+        headers.append(name, value);
+    }
+    
+    // reqwest picks up the proxy environment variables on its own
+    let mut builder = reqwest::Client::builder().default_headers(headers);
+    if let Some(proxy) = &backend.proxy {
+        let proxy = reqwest::Proxy::all(proxy)
+            .map_err(|e| anyhow::anyhow!("Invalid proxy URL: {}", e))?;
+        builder = builder.proxy(proxy);
+    }
+    if let Some(path) = &backend.ca_cert {
+        let pem = std::fs::read(path)
+            .map_err(|e| anyhow::anyhow!("Failed to read CA certificate {}: {}", path.display(), e))?;
+        let certs = reqwest::Certificate::from_pem_bundle(&pem)
+            .map_err(|e| anyhow::anyhow!("Invalid CA certificate {}: {}", path.display(), e))?;
+        for cert in certs {
+            builder = builder.add_root_certificate(cert);
+        }
+    }
+    if backend.insecure_skip_tls_verify {
+        builder = builder.danger_accept_invalid_certs(true);
+    }
+    
+    Ok(Some(builder.build()?))
+}
+
+/// The HTTP client the operator sends requests through.
+#[cfg(feature = "http-options")]
+pub(crate) fn operator_http_client(backend: &BackendArgs) -> Result<opendal::raw::HttpClient> {
+    Ok(match build_http_client(backend)? {
+        Some(client) => opendal::raw::HttpClient::with(client),
+        None => opendal::raw::HttpClient::new()?,
+    })
+}
+
+/// Without the `http-options` feature OpenDAL always keeps its default client.
+#[cfg(not(feature = "http-options"))]
+pub(crate) fn operator_http_client(_backend: &BackendArgs) -> Result<opendal::raw::HttpClient> {
+    Ok(opendal::raw::HttpClient::new()?)
+}
+
+pub(crate) fn create_operator(backend: &BackendArgs) -> Result<Operator> {
+    use opendal::services::S3;
+    use opendal::Operator;
+    
+    let mut builder = S3::default()
+        .root("/")
+        .bucket(&backend.bucket)
+        .endpoint(&backend.endpoint)
+        .region(&backend.region)
+        .access_key_id(&backend.access_key)
+        .secret_access_key(&backend.secret_key);
+    
+    if let Some(token) = &backend.session_token {
+        builder = builder.session_token(token);
+    }
+    
+    // OpenDAL defaults to path-style addressing
+    if resolve_addressing_style(backend)? == "virtual" {
+        builder = builder.enable_virtual_host_style();
+    }
+    
+    let mut op: Operator = Operator::new(builder)?.finish();
+    match backend.log_layer.as_str() {
+        "on" => op = op.layer(opendal::layers::LoggingLayer::default()),
+        "off" => {}
+        other => anyhow::bail!("Unknown --log-layer: {}. Use on or off", other),
+    }
+    
+    if backend.trace_sample_ratio.is_some() {
+        #[cfg(feature = "otlp-traces")]
+        {
+            op = op.layer(opendal::layers::TracingLayer);
+        }
+        #[cfg(not(feature = "otlp-traces"))]
+        anyhow::bail!("--trace-sample-ratio requires building with the `otlp-traces` feature");
+    }
+    
+    let http = operator_http_client(backend)?;
+    op = op.layer(opendal::layers::HttpClientLayer::new(opendal::raw::HttpClient::with(StatusRecorder(http))));
+    
+    if backend.retries > 0 {
+        let mut retry = opendal::layers::RetryLayer::new()
+            .with_max_times(backend.retries)
+            .with_min_delay(Duration::from_millis(backend.retry_backoff_ms))
+            .with_factor(2.0);
+        if backend.retry_jitter {
+            retry = retry.with_jitter();
+        }
+        op = op.layer(retry.with_notify(RetryCounter));
+    }
+    
+    Ok(op)
+}
+
+pub(crate) fn create_client(backend: &BackendArgs) -> Result<Arc<dyn BenchClient>> {
+    let client: Arc<dyn BenchClient> = match backend.client.as_str() {
+        "opendal" => Arc::new(create_operator(backend)?),
+        #[cfg(feature = "raw-client")]
+        "raw" => Arc::new(RawS3Client::new(backend)?),
+        #[cfg(not(feature = "raw-client"))]
+        "raw" => anyhow::bail!("--client raw requires building with the `raw-client` feature"),
+        #[cfg(feature = "aws-sdk")]
+        "aws-sdk" => Arc::new(AwsSdkClient::new(backend)?),
+        #[cfg(not(feature = "aws-sdk"))]
+        "aws-sdk" => anyhow::bail!("--client aws-sdk requires building with `--features aws-sdk`"),
+        other => anyhow::bail!("Unknown client: {}. Supported clients: opendal, raw, aws-sdk", other),
+    };
+    let client = match &backend.transfer_engine {
+        Some(engine) => with_transfer_engine(client, create_transfer_engine(engine, backend)?),
+        None => client,
+    };
+    let client: Arc<dyn BenchClient> = match backend.operator_rate_limit {
+        Some(per_second) if per_second > 0.0 => Arc::new(RateLimitedClient::new(client, per_second)),
+        Some(_) => return Err(RunFailure::new(FailureCategory::Config, "--operator-rate-limit must be positive").into()),
+        None => client,
+    };
+    // Cache hits never reach the rate limiter
+    Ok(match backend.stat_cache_ttl_ms {
+        Some(ttl_ms) => Arc::new(StatCacheClient::new(client, Duration::from_millis(ttl_ms))),
+        None => client,
+    })
+}
+
+/// An alternative path for moving whole object bodies (e.g. multipart uploads
+/// and parallel ranged reads, or a CRT-based transfer manager), measured under
+/// the same workloads and reporting as the clients. New engines implement this
+/// behind their own cargo feature and register in `create_transfer_engine`.
+#[cfg(feature = "transfer-engines")]
+pub(crate) trait TransferEngine: Send + Sync {
+    /// Reads the whole object, returning its size.
+    fn download<'a>(&'a self, path: &'a str) -> BoxFuture<'a, opendal::Result<usize>>;
+    fn upload<'a>(&'a self, path: &'a str, data: Vec<u8>) -> BoxFuture<'a, opendal::Result<()>>;
+}
+
+/// OpenDAL's chunked reader and writer: objects are fetched as concurrent
+/// ranged GETs and written as concurrent multipart upload parts.
+#[cfg(feature = "transfer-engines")]
+pub(crate) struct ChunkedTransfer {
+    operator: Operator,
+    chunk: usize,
+    concurrent: usize,
+}
+
+#[cfg(feature = "transfer-engines")]
+impl TransferEngine for ChunkedTransfer {
+    fn download<'a>(&'a self, path: &'a str) -> BoxFuture<'a, opendal::Result<usize>> {
+        Box::pin(async move {
+            let buffer = self.operator.read_with(path).chunk(self.chunk).concurrent(self.concurrent).await?;
+            Ok(buffer.len())
+        })
+    }
+
+    fn upload<'a>(&'a self, path: &'a str, data: Vec<u8>) -> BoxFuture<'a, opendal::Result<()>> {
+        Box::pin(async move {
+            self.operator.write_with(path, data).chunk(self.chunk).concurrent(self.concurrent).await?;
+            Ok(())
+        })
+    }
+}
+
+#[cfg(feature = "transfer-engines")]
+pub(crate) fn create_transfer_engine(name: &str, backend: &BackendArgs) -> Result<Arc<dyn TransferEngine>> {
+    if backend.transfer_chunk_bytes == 0 || backend.transfer_concurrency == 0 {
+        return Err(RunFailure::new(FailureCategory::Config, "--transfer-chunk-bytes and --transfer-concurrency must be at least 1").into());
+    }
+    match name {
+        "opendal-chunked" => Ok(Arc::new(ChunkedTransfer {
+            operator: create_operator(backend)?,
+            chunk: backend.transfer_chunk_bytes,
+            concurrent: backend.transfer_concurrency,
+        })),
+        other => {
+            let message = format!("Unknown transfer engine: {}. Supported transfer engines: {}", other, TRANSFER_ENGINES.join(", "));
+            Err(RunFailure::new(FailureCategory::Config, message).into())
+        }
+    }
+}
+
+#[cfg(not(feature = "transfer-engines"))]
+pub(crate) fn create_transfer_engine(_name: &str, _backend: &BackendArgs) -> Result<()> {
+    Err(RunFailure::new(FailureCategory::Config, "--transfer-engine requires building with the `transfer-engines` cargo feature").into())
+}
+
+/// Names accepted by --transfer-engine in this build.
+pub(crate) const TRANSFER_ENGINES: &[&str] = if cfg!(feature = "transfer-engines") { &["opendal-chunked"] } else { &[] };
+
+#[cfg(feature = "transfer-engines")]
+pub(crate) fn with_transfer_engine(client: Arc<dyn BenchClient>, engine: Arc<dyn TransferEngine>) -> Arc<dyn BenchClient> {
+    Arc::new(TransferEngineClient { inner: client, engine })
+}
+
+#[cfg(not(feature = "transfer-engines"))]
+pub(crate) fn with_transfer_engine(client: Arc<dyn BenchClient>, _engine: ()) -> Arc<dyn BenchClient> {
+    client
+}
+
+/// A client whose reads and writes go through a transfer engine; metadata
+/// ops and deletes stay on the client.
+#[cfg(feature = "transfer-engines")]
+pub(crate) struct TransferEngineClient {
+    inner: Arc<dyn BenchClient>,
+    engine: Arc<dyn TransferEngine>,
+}
+
+#[cfg(feature = "transfer-engines")]
+impl BenchClient for TransferEngineClient {
+    fn stat<'a>(&'a self, path: &'a str) -> BoxFuture<'a, opendal::Result<()>> {
+        self.inner.stat(path)
+    }
+
+    fn read<'a>(&'a self, path: &'a str) -> BoxFuture<'a, opendal::Result<usize>> {
+        self.engine.download(path)
+    }
+
+    fn read_content<'a>(&'a self, path: &'a str) -> BoxFuture<'a, opendal::Result<Vec<u8>>> {
+        self.inner.read_content(path)
+    }
+
+    fn read_range<'a>(&'a self, path: &'a str, offset: u64, length: u64) -> BoxFuture<'a, opendal::Result<Vec<u8>>> {
+        self.inner.read_range(path, offset, length)
+    }
+
+    fn write<'a>(&'a self, path: &'a str, data: Vec<u8>) -> BoxFuture<'a, opendal::Result<()>> {
+        self.engine.upload(path, data)
+    }
+
+    fn delete<'a>(&'a self, path: &'a str) -> BoxFuture<'a, opendal::Result<()>> {
+        self.inner.delete(path)
+    }
+
+    fn list<'a>(&'a self, path: &'a str, max_entries: usize) -> BoxFuture<'a, opendal::Result<ListOutcome>> {
+        self.inner.list(path, max_entries)
+    }
+
+    fn delete_batch_size(&self) -> usize {
+        self.inner.delete_batch_size()
+    }
+
+    fn capability(&self) -> Option<opendal::Capability> {
+        self.inner.capability()
+    }
+
+    fn delete_batch<'a>(&'a self, paths: &'a [String]) -> BoxFuture<'a, opendal::Result<()>> {
+        self.inner.delete_batch(paths)
+    }
+}
+
+/// Spaces the requests of another client evenly at a fixed rate, shared by all
+/// tasks. The time the op spends blocked is added to its OpContext so it can
+/// be excluded from latencies.
+pub(crate) struct RateLimitedClient {
+    inner: Arc<dyn BenchClient>,
+    interval: Duration,
+    /// Start of the next free slot
+    next_slot: std::sync::Mutex<Instant>,
+}
+
+impl RateLimitedClient {
+    fn new(inner: Arc<dyn BenchClient>, per_second: f64) -> Self {
+        RateLimitedClient {
+            inner,
+            interval: Duration::from_secs_f64(1.0 / per_second),
+            next_slot: std::sync::Mutex::new(Instant::now()),
+        }
+    }
+
+    /// Waits for this request's slot.
+    async fn acquire(&self) {
+        let slot = {
+            let mut next_slot = self.next_slot.lock().unwrap();
+            let slot = (*next_slot).max(Instant::now());
+            *next_slot = slot + self.interval;
+            slot
+        };
+        let _ = OP_CONTEXT.try_with(|context| {
+            if context.waiting.get() == 0 {
+                context.waiting_since.set(Some(Instant::now()));
+            }
+            context.waiting.set(context.waiting.get() + 1);
+        });
+        tokio::time::sleep_until(slot.into()).await;
+        let _ = OP_CONTEXT.try_with(|context| {
+            context.waiting.set(context.waiting.get() - 1);
+            if let (0, Some(since)) = (context.waiting.get(), context.waiting_since.get()) {
+                context.throttled.set(context.throttled.get() + since.elapsed());
+            }
+        });
+    }
+
+    fn limited<'a, T: Send + 'a>(&'a self, fut: BoxFuture<'a, opendal::Result<T>>) -> BoxFuture<'a, opendal::Result<T>> {
+        Box::pin(async move {
+            self.acquire().await;
+            fut.await
+        })
+    }
+}
+
+impl BenchClient for RateLimitedClient {
+    fn stat<'a>(&'a self, path: &'a str) -> BoxFuture<'a, opendal::Result<()>> {
+        self.limited(self.inner.stat(path))
+    }
+
+    fn read<'a>(&'a self, path: &'a str) -> BoxFuture<'a, opendal::Result<usize>> {
+        self.limited(self.inner.read(path))
+    }
+
+    fn read_content<'a>(&'a self, path: &'a str) -> BoxFuture<'a, opendal::Result<Vec<u8>>> {
+        self.limited(self.inner.read_content(path))
+    }
+
+    fn read_range<'a>(&'a self, path: &'a str, offset: u64, length: u64) -> BoxFuture<'a, opendal::Result<Vec<u8>>> {
+        self.limited(self.inner.read_range(path, offset, length))
+    }
+
+    fn write<'a>(&'a self, path: &'a str, data: Vec<u8>) -> BoxFuture<'a, opendal::Result<()>> {
+        self.limited(self.inner.write(path, data))
+    }
+
+    fn delete<'a>(&'a self, path: &'a str) -> BoxFuture<'a, opendal::Result<()>> {
+        self.limited(self.inner.delete(path))
+    }
+
+    fn list<'a>(&'a self, path: &'a str, max_entries: usize) -> BoxFuture<'a, opendal::Result<ListOutcome>> {
+        self.limited(self.inner.list(path, max_entries))
+    }
+
+    fn delete_batch_size(&self) -> usize {
+        self.inner.delete_batch_size()
+    }
+
+    fn capability(&self) -> Option<opendal::Capability> {
+        self.inner.capability()
+    }
+
+    fn delete_batch<'a>(&'a self, paths: &'a [String]) -> BoxFuture<'a, opendal::Result<()>> {
+        self.limited(self.inner.delete_batch(paths))
+    }
+}
+
+/// Client-side metadata cache in front of another client: a successful stat
+/// is remembered for `ttl` and answered locally, and writes and deletes made
+/// through it invalidate the entry.
+pub(crate) struct StatCacheClient {
+    inner: Arc<dyn BenchClient>,
+    ttl: Duration,
+    entries: std::sync::Mutex<std::collections::HashMap<String, Instant>>,
+    stats: std::sync::Mutex<StatCacheStats>,
+}
+
+pub(crate) struct StatCacheStats {
+    hits: u64,
+    misses: u64,
+    /// Latency of stats that went to the backend
+    miss_latency: Histogram<u64>,
+}
+
+impl StatCacheStats {
+    fn new() -> Self {
+        StatCacheStats { hits: 0, misses: 0, miss_latency: Histogram::<u64>::new(3).unwrap() }
+    }
+}
+
+impl StatCacheClient {
+    fn new(inner: Arc<dyn BenchClient>, ttl: Duration) -> Self {
+        StatCacheClient {
+            inner,
+            ttl,
+            entries: std::sync::Mutex::new(std::collections::HashMap::new()),
+            stats: std::sync::Mutex::new(StatCacheStats::new()),
+        }
+    }
+
+    fn invalidate(&self, path: &str) {
+        self.entries.lock().unwrap().remove(path);
+    }
+}
+
+impl BenchClient for StatCacheClient {
+    fn stat<'a>(&'a self, path: &'a str) -> BoxFuture<'a, opendal::Result<()>> {
+        Box::pin(async move {
+            let fresh = self.entries.lock().unwrap().get(path).is_some_and(|cached_at| cached_at.elapsed() < self.ttl);
+            if fresh {
+                self.stats.lock().unwrap().hits += 1;
+                let _ = STAT_CACHE_HIT.try_with(|hit| hit.set(true));
+                return Ok(());
+            }
+
+            let start = Instant::now();
+            let result = self.inner.stat(path).await;
+            let mut stats = self.stats.lock().unwrap();
+            stats.misses += 1;
+            if result.is_ok() {
+                stats.miss_latency.record(start.elapsed().as_micros() as u64).ok();
+                self.entries.lock().unwrap().insert(path.to_string(), Instant::now());
+            }
+            result
+        })
+    }
+
+    fn read<'a>(&'a self, path: &'a str) -> BoxFuture<'a, opendal::Result<usize>> {
+        self.inner.read(path)
+    }
+
+    fn read_content<'a>(&'a self, path: &'a str) -> BoxFuture<'a, opendal::Result<Vec<u8>>> {
+        self.inner.read_content(path)
+    }
+
+    fn read_range<'a>(&'a self, path: &'a str, offset: u64, length: u64) -> BoxFuture<'a, opendal::Result<Vec<u8>>> {
+        self.inner.read_range(path, offset, length)
+    }
+
+    fn write<'a>(&'a self, path: &'a str, data: Vec<u8>) -> BoxFuture<'a, opendal::Result<()>> {
+        Box::pin(async move {
+            self.invalidate(path);
+            self.inner.write(path, data).await
+        })
+    }
+
+    fn delete<'a>(&'a self, path: &'a str) -> BoxFuture<'a, opendal::Result<()>> {
+        Box::pin(async move {
+            self.invalidate(path);
+            self.inner.delete(path).await
+        })
+    }
+
+    fn list<'a>(&'a self, path: &'a str, max_entries: usize) -> BoxFuture<'a, opendal::Result<ListOutcome>> {
+        self.inner.list(path, max_entries)
+    }
+
+    fn delete_batch_size(&self) -> usize {
+        self.inner.delete_batch_size()
+    }
+
+    fn capability(&self) -> Option<opendal::Capability> {
+        self.inner.capability()
+    }
+
+    fn delete_batch<'a>(&'a self, paths: &'a [String]) -> BoxFuture<'a, opendal::Result<()>> {
+        for path in paths {
+            self.invalidate(path);
+        }
+        self.inner.delete_batch(paths)
+    }
+
+    fn take_stat_cache_report(&self) -> Option<StatCacheReport> {
+        let stats = std::mem::replace(&mut *self.stats.lock().unwrap(), StatCacheStats::new());
+        let lookups = stats.hits + stats.misses;
+        Some(StatCacheReport {
+            ttl_ms: self.ttl.as_millis() as u64,
+            hits: stats.hits,
+            misses: stats.misses,
+            hit_rate: if lookups == 0 { 0.0 } else { stats.hits as f64 / lookups as f64 },
+            miss_latency_us_p50: stats.miss_latency.value_at_quantile(0.5),
+            miss_latency_us_p99: stats.miss_latency.value_at_quantile(0.99),
+        })
+    }
+}
+
+/// aws-sdk-s3 client, for comparing client stacks on the same workloads. SDK
+/// retries and default PUT checksums are disabled to match the OpenDAL client.
+#[cfg(feature = "aws-sdk")]
+pub(crate) struct AwsSdkClient {
+    client: aws_sdk_s3::Client,
+    bucket: String,
+}
+
+#[cfg(feature = "aws-sdk")]
+impl AwsSdkClient {
+    fn new(backend: &BackendArgs) -> Result<Self> {
+        use aws_sdk_s3::config::retry::RetryConfig;
+        use aws_sdk_s3::config::{BehaviorVersion, Credentials, Region, RequestChecksumCalculation};
+        
+        // The SDK brings its own HTTP stack, so the reqwest-based options can't apply
+        if !backend.headers.is_empty() || backend.proxy.is_some() || backend.ca_cert.is_some() || backend.insecure_skip_tls_verify {
+            anyhow::bail!("--header, --proxy, --ca-cert and --insecure-skip-tls-verify are not supported with --client aws-sdk");
+        }
+        
+        let credentials = Credentials::new(&backend.access_key, &backend.secret_key, backend.session_token.clone(), None, "qps-bench");
+        let config = aws_sdk_s3::Config::builder()
+            .behavior_version(BehaviorVersion::latest())
+            .region(Region::new(backend.region.clone()))
+            .endpoint_url(&backend.endpoint)
+            .credentials_provider(credentials)
+            .force_path_style(resolve_addressing_style(backend)? == "path")
+            .retry_config(RetryConfig::disabled())
+            .request_checksum_calculation(RequestChecksumCalculation::WhenRequired)
+            .build();
+        
+        Ok(AwsSdkClient {
+            client: aws_sdk_s3::Client::from_conf(config),
+            bucket: backend.bucket.clone(),
+        })
+    }
+}
+
+#[cfg(feature = "aws-sdk")]
+impl BenchClient for AwsSdkClient {
+    fn stat<'a>(&'a self, path: &'a str) -> BoxFuture<'a, opendal::Result<()>> {
+        Box::pin(async move {
+            self.client
+                .head_object()
+                .bucket(&self.bucket)
+                .key(normalize_object_path(path))
+                .send()
+                .await
+                .map(|_| ())
+                .map_err(sdk_error)
+        })
+    }
+
+    fn read<'a>(&'a self, path: &'a str) -> BoxFuture<'a, opendal::Result<usize>> {
+        Box::pin(async move {
+            let output = self.client
+                .get_object()
+                .bucket(&self.bucket)
+                .key(normalize_object_path(path))
+                .send()
+                .await
+                .map_err(sdk_error)?;
+            let body = output.body.collect().await.map_err(|e| {
+                opendal::Error::new(opendal::ErrorKind::Unexpected, "aws-sdk S3 body read failed").set_source(e)
+            })?;
+            Ok(body.into_bytes().len())
+        })
+    }
+
+    fn read_content<'a>(&'a self, path: &'a str) -> BoxFuture<'a, opendal::Result<Vec<u8>>> {
+        Box::pin(async move {
+            let output = self.client
+                .get_object()
+                .bucket(&self.bucket)
+                .key(normalize_object_path(path))
+                .send()
+                .await
+                .map_err(sdk_error)?;
+            let body = output.body.collect().await.map_err(|e| {
+                opendal::Error::new(opendal::ErrorKind::Unexpected, "aws-sdk S3 body read failed").set_source(e)
+            })?;
+            Ok(body.into_bytes().to_vec())
+        })
+    }
+
+    fn write<'a>(&'a self, path: &'a str, data: Vec<u8>) -> BoxFuture<'a, opendal::Result<()>> {
+        Box::pin(async move {
+            self.client
+                .put_object()
+                .bucket(&self.bucket)
+                .key(normalize_object_path(path))
+                .body(data.into())
+                .send()
+                .await
+                .map(|_| ())
+                .map_err(sdk_error)
+        })
+    }
+
+    fn delete<'a>(&'a self, path: &'a str) -> BoxFuture<'a, opendal::Result<()>> {
+        Box::pin(async move {
+            self.client
+                .delete_object()
+                .bucket(&self.bucket)
+                .key(normalize_object_path(path))
+                .send()
+                .await
+                .map(|_| ())
+                .map_err(sdk_error)
+        })
+    }
+
+    fn list<'a>(&'a self, path: &'a str, max_entries: usize) -> BoxFuture<'a, opendal::Result<ListOutcome>> {
+        Box::pin(async move {
+            let prefix = match normalize_object_path(path) {
+                p if p.is_empty() => p,
+                p => format!("{}/", p),
+            };
+            let mut pages = self.client
+                .list_objects_v2()
+                .bucket(&self.bucket)
+                .prefix(prefix)
+                .delimiter("/")
+                .into_paginator()
+                .send();
+            let mut entries = 0;
+            while let Some(page) = pages.next().await {
+                let page = page.map_err(sdk_error)?;
+                entries += page.contents().len() + page.common_prefixes().len();
+                if entries > max_entries {
+                    return Ok(ListOutcome { truncated: true });
+                }
+            }
+            Ok(ListOutcome { truncated: false })
+        })
+    }
+}
+
+#[cfg(feature = "aws-sdk")]
+pub(crate) fn sdk_error<E>(e: aws_sdk_s3::error::SdkError<E, aws_sdk_s3::config::http::HttpResponse>) -> opendal::Error
+where
+    E: std::error::Error + aws_sdk_s3::error::ProvideErrorMetadata + Send + Sync + 'static,
+{
+    use aws_sdk_s3::error::ProvideErrorMetadata;
+    
+    let status = e.raw_response().map(|response| response.status().as_u16());
+    let kind = status.map_or(opendal::ErrorKind::Unexpected, |status| status_error_kind(status, e.code()));
+    if let Some(status) = status {
+        note_error_response(status, e.code().map(str::to_string));
+    }
+    let mut error = opendal::Error::new(kind, "aws-sdk S3 request failed");
+    if let Some(status) = status {
+        error = error.with_context("status", status.to_string());
+    }
+    if let Some(code) = e.code() {
+        error = error.with_context("code", code.to_string());
+    }
+    error.set_source(e)
+}
+
+/// Bare-bones S3 client: one SigV4-signed request per call with no retries,
+/// layers or streaming. Payloads are sent unsigned (UNSIGNED-PAYLOAD), as
+/// OpenDAL does, so both clients do the same signing work.
+#[cfg(feature = "raw-client")]
+pub(crate) struct RawS3Client {
+    http: reqwest::Client,
+    /// scheme://authority requests are sent to (bucket included for virtual-hosted style)
+    base_url: String,
+    /// Host header value; signed, so it must match what reqwest sends
+    host: String,
+    /// Path prefix of every object URI: "/bucket/" or "/" for virtual-hosted style
+    base_path: String,
+    region: String,
+    access_key: String,
+    secret_key: String,
+    session_token: Option<String>,
+}
+
+#[cfg(feature = "raw-client")]
+impl RawS3Client {
+    fn new(backend: &BackendArgs) -> Result<Self> {
+        let endpoint = reqwest::Url::parse(&backend.endpoint)
+            .map_err(|e| anyhow::anyhow!("Invalid --endpoint {}: {}", backend.endpoint, e))?;
+        let host = endpoint
+            .host_str()
+            .ok_or_else(|| anyhow::anyhow!("--endpoint {} has no host", backend.endpoint))?;
+        // Url::port() is None for the scheme's default port, matching reqwest's Host header
+        let authority = match endpoint.port() {
+            Some(port) => format!("{}:{}", host, port),
+            None => host.to_string(),
+        };
+        let (host, base_path) = if resolve_addressing_style(backend)? == "virtual" {
+            (format!("{}.{}", backend.bucket, authority), "/".to_string())
+        } else {
+            (authority, format!("/{}/", backend.bucket))
+        };
+        
+        Ok(RawS3Client {
+            http: build_http_client(backend)?.unwrap_or_default(),
+            base_url: format!("{}://{}", endpoint.scheme(), host),
+            host,
+            base_path,
+            region: backend.region.clone(),
+            access_key: backend.access_key.clone(),
+            secret_key: backend.secret_key.clone(),
+            session_token: backend.session_token.clone(),
+        })
+    }
+
+    /// Signs and sends one request for `path`, mapping non-2xx responses to
+    /// the OpenDAL error kind the operator would have returned.
+    async fn send(&self, method: reqwest::Method, path: &str, query: &[(&str, &str)], body: Option<Vec<u8>>) -> opendal::Result<reqwest::Response> {
+        let uri = format!("{}{}", self.base_path, uri_encode(&normalize_object_path(path), true));
+        let query = sigv4_canonical_query(query);
+        
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        let (date, timestamp) = amz_dates(now);
+        // Canonical headers must be sorted by name
+        let mut headers = vec![
+            ("host", self.host.clone()),
+            ("x-amz-content-sha256", "UNSIGNED-PAYLOAD".to_string()),
+            ("x-amz-date", timestamp.clone()),
+        ];
+        if let Some(token) = &self.session_token {
+            headers.push(("x-amz-security-token", token.clone()));
+        }
+        let signed_headers = headers.iter().map(|(name, _)| *name).collect::<Vec<_>>().join(";");
+        let canonical_request = sigv4_canonical_request(method.as_str(), &uri, &query, &headers, "UNSIGNED-PAYLOAD");
+        let scope = format!("{}/{}/s3/aws4_request", date, self.region);
+        let signature = sigv4_signature(&self.secret_key, &timestamp, &self.region, "s3", &canonical_request);
+        let authorization = format!(
+            "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+            self.access_key, scope, signed_headers, signature
+        );
+        
+        let mut url = format!("{}{}", self.base_url, uri);
+        if !query.is_empty() {
+            url.push('?');
+            url.push_str(&query);
+        }
+        // reqwest derives Host from the URL
+        let mut request = self.http.request(method, url).header("authorization", authorization);
+        for (name, value) in headers.into_iter().skip(1) {
+            request = request.header(name, value);
+        }
+        if let Some(body) = body {
+            request = request.body(body);
+        }
+        
+        let response = request.send().await.map_err(raw_request_error)?;
+        let status = response.status();
+        if status.is_success() {
+            return Ok(response);
+        }
+        let body = response.text().await.unwrap_or_default();
+        let code = xml_tag_value(&body, "Code");
+        note_error_response(status.as_u16(), code.clone());
+        let mut error = opendal::Error::new(status_error_kind(status.as_u16(), code.as_deref()), format!("raw S3 request failed with status {}", status))
+            .with_context("status", status.as_u16().to_string());
+        if let Some(code) = code {
+            error = error.with_context("code", code);
+        }
+        Err(error)
+    }
+}
+
+#[cfg(feature = "raw-client")]
+impl BenchClient for RawS3Client {
+    fn stat<'a>(&'a self, path: &'a str) -> BoxFuture<'a, opendal::Result<()>> {
+        Box::pin(async move { self.send(reqwest::Method::HEAD, path, &[], None).await.map(|_| ()) })
+    }
+
+    fn read<'a>(&'a self, path: &'a str) -> BoxFuture<'a, opendal::Result<usize>> {
+        Box::pin(async move {
+            let response = self.send(reqwest::Method::GET, path, &[], None).await?;
+            let body = response.bytes().await.map_err(raw_request_error)?;
+            Ok(body.len())
+        })
+    }
+
+    fn read_content<'a>(&'a self, path: &'a str) -> BoxFuture<'a, opendal::Result<Vec<u8>>> {
+        Box::pin(async move {
+            let response = self.send(reqwest::Method::GET, path, &[], None).await?;
+            let body = response.bytes().await.map_err(raw_request_error)?;
+            Ok(body.to_vec())
+        })
+    }
+
+    fn write<'a>(&'a self, path: &'a str, data: Vec<u8>) -> BoxFuture<'a, opendal::Result<()>> {
+        Box::pin(async move { self.send(reqwest::Method::PUT, path, &[], Some(data)).await.map(|_| ()) })
+    }
+
+    fn delete<'a>(&'a self, path: &'a str) -> BoxFuture<'a, opendal::Result<()>> {
+        // S3 answers 204 for missing keys too, matching OpenDAL's idempotent delete
+        Box::pin(async move { self.send(reqwest::Method::DELETE, path, &[], None).await.map(|_| ()) })
+    }
+
+    fn list<'a>(&'a self, path: &'a str, max_entries: usize) -> BoxFuture<'a, opendal::Result<ListOutcome>> {
+        Box::pin(async move {
+            // ListObjectsV2 with a delimiter, paging like OpenDAL's non-recursive list
+            let prefix = match normalize_object_path(path) {
+                p if p.is_empty() => p,
+                p => format!("{}/", p),
+            };
+            let mut entries = 0;
+            let mut token: Option<String> = None;
+            loop {
+                let mut query = vec![("list-type", "2"), ("delimiter", "/"), ("prefix", prefix.as_str())];
+                if let Some(token) = &token {
+                    query.push(("continuation-token", token.as_str()));
+                }
+                let response = self.send(reqwest::Method::GET, "", &query, None).await?;
+                let body = response.text().await.map_err(raw_request_error)?;
+                entries += body.matches("<Contents>").count() + body.matches("<CommonPrefixes>").count();
+                if entries > max_entries {
+                    return Ok(ListOutcome { truncated: true });
+                }
+                token = xml_tag_value(&body, "NextContinuationToken");
+                if token.is_none() || xml_tag_value(&body, "IsTruncated").as_deref() != Some("true") {
+                    return Ok(ListOutcome { truncated: false });
+                }
+            }
+        })
+    }
+}
+
+/// Maps an S3 HTTP status and error code to the OpenDAL error kind the
+/// operator reports for them.
+#[cfg(any(feature = "raw-client", feature = "aws-sdk"))]
+pub(crate) fn status_error_kind(status: u16, code: Option<&str>) -> opendal::ErrorKind {
+    match (status, code) {
+        (_, Some("SlowDown")) | (429, _) => opendal::ErrorKind::RateLimited,
+        (404, _) => opendal::ErrorKind::NotFound,
+        (403, _) => opendal::ErrorKind::PermissionDenied,
+        _ => opendal::ErrorKind::Unexpected,
+    }
+}
+
+#[cfg(feature = "raw-client")]
+pub(crate) fn raw_request_error(e: reqwest::Error) -> opendal::Error {
+    opendal::Error::new(opendal::ErrorKind::Unexpected, "raw S3 request failed").set_source(e)
+}
+
+/// Collapses repeated and leading slashes the way OpenDAL normalizes paths,
+/// so both clients address the same keys.
+#[cfg(any(feature = "raw-client", feature = "aws-sdk"))]
+pub(crate) fn normalize_object_path(path: &str) -> String {
+    path.split('/').filter(|segment| !segment.is_empty()).collect::<Vec<_>>().join("/")
+}
+
+/// Percent-encodes `input` as SigV4 requires: only unreserved characters (and
+/// '/' in object paths) are left as-is.
+#[cfg(feature = "raw-client")]
+pub(crate) fn uri_encode(input: &str, keep_slash: bool) -> String {
+    let mut encoded = String::with_capacity(input.len());
+    for byte in input.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => encoded.push(byte as char),
+            b'/' if keep_slash => encoded.push('/'),
+            _ => {
+                let _ = write!(encoded, "%{:02X}", byte);
+            }
+        }
+    }
+    encoded
+}
+
+#[cfg(feature = "raw-client")]
+pub(crate) fn hmac_sha256(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(data);
+    mac.finalize().into_bytes().to_vec()
+}
+
+/// Encodes and sorts query parameters into the SigV4 canonical query string.
+#[cfg(feature = "raw-client")]
+pub(crate) fn sigv4_canonical_query(query: &[(&str, &str)]) -> String {
+    let mut query: Vec<(String, String)> = query.iter().map(|(k, v)| (uri_encode(k, false), uri_encode(v, false))).collect();
+    query.sort();
+    query.iter().map(|(k, v)| format!("{}={}", k, v)).collect::<Vec<_>>().join("&")
+}
+
+/// The SigV4 canonical request. `headers` are the signed headers, lowercase
+/// and sorted by name; `uri` and `query` are already encoded.
+#[cfg(feature = "raw-client")]
+pub(crate) fn sigv4_canonical_request(method: &str, uri: &str, query: &str, headers: &[(&str, String)], payload_hash: &str) -> String {
+    let signed_headers = headers.iter().map(|(name, _)| *name).collect::<Vec<_>>().join(";");
+    let canonical_headers: String = headers.iter().map(|(name, value)| format!("{}:{}\n", name, value)).collect();
+    format!("{}\n{}\n{}\n{}\n{}\n{}", method, uri, query, canonical_headers, signed_headers, payload_hash)
+}
+
+/// Derives the SigV4 signing key for `date` (YYYYMMDD), `region` and `service`.
+#[cfg(feature = "raw-client")]
+pub(crate) fn sigv4_signing_key(secret_key: &str, date: &str, region: &str, service: &str) -> Vec<u8> {
+    let mut signing_key = hmac_sha256(format!("AWS4{}", secret_key).as_bytes(), date.as_bytes());
+    for part in [region, service, "aws4_request"] {
+        signing_key = hmac_sha256(&signing_key, part.as_bytes());
+    }
+    signing_key
+}
+
+/// Signs `canonical_request` made at `timestamp` (YYYYMMDDTHHMMSSZ),
+/// returning the hex signature for the Authorization header.
+#[cfg(feature = "raw-client")]
+pub(crate) fn sigv4_signature(secret_key: &str, timestamp: &str, region: &str, service: &str, canonical_request: &str) -> String {
+    let date = &timestamp[..8];
+    let string_to_sign = format!(
+        "AWS4-HMAC-SHA256\n{}\n{}/{}/{}/aws4_request\n{}",
+        timestamp,
+        date,
+        region,
+        service,
+        hex::encode(Sha256::digest(canonical_request.as_bytes()))
+    );
+    hex::encode(hmac_sha256(&sigv4_signing_key(secret_key, date, region, service), string_to_sign.as_bytes()))
+}
+
+/// Formats a unix timestamp as the compact UTC date (YYYYMMDD) and timestamp
+/// (YYYYMMDDTHHMMSSZ) used by SigV4 and results directory names.
+pub(crate) fn amz_dates(unix_secs: u64) -> (String, String) {
+    // Civil-from-days conversion (proleptic Gregorian calendar, UTC)
+    let days = (unix_secs / 86_400) as i64 + 719_468;
+    let secs = unix_secs % 86_400;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era = (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 { shifted_month + 3 } else { shifted_month - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    
+    let date = format!("{:04}{:02}{:02}", year, month, day);
+    let timestamp = format!("{}T{:02}{:02}{:02}Z", date, secs / 3_600, secs % 3_600 / 60, secs % 60);
+    (date, timestamp)
+}
+
+/// Returns the text of the first `<tag>` element in an S3 XML response.
+#[cfg(feature = "raw-client")]
+pub(crate) fn xml_tag_value(body: &str, tag: &str) -> Option<String> {
+    let open = format!("<{}>", tag);
+    let start = body.find(&open)? + open.len();
+    let end = start + body[start..].find(&format!("</{}>", tag))?;
+    Some(body[start..end].replace("&amp;", "&"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // SigV4 vectors from AWS's "Signature Calculations for the Authorization
+    // Header" S3 examples and "Derive a signing key" IAM example
+    #[cfg(feature = "raw-client")]
+    const EXAMPLE_SECRET_KEY: &str = "wJalrXUtnFEMI/K7MDENG/bPxRfiCYEXAMPLEKEY";
+    #[cfg(feature = "raw-client")]
+    const EMPTY_PAYLOAD_HASH: &str = "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+
+    #[cfg(feature = "raw-client")]
+    fn example_headers(extra: &[(&'static str, &str)]) -> Vec<(&'static str, String)> {
+        let mut headers = vec![
+            ("host", "examplebucket.s3.amazonaws.com".to_string()),
+            ("x-amz-content-sha256", EMPTY_PAYLOAD_HASH.to_string()),
+            ("x-amz-date", "20130524T000000Z".to_string()),
+        ];
+        headers.extend(extra.iter().map(|(name, value)| (*name, value.to_string())));
+        headers.sort();
+        headers
+    }
+
+    #[test]
+    #[cfg(feature = "raw-client")]
+    fn uri_encode_leaves_only_unreserved_characters() {
+        assert_eq!(uri_encode("test$file.text", true), "test%24file.text");
+        assert_eq!(uri_encode("photos/photo 1~a_b-c.jpg", true), "photos/photo%201~a_b-c.jpg");
+        assert_eq!(uri_encode("a/b+c=d", false), "a%2Fb%2Bc%3Dd");
+        assert_eq!(uri_encode("é", false), "%C3%A9");
+    }
+
+    #[test]
+    #[cfg(feature = "raw-client")]
+    fn sigv4_signing_key_matches_aws_example() {
+        let key = sigv4_signing_key("wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY", "20150830", "us-east-1", "iam");
+        assert_eq!(hex::encode(key), "c4afb1cc5771d871763a393e44b703571b55cc28424d1a5e86da6ed3c154a4b9");
+    }
+
+    #[test]
+    #[cfg(feature = "raw-client")]
+    fn sigv4_get_object_matches_aws_example() {
+        let headers = example_headers(&[("range", "bytes=0-9")]);
+        let canonical_request = sigv4_canonical_request("GET", "/test.txt", "", &headers, EMPTY_PAYLOAD_HASH);
+        assert_eq!(
+            canonical_request,
+            format!(
+                "GET\n/test.txt\n\nhost:examplebucket.s3.amazonaws.com\nrange:bytes=0-9\nx-amz-content-sha256:{0}\n\
+                 x-amz-date:20130524T000000Z\n\nhost;range;x-amz-content-sha256;x-amz-date\n{0}",
+                EMPTY_PAYLOAD_HASH
+            )
+        );
+        assert_eq!(hex::encode(Sha256::digest(canonical_request.as_bytes())), "7344ae5b7ee6c3e7e6b0fe0640412a37625d1fbfff95c48bbb2dc43964946972");
+        assert_eq!(
+            sigv4_signature(EXAMPLE_SECRET_KEY, "20130524T000000Z", "us-east-1", "s3", &canonical_request),
+            "f0e8bdb87c964420e857bd35b5d6ed310bd44f0170aba48dd91039c6036bdb41"
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "raw-client")]
+    fn sigv4_list_objects_matches_aws_example() {
+        let query = sigv4_canonical_query(&[("prefix", "J"), ("max-keys", "2")]);
+        assert_eq!(query, "max-keys=2&prefix=J");
+        let canonical_request = sigv4_canonical_request("GET", "/", &query, &example_headers(&[]), EMPTY_PAYLOAD_HASH);
+        assert_eq!(hex::encode(Sha256::digest(canonical_request.as_bytes())), "df57d21db20da04d7fa30298dd4488ba3a2b47ca3a489c74750e0f1e7df1b9b7");
+        assert_eq!(
+            sigv4_signature(EXAMPLE_SECRET_KEY, "20130524T000000Z", "us-east-1", "s3", &canonical_request),
+            "34b48302e7b5fa45bde8084f4b7868a86f0a534bc59db6670ed5711ef69dc6f7"
+        );
+    }
+
+    #[test]
+    fn amz_dates_formats_utc() {
+        assert_eq!(amz_dates(1_369_353_600), ("20130524".to_string(), "20130524T000000Z".to_string()));
+        assert_eq!(amz_dates(951_782_399), ("20000228".to_string(), "20000228T235959Z".to_string()));
+    }
+
+    #[test]
+    #[cfg(feature = "http-options")]
+    fn headers_parse_as_name_colon_value() {
+        let (name, value) = parse_header("X-Amz-Request-Payer: requester").unwrap();
+        assert_eq!(name.as_str(), "x-amz-request-payer");
+        assert_eq!(value, "requester");
+        assert_eq!(parse_header("no-colon").unwrap_err().to_string(), "Invalid header 'no-colon': expected Name:Value");
+        assert!(parse_header("bad name: value").unwrap_err().to_string().starts_with("Invalid header name in 'bad name: value'"));
+    }
+
+    #[test]
+    #[cfg(feature = "raw-client")]
+    fn xml_tag_value_reads_the_first_element() {
+        let body = "<Error><Code>SlowDown</Code><Message>Reduce &amp; retry</Message><Code>Other</Code></Error>";
+        assert_eq!(xml_tag_value(body, "Code").as_deref(), Some("SlowDown"));
+        assert_eq!(xml_tag_value(body, "Message").as_deref(), Some("Reduce & retry"));
+        assert_eq!(xml_tag_value(body, "RequestId"), None);
+        assert_eq!(xml_tag_value("<Code>unterminated", "Code"), None);
+    }
+
+    #[test]
+    #[cfg(any(feature = "raw-client", feature = "aws-sdk"))]
+    fn status_error_kind_matches_the_operator() {
+        assert_eq!(status_error_kind(503, Some("SlowDown")), opendal::ErrorKind::RateLimited);
+        assert_eq!(status_error_kind(429, None), opendal::ErrorKind::RateLimited);
+        assert_eq!(status_error_kind(404, Some("NoSuchKey")), opendal::ErrorKind::NotFound);
+        assert_eq!(status_error_kind(403, Some("AccessDenied")), opendal::ErrorKind::PermissionDenied);
+        assert_eq!(status_error_kind(500, Some("InternalError")), opendal::ErrorKind::Unexpected);
+    }
+}
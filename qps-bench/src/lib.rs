@@ -0,0 +1,5724 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! QPS and latency microbenchmark for OpenDAL and S3 clients. The
+//! `qps-bench` binary is a thin command line over [`run_cli`]; tests and
+//! harnesses describe a [`Scenario`] and [`run`] it for a typed [`BenchReport`].
+
+use anyhow::Result;
+use clap::{CommandFactory, FromArgMatches, Parser, Subcommand};
+use futures::future::BoxFuture;
+use futures::StreamExt;
+use hdrhistogram::Histogram;
+#[cfg(feature = "raw-client")]
+use hmac::{Hmac, Mac};
+use opendal::Operator;
+use rand::rngs::StdRng;
+use rand::{Rng, RngCore, SeedableRng};
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "raw-client")]
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::future::Future;
+#[cfg(feature = "bundle")]
+use std::io::Read;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+/// Prints report output: the results, tables and summaries a command exists
+/// to produce. Banners, progress and warnings go through tracing instead.
+macro_rules! report {
+    () => {
+        report_line(format_args!(""))
+    };
+    ($($arg:tt)*) => {
+        report_line(format_args!($($arg)*))
+    };
+}
+
+mod client;
+mod modes;
+mod report;
+mod sinks;
+
+use client::*;
+use modes::*;
+use report::*;
+use sinks::*;
+
+#[derive(Parser, Debug)]
+#[command(name = "qps-bench", version)]
+#[command(about = "QPS and latency microbenchmark for OpenDAL operations")]
+#[command(args_conflicts_with_subcommands = true)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// Print the compiled-in clients, services, modes, layers, sinks and
+    /// optional features as JSON and exit
+    #[arg(long)]
+    capabilities: bool,
+
+    /// Log at this level (error, warn, info, debug, trace, or a RUST_LOG-style
+    /// filter); debug shows every request seen by the logging layer. Progress
+    /// and warnings are logged at info by default, or per RUST_LOG when set.
+    #[arg(long, global = true)]
+    log_level: Option<String>,
+
+    /// Progress, warnings and log records as plain text lines, or as one JSON
+    /// object per line for log pipelines. Results are printed either way.
+    #[arg(long, global = true, default_value = "text")]
+    log_format: String,
+
+    /// Without a subcommand, a benchmark is run with these arguments
+    #[command(flatten)]
+    run: Args,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Create a dataset and record it in a manifest for `run` and `cleanup`
+    Prepare(PrepareArgs),
+    /// Run a mode against a dataset created by `prepare`, without creating or deleting it
+    Run(Box<RunArgs>),
+    /// Delete the objects recorded in a dataset manifest
+    Cleanup(CleanupArgs),
+    /// Delete the objects left in a cleanup remainder file
+    Gc(GcArgs),
+    /// Print saved results files in human-readable form
+    Report(ReportArgs),
+    /// Chart QPS and P99 of a scenario's runs recorded with --results-db and
+    /// flag runs that fall behind the moving average of the runs before them
+    Trend(TrendArgs),
+    /// Derive a run config matching the operation mix and object sizes in S3 access logs or a metrics CSV
+    Calibrate(CalibrateArgs),
+    /// Preflight check: probe connectivity, the bucket and PUT/GET/HEAD/LIST/DELETE permissions
+    Doctor(DoctorArgs),
+    /// Check a --config file for unknown settings, conflicting options and
+    /// missing credentials without running anything
+    Validate(ValidateArgs),
+    /// Ask for the provider, endpoint, credentials source and a workload preset,
+    /// then write a ready-to-run config file
+    Init(InitArgs),
+    /// Re-run the workload recorded in a reproducibility bundle against a backend and compare results
+    Rerun(RerunArgs),
+    /// Print a Grafana dashboard (JSON) charting the metrics exported by
+    /// --prometheus-listen, --pushgateway-url and --otlp-endpoint
+    GrafanaDashboard(GrafanaDashboardArgs),
+}
+
+#[derive(clap::Args, Debug)]
+struct PrepareArgs {
+    /// Where to write the dataset manifest
+    #[arg(long)]
+    dataset: std::path::PathBuf,
+
+    #[command(flatten)]
+    backend: BackendArgs,
+
+    /// Key prefix
+    #[arg(long, default_value = "bench")]
+    prefix: String,
+
+    /// Number of objects in dataset
+    #[arg(long, default_value = "10000")]
+    objects: usize,
+
+    /// Object size in bytes
+    #[arg(long, default_value = "1024")]
+    object_size_bytes: usize,
+
+    /// Uploads in flight while creating the dataset
+    #[arg(long, default_value = "32")]
+    prepare_concurrency: usize,
+
+    /// Seed for the dataset prefix (random if unset)
+    #[arg(long)]
+    seed: Option<u64>,
+
+    /// Also store the dataset manifest in the bucket, next to the objects
+    #[arg(long, default_value = "false")]
+    upload_manifest: bool,
+}
+
+#[derive(clap::Args, Debug)]
+struct RunArgs {
+    /// Dataset manifest written by `prepare`
+    #[arg(long)]
+    dataset: std::path::PathBuf,
+
+    #[command(flatten)]
+    run: Args,
+}
+
+#[derive(clap::Args, Debug)]
+struct CleanupArgs {
+    /// Dataset manifest written by `prepare`
+    #[arg(long)]
+    dataset: std::path::PathBuf,
+
+    #[command(flatten)]
+    backend: BackendArgs,
+
+    #[command(flatten)]
+    options: CleanupOptions,
+}
+
+#[derive(clap::Args, Debug)]
+struct GcArgs {
+    /// Remainder file written by a cleanup that timed out or had failures
+    remainder: std::path::PathBuf,
+
+    #[command(flatten)]
+    backend: BackendArgs,
+
+    /// Stop after this many seconds, leaving the rest in the remainder file
+    #[arg(long)]
+    cleanup_timeout: Option<u64>,
+
+    /// Delete requests in flight (each may be a batch delete)
+    #[arg(long, default_value = "16")]
+    cleanup_concurrency: usize,
+}
+
+#[derive(clap::Args, Debug, Clone, Serialize, Deserialize)]
+struct CleanupOptions {
+    /// Stop cleanup after this many seconds and record the keys left for `gc`
+    #[arg(long)]
+    cleanup_timeout: Option<u64>,
+
+    /// File recording the keys a timed-out or failed cleanup left behind
+    /// [default: qps-bench-remainder-<run id>.json, one per run]
+    #[arg(long)]
+    cleanup_remainder: Option<std::path::PathBuf>,
+
+    /// Delete requests in flight during cleanup (each may be a batch delete)
+    #[arg(long, default_value = "16")]
+    cleanup_concurrency: usize,
+}
+
+#[derive(clap::Args, Debug)]
+struct DoctorArgs {
+    #[command(flatten)]
+    backend: BackendArgs,
+
+    /// Key prefix the probe objects are written under
+    #[arg(long, default_value = "bench")]
+    prefix: String,
+}
+
+#[derive(clap::Args, Debug)]
+struct ValidateArgs {
+    /// TOML or YAML file with run settings, as passed to --config
+    #[arg(long)]
+    config: std::path::PathBuf,
+}
+
+#[derive(clap::Args, Debug)]
+struct InitArgs {
+    /// Where to write the config file
+    #[arg(long, default_value = "bench.toml")]
+    output: std::path::PathBuf,
+
+    /// Overwrite the config file if it already exists
+    #[arg(long, default_value = "false")]
+    force: bool,
+}
+
+#[derive(clap::Args, Debug)]
+struct ReportArgs {
+    /// Results JSON files, or run directories written by --results-dir
+    #[arg(required = true)]
+    files: Vec<std::path::PathBuf>,
+
+    /// Output format: text, md (Markdown tables of the config, percentiles
+    /// and errors, for pasting into a PR or wiki), html (one self-contained
+    /// page with charts covering all the files), or hgrm (the histograms of
+    /// results with the same label merged across the files; needs --hgrm-dir runs)
+    #[arg(long, default_value = "text")]
+    format: String,
+}
+
+#[derive(clap::Args, Debug)]
+struct TrendArgs {
+    /// SQLite file the runs were recorded in with --results-db
+    #[arg(long)]
+    results_db: std::path::PathBuf,
+
+    /// Scenario to chart, as recorded with --scenario
+    #[arg(long)]
+    scenario: String,
+
+    /// Only the most recent runs
+    #[arg(long, default_value = "30")]
+    limit: usize,
+
+    /// Runs averaged as the reference for the next one
+    #[arg(long, default_value = "3")]
+    window: usize,
+
+    /// How far (in percent) QPS may drop below, or P99 rise above, the moving
+    /// average before a run is flagged as a regression
+    #[arg(long, default_value = "10")]
+    threshold_pct: f64,
+
+    /// Exit with the SLA breach code when the latest run is a regression
+    #[arg(long, default_value = "false")]
+    fail_on_regression: bool,
+}
+
+#[derive(clap::Args, Debug)]
+struct CalibrateArgs {
+    /// S3 server access log files, or a CSV summary with `operation,count` and
+    /// an optional `avg_object_size_bytes` column (e.g. a CloudWatch export)
+    #[arg(required = true)]
+    files: Vec<std::path::PathBuf>,
+
+    /// Input format: auto (CSV if the file ends in .csv), access-log, csv
+    #[arg(long, default_value = "auto")]
+    format: String,
+
+    /// Total run time to split between the phases of the generated config
+    #[arg(long, default_value = "600")]
+    duration_seconds: u64,
+
+    /// Where to write the generated TOML config (stdout if unset)
+    #[arg(long)]
+    output: Option<std::path::PathBuf>,
+}
+
+#[derive(clap::Args, Debug)]
+struct GrafanaDashboardArgs {
+    /// Dashboard title
+    #[arg(long, default_value = "qps-bench")]
+    title: String,
+
+    /// Where to write the dashboard JSON (stdout if unset)
+    #[arg(long)]
+    output: Option<std::path::PathBuf>,
+}
+
+#[derive(clap::Args, Debug)]
+struct RerunArgs {
+    /// Bundle written by --export-bundle
+    bundle: std::path::PathBuf,
+
+    #[command(flatten)]
+    backend: BackendArgs,
+}
+
+#[derive(clap::Args, Debug, Clone, Serialize, Deserialize)]
+struct BackendArgs {
+    /// Service type (s3, oss, gcs, etc.)
+    #[arg(long, default_value = "s3")]
+    service: String,
+
+    /// S3 endpoint URL (required)
+    #[arg(long, default_value = "", hide_default_value = true)]
+    endpoint: String,
+
+    /// Region
+    #[arg(long, default_value = "us-east-1")]
+    region: String,
+
+    /// Bucket name (required)
+    #[arg(long, default_value = "", hide_default_value = true)]
+    bucket: String,
+
+    /// Access key ID (required); prefer the env var to keep it out of `ps` output
+    #[arg(long, env = "QPS_BENCH_ACCESS_KEY", hide_env_values = true, default_value = "", hide_default_value = true)]
+    #[serde(skip)]
+    access_key: String,
+
+    /// Secret access key (required); prefer the env var to keep it out of `ps` output
+    #[arg(long, env = "QPS_BENCH_SECRET_KEY", hide_env_values = true, default_value = "", hide_default_value = true)]
+    #[serde(skip)]
+    secret_key: String,
+
+    /// Session token (optional, for temporary credentials)
+    #[arg(long, env = "QPS_BENCH_SESSION_TOKEN", hide_env_values = true)]
+    #[serde(skip)]
+    session_token: Option<String>,
+
+    /// Force path-style addressing (for S3-compatible services); same as --addressing-style path
+    #[arg(long, default_value = "false")]
+    force_path_style: bool,
+
+    /// S3 addressing style: auto (virtual-hosted for AWS endpoints, path-style otherwise), path, virtual
+    #[arg(long, default_value = "auto")]
+    addressing_style: String,
+
+    /// Extra HTTP header sent with every request, as Name:Value (repeatable).
+    /// Added after request signing, so x-amz-* headers will be rejected by S3.
+    #[arg(long = "header", value_name = "NAME:VALUE")]
+    #[serde(skip)]
+    headers: Vec<String>,
+
+    /// HTTP(S) proxy URL for all requests; HTTP_PROXY/HTTPS_PROXY/NO_PROXY are honored when unset
+    #[arg(long)]
+    #[serde(skip)]
+    proxy: Option<String>,
+
+    /// PEM file with extra CA certificates to trust (e.g. a self-signed MinIO/Ceph cluster)
+    #[arg(long)]
+    ca_cert: Option<std::path::PathBuf>,
+
+    /// Skip TLS certificate verification (test clusters only). This and the
+    /// header, proxy and CA options need the `http-options` cargo feature
+    #[arg(long, default_value = "false")]
+    insecure_skip_tls_verify: bool,
+
+    /// Client issuing the requests: opendal, raw (hand-signed S3 requests without
+    /// OpenDAL, to measure its overhead against the bare protocol), or aws-sdk
+    /// (aws-sdk-s3; needs the `aws-sdk` cargo feature)
+    #[arg(long, default_value = "opendal")]
+    client: String,
+
+    /// Move object bodies (reads and writes) through this transfer engine instead
+    /// of the client, keeping stat, list and delete on the client, to compare a
+    /// high-throughput path against it: opendal-chunked (parallel ranged GETs
+    /// and multipart uploads). Needs the `transfer-engines` cargo feature.
+    #[arg(long)]
+    transfer_engine: Option<String>,
+
+    /// Part size of the transfer engine's ranged reads and multipart uploads
+    #[arg(long, default_value = "8388608")]
+    transfer_chunk_bytes: usize,
+
+    /// Parts of one object the transfer engine moves at once
+    #[arg(long, default_value = "8")]
+    transfer_concurrency: usize,
+
+    /// Answer repeated stats from a client-side metadata cache with this TTL,
+    /// reporting hit rate and uncached latency (cache experiment; off by default)
+    #[arg(long)]
+    stat_cache_ttl_ms: Option<u64>,
+
+    /// Retry failed requests up to this many times with OpenDAL's RetryLayer
+    /// (opendal client only; 0 = no retries). Retried ops are reported.
+    #[arg(long, default_value = "0")]
+    retries: usize,
+
+    /// Delay before the first retry, doubling for each later one
+    #[arg(long, default_value = "100")]
+    retry_backoff_ms: u64,
+
+    /// Randomize retry delays so throttled clients don't retry in lockstep
+    #[arg(long, default_value = "false")]
+    retry_jitter: bool,
+
+    /// OpenDAL LoggingLayer on the operator: on, or off to keep its overhead off the hot path
+    #[arg(long, default_value = "on")]
+    log_layer: String,
+
+    /// Trace this fraction of ops (e.g. 0.001) through OpenDAL's TracingLayer,
+    /// retries included, and export the spans to --otlp-endpoint (needs the
+    /// `otlp-traces` cargo feature; opendal client only)
+    #[arg(long)]
+    trace_sample_ratio: Option<f64>,
+
+    /// Cap requests at this many per second, whatever the concurrency, to measure
+    /// latency at a fixed rate below saturation. Time spent waiting for the
+    /// limiter is excluded from latencies.
+    #[arg(long)]
+    operator_rate_limit: Option<f64>,
+}
+
+impl BackendArgs {
+    /// Checks the settings clap cannot enforce because they are not required
+    /// for every subcommand.
+    fn validate(&self) -> Result<()> {
+        for (flag, value) in [
+            ("--endpoint", &self.endpoint),
+            ("--bucket", &self.bucket),
+            ("--access-key (or QPS_BENCH_ACCESS_KEY)", &self.access_key),
+            ("--secret-key (or QPS_BENCH_SECRET_KEY)", &self.secret_key),
+        ] {
+            if value.is_empty() {
+                return Err(RunFailure::new(FailureCategory::Config, format!("{} is required", flag)).into());
+            }
+        }
+        if self.retries > 0 && self.client != "opendal" {
+            return Err(RunFailure::new(FailureCategory::Config, "--retries uses OpenDAL's RetryLayer and needs --client opendal").into());
+        }
+        if self.trace_sample_ratio.is_some() && self.client != "opendal" {
+            return Err(RunFailure::new(FailureCategory::Config, "--trace-sample-ratio uses OpenDAL's TracingLayer and needs --client opendal").into());
+        }
+        Ok(())
+    }
+}
+
+#[derive(clap::Args, Debug, Clone, Serialize, Deserialize)]
+struct Args {
+    /// TOML or YAML file with run settings (flag names in snake_case, optionally
+    /// grouped in tables); flags given on the command line take precedence
+    #[arg(long)]
+    #[serde(skip)]
+    config: Option<std::path::PathBuf>,
+
+    /// Workload preset: small-object-metadata, throughput, mixed-oltp. Sets mode,
+    /// objects, object size, concurrency and duration unless given explicitly
+    #[arg(long)]
+    preset: Option<String>,
+
+    #[command(flatten)]
+    #[serde(flatten)]
+    backend: BackendArgs,
+
+    /// Key prefix
+    #[arg(long, default_value = "bench")]
+    prefix: String,
+
+    /// Number of objects in dataset
+    #[arg(long, default_value = "10000")]
+    objects: usize,
+
+    /// Object size in bytes
+    #[arg(long, default_value = "1024")]
+    object_size_bytes: usize,
+
+    /// Random write payloads generated before the run and reused round-robin;
+    /// 0 generates a fresh payload for every write
+    #[arg(long, default_value = "16")]
+    payload_pool: usize,
+
+    /// Uploads in flight while creating the dataset
+    #[arg(long, default_value = "32")]
+    prepare_concurrency: usize,
+
+    /// Concurrency level
+    #[arg(long, default_value = "64")]
+    concurrency: usize,
+
+    /// Lower the concurrency when object size × concurrency would not fit in half
+    /// the available memory or would take over 10s to cross the NIC, instead of
+    /// only warning. The cap and its reason are recorded in the results.
+    #[arg(long, default_value = "false")]
+    auto_cap_concurrency: bool,
+
+    /// Duration in seconds; 0 runs until stopped, like --run-until-signal
+    #[arg(long, default_value = "60")]
+    duration_seconds: u64,
+
+    /// Run until SIGINT/SIGTERM (or `stop` on --control-socket) instead of for
+    /// --duration-seconds, reporting progress as it goes. Stopping is
+    /// then the normal end of the run, not an interruption
+    #[arg(long, default_value = "false")]
+    run_until_signal: bool,
+
+    /// Hard ceiling in seconds on the whole invocation: dataset creation, the
+    /// benchmark and cleanup. Creation and the benchmark stop (as on a signal)
+    /// once 90% of it has passed, and cleanup gets the rest, leaving what it
+    /// can't delete in time to `gc`. Whatever still runs at the limit is
+    /// abandoned: a partial report is written and the exit code is 8
+    #[arg(long, value_name = "SECONDS")]
+    max_run_time: Option<u64>,
+
+    /// Listen on this Unix socket for control commands, one per line: `stop`
+    /// ends the run as a signal would, `status` replies with live counters
+    #[arg(long, value_name = "PATH")]
+    #[serde(skip)]
+    control_socket: Option<std::path::PathBuf>,
+
+    /// Benchmark mode: stat, stat_storm, read_small, write_small, scatter_write, commit, delete, list, dual_write, read_compare,
+    /// read_overwrite (experiment: reads race overwrites of the same keys), read_write (combined)
+    #[arg(long, default_value = "stat")]
+    mode: String,
+
+    /// Objects per logical op: scatter_write writes this many in parallel (parts + manifest),
+    /// commit writes this many parts in parallel and then a manifest
+    #[arg(long, default_value = "4")]
+    fanout: usize,
+
+    /// read_overwrite: dataset keys that are read while being overwritten
+    #[arg(long, default_value = "16")]
+    overwrite_keys: usize,
+
+    /// read_overwrite: background writers overwriting those keys, on top of --concurrency readers
+    #[arg(long, default_value = "4")]
+    overwriters: usize,
+
+    /// stat and read_small: a background task deletes and recreates this
+    /// percentage of the dataset per minute, modelling a bucket with object
+    /// turnover. Reads that land between the delete and the recreate see
+    /// NotFound (after --not-found-retry-ms), and are counted apart
+    #[arg(long, value_name = "PCT")]
+    churn_pct_per_minute: Option<f64>,
+
+    /// Keys hammered by stat_storm (1-10); latency is broken down per key
+    #[arg(long, default_value = "4")]
+    storm_keys: usize,
+
+    /// Stop each list op after this many entries, so listing a huge prefix by
+    /// mistake can't exhaust memory; cut-short lists are reported
+    #[arg(long, default_value = "100000")]
+    list_max_entries: usize,
+
+    /// dual_write/read_compare: endpoint of the second backend, e.g. the cluster
+    /// being migrated to. Other --mirror-* settings default to the primary backend's.
+    #[arg(long)]
+    mirror_endpoint: Option<String>,
+
+    /// dual_write/read_compare: bucket on the second backend
+    #[arg(long)]
+    mirror_bucket: Option<String>,
+
+    /// dual_write/read_compare: region of the second backend
+    #[arg(long)]
+    mirror_region: Option<String>,
+
+    /// dual_write/read_compare: service type of the second backend
+    #[arg(long)]
+    mirror_service: Option<String>,
+
+    /// dual_write/read_compare: access key ID for the second backend
+    #[arg(long, env = "QPS_BENCH_MIRROR_ACCESS_KEY", hide_env_values = true)]
+    #[serde(skip)]
+    mirror_access_key: Option<String>,
+
+    /// dual_write/read_compare: secret access key for the second backend
+    #[arg(long, env = "QPS_BENCH_MIRROR_SECRET_KEY", hide_env_values = true)]
+    #[serde(skip)]
+    mirror_secret_key: Option<String>,
+
+    /// dual_write: read every object back from both backends and compare it with
+    /// what was written (op latency then includes the read-backs)
+    #[arg(long, default_value = "false")]
+    dual_write_verify: bool,
+
+    /// read_small: read a random range of this many bytes of each object instead
+    /// of the whole object (opendal client only)
+    #[arg(long)]
+    range_read_bytes: Option<usize>,
+
+    /// read_small: check that every read (or range) returns the expected slice
+    /// of the dataset's seeded content, reporting the offsets of mismatches.
+    /// Needs a dataset created by this version (opendal client only).
+    #[arg(long, default_value = "false")]
+    verify_reads: bool,
+
+    /// Run the mode once per concurrency level (comma-separated) against the same dataset
+    #[arg(long, value_delimiter = ',')]
+    sweep_concurrency: Vec<usize>,
+
+    /// Latency percentiles reported for every result (comma-separated), next
+    /// to the fixed P50/P95/P99, min, max and standard deviation
+    #[arg(long, value_delimiter = ',', default_values_t = [50.0, 90.0, 99.0, 99.9, 99.99])]
+    percentiles: Vec<f64>,
+
+    /// Latency goal in ms; with --sweep-concurrency, reports the highest concurrency/QPS meeting it
+    #[arg(long)]
+    latency_goal_ms: Option<f64>,
+
+    /// Percentile the latency goal applies to
+    #[arg(long, default_value = "99")]
+    latency_goal_percentile: f64,
+
+    /// Cleanup created objects after benchmark (datasets from `prepare` are
+    /// kept); --cleanup=false keeps them too
+    #[arg(long, default_value_t = true, num_args = 0..=1, default_missing_value = "true", action = clap::ArgAction::Set)]
+    cleanup: bool,
+
+    #[command(flatten)]
+    #[serde(flatten)]
+    cleanup_options: CleanupOptions,
+
+    /// Retry stat/read ops that fail with NotFound for up to this many ms before
+    /// counting a failure, modeling consumers that tolerate eventual visibility (0 = off)
+    #[arg(long, default_value = "0")]
+    not_found_retry_ms: u64,
+
+    /// CSV of `key,weight` rows (e.g. per-key request counts from production)
+    /// driving stat/read key selection instead of round-robin. Rows are ranks:
+    /// the Nth row's weight goes to the Nth dataset object.
+    #[arg(long)]
+    key_popularity: Option<std::path::PathBuf>,
+
+    /// Fail any operation still running after this many ms, freeing its
+    /// concurrency slot; timeouts are counted apart from other errors
+    #[arg(long)]
+    op_timeout_ms: Option<u64>,
+
+    /// Start ops on a fixed schedule of this many per second (still capped at
+    /// --concurrency in flight) and also report latencies measured from each
+    /// op's intended start, which count a stall against every op queued behind
+    /// it instead of hiding it (coordinated omission). read_write runs its read
+    /// and write halves one after the other, each at this full rate
+    #[arg(long)]
+    target_rate: Option<f64>,
+
+    /// Seed for the run's random choices (random if unset); recorded in bundles
+    #[arg(long)]
+    seed: Option<u64>,
+
+    /// Track ops and latency per worker (each of the --concurrency op loops)
+    /// and flag stragglers: workers whose P99 is 5x the median worker's, as a
+    /// bad connection or NIC queue would show
+    #[arg(long, default_value = "false")]
+    worker_stats: bool,
+
+    /// Visit dataset keys in creation order instead of shuffling them with
+    /// --seed. Creation order tends to follow the backend's placement, which
+    /// can skew reads toward a few partitions
+    #[arg(long, default_value = "false")]
+    no_shuffle_keys: bool,
+
+    /// Dataset manifest file: reused (skipping dataset creation) when it exists,
+    /// otherwise written after creating the dataset, which is then kept for reuse
+    #[arg(long)]
+    #[serde(skip)]
+    manifest: Option<std::path::PathBuf>,
+
+    /// Also store the dataset manifest in the bucket, next to the objects
+    #[arg(long, default_value = "false")]
+    #[serde(skip)]
+    upload_manifest: bool,
+
+    /// Write a reproducibility bundle (config, seed, manifest, results, environment) to this .tar.zst
+    #[arg(long)]
+    #[serde(skip)]
+    export_bundle: Option<std::path::PathBuf>,
+
+    /// Fail the run (and alert the webhook) when P99 latency exceeds this many ms
+    #[arg(long)]
+    sla_p99_ms: Option<f64>,
+
+    /// Fail the run (and alert the webhook) when the error rate exceeds this percentage
+    #[arg(long)]
+    sla_max_error_pct: Option<f64>,
+
+    /// Print GitHub Actions annotations (a notice per result, an error per SLA
+    /// breach) and append a results table to the job summary ($GITHUB_STEP_SUMMARY)
+    #[arg(long, default_value = "false")]
+    #[serde(skip)]
+    gha: bool,
+
+    /// POST the final results JSON, or an alert on abort/SLA breach, to this URL
+    /// (e.g. a Slack incoming webhook; the message is in `text`; needs the
+    /// `http-sinks` cargo feature)
+    #[arg(long, alias = "notify-url")]
+    #[serde(skip)]
+    webhook_url: Option<String>,
+
+    /// What the completion notification carries: full (the results JSON) or
+    /// summary (QPS, P99 and errors per result, also listed in `text`)
+    #[arg(long, alias = "notify-format", default_value = "full")]
+    #[serde(skip)]
+    webhook_format: String,
+
+    /// Serve live Prometheus metrics (ops, errors, in-flight ops, latency
+    /// histogram) at http://<addr>/metrics during the run, e.g. 0.0.0.0:9090
+    #[arg(long, value_name = "ADDR")]
+    #[serde(skip)]
+    prometheus_listen: Option<String>,
+
+    /// Push the final results to this Prometheus Pushgateway (e.g.
+    /// http://pushgateway:9091; needs the `http-sinks` cargo feature)
+    #[arg(long)]
+    #[serde(skip)]
+    pushgateway_url: Option<String>,
+
+    /// Pushgateway job label
+    #[arg(long, default_value = "qps-bench")]
+    #[serde(skip)]
+    pushgateway_job: String,
+
+    /// Pushgateway instance label (the hostname if unset)
+    #[arg(long)]
+    #[serde(skip)]
+    pushgateway_instance: Option<String>,
+
+    /// Also push the live metrics every this many seconds during the run
+    #[arg(long)]
+    #[serde(skip)]
+    pushgateway_interval_seconds: Option<u64>,
+
+    /// Export live metrics to this OpenTelemetry collector over OTLP/HTTP (JSON),
+    /// e.g. http://otel-collector:4318 (needs the `otlp-metrics` cargo feature)
+    #[arg(long)]
+    #[serde(skip)]
+    otlp_endpoint: Option<String>,
+
+    /// Seconds between OTLP exports
+    #[arg(long, default_value = "10")]
+    #[serde(skip)]
+    otlp_interval_seconds: u64,
+
+    /// Send op counters, in-flight ops and op timings to this StatsD/DogStatsD
+    /// agent over UDP every second, tagged with mode and backend, e.g. 127.0.0.1:8125
+    #[arg(long, value_name = "ADDR")]
+    #[serde(skip)]
+    statsd_addr: Option<String>,
+
+    /// Publish one compact JSON record per completed op (timestamp, op, latency,
+    /// status) to nats://HOST:PORT/SUBJECT or kafka://BROKER:PORT/TOPIC (Kafka
+    /// needs the `kafka` cargo feature)
+    #[arg(long, value_name = "URL")]
+    #[serde(skip)]
+    event_sink: Option<String>,
+
+    /// Events buffered for --event-sink before new ones are dropped (and counted)
+    #[arg(long, default_value = "65536")]
+    #[serde(skip)]
+    event_sink_buffer: usize,
+
+    /// Write one JSON line per request of every benchmark op (start time, HTTP
+    /// method, key, latency, bytes, result) to this file, for custom percentiles
+    /// and correlation with server-side logs
+    #[arg(long, value_name = "FILE")]
+    #[serde(skip)]
+    op_log: Option<std::path::PathBuf>,
+
+    /// Print full diagnostics of the first K failed requests (the error with
+    /// its context, which holds the response status, headers and body where
+    /// the service returned them, plus timing and retries), then stop the run.
+    /// Shortens the loop when standing up a new S3-compatible endpoint
+    #[arg(long, value_name = "K")]
+    #[serde(skip)]
+    debug_errors: Option<usize>,
+
+    /// Write per-interval and final measurements to this InfluxDB (v2 write API),
+    /// e.g. http://influxdb:8086 (needs the `http-sinks` cargo feature)
+    #[arg(long)]
+    #[serde(skip)]
+    influx_url: Option<String>,
+
+    /// InfluxDB bucket to write to
+    #[arg(long, default_value = "qps-bench")]
+    #[serde(skip)]
+    influx_bucket: String,
+
+    /// InfluxDB organization
+    #[arg(long, default_value = "")]
+    #[serde(skip)]
+    influx_org: String,
+
+    /// InfluxDB API token
+    #[arg(long, env = "QPS_BENCH_INFLUX_TOKEN", hide_env_values = true)]
+    #[serde(skip)]
+    influx_token: Option<String>,
+
+    /// Also (or instead) append the measurements in line protocol to this file
+    #[arg(long)]
+    #[serde(skip)]
+    influx_file: Option<std::path::PathBuf>,
+
+    /// Label attached to every result, as key=value (repeatable). Pod and node
+    /// metadata is added automatically when running in Kubernetes
+    #[arg(long = "label", value_name = "KEY=VALUE")]
+    labels: Vec<String>,
+
+    /// Write the final results JSON to this file, apart from the progress output
+    #[arg(long, value_name = "FILE")]
+    #[serde(skip)]
+    output: Option<std::path::PathBuf>,
+
+    /// Append the results to --output (as one JSON line, or CSV rows without a
+    /// repeated header) instead of replacing the file, so repeated runs build up a log
+    #[arg(long, default_value = "false", requires = "output")]
+    #[serde(skip)]
+    append: bool,
+
+    /// Format of --output: json, or csv with one flat row per result (mode,
+    /// concurrency, QPS, latency percentiles, errors, bytes/s), which is also
+    /// printed after the run
+    #[arg(long, default_value = "json")]
+    #[serde(skip)]
+    format: String,
+
+    /// Write a self-contained HTML page with the results and charts (latency
+    /// percentiles, QPS over time, errors over time) to this file
+    #[arg(long, value_name = "FILE")]
+    #[serde(skip)]
+    html_report: Option<std::path::PathBuf>,
+
+    /// Record QPS, P99 and the full results of every run in this SQLite file
+    /// under --scenario, for `qps-bench trend`
+    #[arg(long, value_name = "FILE")]
+    #[serde(skip)]
+    results_db: Option<std::path::PathBuf>,
+
+    /// Name the run is recorded under in --results-db, so only runs of the
+    /// same workload and backend are compared [default: the mode and a hash of
+    /// the workload and backend settings]
+    #[arg(long)]
+    scenario: Option<String>,
+
+    /// Also send the results to this destination, as KIND=TARGET (repeatable):
+    /// file=PATH (like --output), prometheus=URL (a Pushgateway, like
+    /// --pushgateway-url), webhook=URL (like --webhook-url), sqlite=PATH (like
+    /// --results-db), or just `stdout` for the CSV rows
+    #[arg(long = "sink", value_name = "KIND[=TARGET]")]
+    #[serde(skip)]
+    sinks: Vec<String>,
+
+    /// Write each result's full latency histogram to DIR/<result>.hgrm in
+    /// HdrHistogram's percentile distribution format, and keep it in the
+    /// results JSON so `report --format hgrm` can merge runs
+    #[arg(long, value_name = "DIR")]
+    #[serde(skip)]
+    hgrm_dir: Option<std::path::PathBuf>,
+
+    /// Record QPS, error rate, peak in-flight ops and latency per interval of
+    /// this many seconds, as the `timeseries` array of each result
+    #[arg(long, value_name = "SECONDS")]
+    timeseries_interval_seconds: Option<u64>,
+
+    /// Write the time series as CSV to this file (1-second intervals unless
+    /// --timeseries-interval-seconds is set)
+    #[arg(long, value_name = "FILE")]
+    #[serde(skip)]
+    timeseries_csv: Option<std::path::PathBuf>,
+
+    /// Print newline-delimited JSON events to stdout as the run goes, for
+    /// wrapper tooling: `phase` when a mode starts, `interval` progress (every
+    /// --timeseries-interval-seconds, default 1), `warning`, and a `result`
+    /// per result and `finished` at the end. Every event line is a JSON object
+    /// with an `event` field; logs and the printed results go to stderr instead
+    #[arg(long, default_value = "false")]
+    #[serde(skip)]
+    stream_events: bool,
+
+    /// Show a live dashboard instead of the log while the benchmark runs:
+    /// current QPS, rolling P50/P99, error counts and a QPS sparkline. Press q
+    /// (or Ctrl-C) to stop early; the summary prints when the dashboard closes.
+    /// Needs the `tui` cargo feature and a terminal on stdout
+    #[arg(long, default_value = "false")]
+    #[serde(skip)]
+    tui: bool,
+
+    /// Print a status line to stderr every this many seconds while the
+    /// benchmark runs: ops so far, current QPS, rolling P99, errors and
+    /// in-flight ops. 0 turns it off
+    #[arg(long, default_value = "10")]
+    #[serde(skip)]
+    progress_interval_seconds: u64,
+
+    /// Also write each run into its own directory under this one (e.g. a mounted
+    /// volume): <mode>/<UTC timestamp>_[<namespace>_]<pod or host>[_<label values>]/
+    /// with results.json, config.json and, when recorded, intervals.csv
+    #[arg(long)]
+    #[serde(skip)]
+    results_dir: Option<std::path::PathBuf>,
+
+    /// Also upload the run's results files to this bucket on the benchmarked
+    /// backend (same endpoint and credentials), under --results-prefix and the
+    /// same <mode>/<timestamp>_... path as --results-dir
+    #[arg(long)]
+    #[serde(skip)]
+    results_bucket: Option<String>,
+
+    /// Key prefix for uploads to --results-bucket
+    #[arg(long, default_value = "qps-bench-results")]
+    #[serde(skip)]
+    results_prefix: String,
+
+    /// Sample the benchmark's own CPU usage during the run and write a pprof
+    /// profile here (relative paths go into the --results-dir run directory), to check
+    /// whether the client rather than the backend limited QPS (needs the `pprof`
+    /// cargo feature)
+    #[arg(long, value_name = "FILE")]
+    #[serde(skip)]
+    pprof: Option<std::path::PathBuf>,
+
+    /// Chained phases from the config file's `[[phases]]` tables, run back to
+    /// back on a shared dataset in place of --mode
+    #[arg(skip)]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    phases: Vec<PhaseSpec>,
+
+    /// Per-operation SLA targets from the config file's `[[sla]]` tables,
+    /// checked alongside --sla-p99-ms and --sla-max-error-pct
+    #[arg(skip)]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    sla: Vec<SlaTarget>,
+
+    /// Set by --auto-cap-concurrency when it lowered the concurrency
+    #[arg(skip)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    concurrency_cap: Option<ConcurrencyCap>,
+}
+
+impl Args {
+    /// Whether the run lasts until stopped rather than for --duration-seconds.
+    fn until_stopped(&self) -> bool {
+        self.run_until_signal || self.duration_seconds == 0
+    }
+}
+
+/// Why --auto-cap-concurrency lowered the requested concurrency.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ConcurrencyCap {
+    requested: usize,
+    capped_to: usize,
+    reason: String,
+}
+
+/// Thresholds for one operation type. `op` is a mode (stat, read_small, ...)
+/// or an HTTP method of the per-request breakdown (HEAD, GET, PUT, DELETE,
+/// LIST, POST), so a read_write or phased run can hold each op to its own limits.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SlaTarget {
+    op: String,
+    p50_ms: Option<f64>,
+    p95_ms: Option<f64>,
+    p99_ms: Option<f64>,
+    max_error_pct: Option<f64>,
+}
+
+/// HTTP methods in the per-request breakdown that `[[sla]]` targets can name.
+const SLA_VERBS: &[&str] = &["HEAD", "GET", "PUT", "DELETE", "LIST", "POST"];
+
+/// One step of a chained run. Unset fields fall back to the run's flags.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PhaseSpec {
+    mode: String,
+    duration_seconds: Option<u64>,
+    concurrency: Option<usize>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct BenchmarkResult {
+    /// Layout version of this document (RESULT_SCHEMA_VERSION when written;
+    /// 0 for results from before it was recorded)
+    #[serde(default)]
+    schema_version: u32,
+    /// Which run produced the result; shared by every result of the run
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    run: Option<RunMetadata>,
+    mode: String,
+    concurrency: usize,
+    duration_seconds: u64,
+    ok_ops: u64,
+    err_ops: u64,
+    /// Failed ops that hit --op-timeout-ms, not included in `err_ops`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    timeout_ops: Option<u64>,
+    /// Set when --retries is enabled
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    retries: Option<RetryStats>,
+    qps: f64,
+    latency_us_p50: u64,
+    latency_us_p95: u64,
+    latency_us_p99: u64,
+    latency_us_mean: u64,
+    #[serde(default)]
+    latency_us_min: u64,
+    #[serde(default)]
+    latency_us_max: u64,
+    #[serde(default)]
+    latency_us_stddev: f64,
+    /// Latencies at --percentiles
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    percentiles: Vec<PercentileLatency>,
+    /// Op counts in power-of-two latency buckets, for the distribution in the summary
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    latency_distribution: Vec<LatencyBucket>,
+    /// Latencies from intended start, with --target-rate
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    corrected_latency: Option<CorrectedLatency>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    queueing: Option<QueueingDelay>,
+    /// Process startup to first successful request; the same in every result of a run
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    startup: Option<StartupTiming>,
+    /// Every recorded latency bucket as (latency_us, count), with --hgrm-dir,
+    /// so the full distribution can be rebuilt and merged across runs
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    histogram: Vec<(u64, u64)>,
+    /// Per-phase latencies for multi-step modes (e.g. commit); the top-level
+    /// latencies are end-to-end
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    phases: BTreeMap<String, PhaseLatency>,
+    /// Per-request metrics keyed by HTTP method (HEAD, GET, PUT, DELETE, LIST),
+    /// for correlating with server-side access logs
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    verbs: BTreeMap<String, VerbLatency>,
+    /// Per-key latency (stat_storm)
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    keys: BTreeMap<String, VerbLatency>,
+    /// List ops that stopped at --list-max-entries
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    truncated_lists: Option<u64>,
+    /// When the run started, for placing `intervals` in time
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    started_at_unix_ms: Option<u64>,
+    /// How much of --op-timeout-ms successful ops used
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    timeout_budget: Option<TimeoutBudget>,
+    /// Per-backend outcomes of dual_write
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    dual_write: Option<DualWriteReport>,
+    /// Per-backend latencies and divergences of read_compare
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    read_compare: Option<ReadCompareReport>,
+    /// Content checks of --verify-reads
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    read_verify: Option<ReadVerifyReport>,
+    /// Read/overwrite races of read_overwrite
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    overwrite: Option<OverwriteReport>,
+    /// Object turnover with --churn-pct-per-minute
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    churn: Option<ChurnReport>,
+    /// Per-interval series (read_write halves, or with --timeseries-interval-seconds),
+    /// to show warm-up, throttling and interference over time
+    #[serde(rename = "timeseries", alias = "intervals", default, skip_serializing_if = "Vec::is_empty")]
+    intervals: Vec<IntervalStat>,
+    /// Set when the backend throttled any op
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    throttling: Option<ThrottleReport>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    qps_stability: Option<QpsStability>,
+    /// Key-sharing policy between the halves of read_write
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    key_isolation: Option<KeyIsolation>,
+    /// Per-worker breakdown, with --worker-stats
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    workers: Option<WorkerReport>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    stat_cache: Option<StatCacheReport>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    payload: Option<PayloadReport>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    memory: Option<MemoryReport>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    concurrency_cap: Option<ConcurrencyCap>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    nic: Option<NicReport>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    labels: BTreeMap<String, String>,
+    /// Wall time from the first op's start until the last in-flight op
+    /// finished, which qps is computed over; duration_seconds is the nominal one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    effective_duration_seconds: Option<f64>,
+    /// Set when SIGINT/SIGTERM cut the run short; qps then covers the time actually run
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    interrupted: bool,
+    backend: BackendInfo,
+}
+
+impl BenchmarkResult {
+    fn from_run(args: &Args, mode: &str, concurrency: usize, ok_ops: u64, err_ops: u64, histogram: &Histogram<u64>, state: &BenchmarkState) -> Self {
+        let interrupted_after = state.take_interrupted_after();
+        let elapsed = state.take_elapsed();
+        let qps = ok_ops as f64 / elapsed.or(interrupted_after).map_or(args.duration_seconds as f64, |d| d.as_secs_f64());
+        let run_time = interrupted_after.unwrap_or(Duration::from_secs(args.duration_seconds));
+        let seconds = state.take_second_counts();
+        let started_at_unix_ms = state.take_started_at_unix_ms();
+        // A run until stopped lasted as long as it took to stop it
+        let duration_seconds = match interrupted_after {
+            Some(elapsed) if args.until_stopped() => elapsed.as_secs(),
+            _ => args.duration_seconds,
+        };
+        BenchmarkResult {
+            schema_version: RESULT_SCHEMA_VERSION,
+            run: Some(run_metadata().clone()),
+            mode: mode.to_string(),
+            concurrency,
+            duration_seconds,
+            ok_ops,
+            err_ops,
+            timeout_ops: state.take_timeouts(),
+            retries: (args.backend.retries > 0).then(|| state.take_retry_stats()),
+            qps,
+            latency_us_p50: histogram.value_at_quantile(0.5),
+            latency_us_p95: histogram.value_at_quantile(0.95),
+            latency_us_p99: histogram.value_at_quantile(0.99),
+            latency_us_mean: histogram.mean() as u64,
+            latency_us_min: histogram.min(),
+            latency_us_max: histogram.max(),
+            latency_us_stddev: histogram.stdev(),
+            percentiles: PercentileLatency::from_histogram(histogram, &args.percentiles),
+            latency_distribution: LatencyBucket::from_histogram(histogram),
+            corrected_latency: state.take_corrected_latency(),
+            queueing: state.take_queueing_delay(),
+            startup: startup_timing(),
+            histogram: if args.hgrm_dir.is_some() { recorded_buckets(histogram) } else { Vec::new() },
+            phases: state.take_phase_latencies(),
+            verbs: state.take_verb_latencies(),
+            keys: state.take_key_latencies(),
+            truncated_lists: Some(state.truncated_lists.swap(0, std::sync::atomic::Ordering::Relaxed)).filter(|count| *count > 0),
+            started_at_unix_ms,
+            timeout_budget: state.op_timeout.map(|timeout| TimeoutBudget::from_histogram(histogram, timeout)),
+            dual_write: state.dual_write.as_ref().map(|target| target.take_report()),
+            read_compare: state.read_compare.as_ref().map(|target| target.take_report()),
+            read_verify: state.read_check.as_ref().and_then(|check| check.take_report()),
+            overwrite: state.overwrite.as_ref().map(|race| race.take_report()),
+            churn: state.churn.as_ref().map(|churn| churn.take_report()),
+            intervals: state.take_intervals(),
+            throttling: ThrottleReport::from_seconds(&seconds, state.take_throttle_reasons(), run_time, started_at_unix_ms),
+            qps_stability: QpsStability::from_seconds(&seconds, run_time),
+            key_isolation: state.overwrite.is_some().then_some(KeyIsolation::Overwrite),
+            workers: state.take_worker_report(),
+            stat_cache: state.client.take_stat_cache_report(),
+            payload: state.payloads.take_report(interrupted_after.unwrap_or(Duration::from_secs(args.duration_seconds)), concurrency),
+            memory: state.take_memory_report(ok_ops + err_ops),
+            concurrency_cap: args.concurrency_cap.clone(),
+            nic: NicReport::measure(&args.backend.endpoint, throughput_bytes_per_second(args, mode, qps)),
+            labels: result_labels(args),
+            effective_duration_seconds: elapsed.map(|elapsed| elapsed.as_secs_f64()),
+            interrupted: interrupted_after.is_some() && !args.until_stopped(),
+            backend: backend_info(&args.backend),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct PhasedResult {
+    phase_results: Vec<BenchmarkResult>,
+    /// Phases not run because the backend lacks an operation they need
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    skipped_phases: Vec<SkippedPhase>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct SkippedPhase {
+    /// 1-based, as printed
+    phase: usize,
+    mode: String,
+    unsupported: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct SweepResult {
+    mode: String,
+    results: Vec<BenchmarkResult>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    latency_goal: Option<LatencyGoalReport>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct LatencyGoalReport {
+    percentile: f64,
+    goal_ms: f64,
+    /// Highest concurrency meeting the goal, interpolated between sweep points
+    max_concurrency: Option<f64>,
+    /// QPS interpolated at `max_concurrency`
+    max_qps: Option<f64>,
+    /// The goal was still met at the highest swept concurrency, so the real limit is higher
+    bounded_by_sweep: bool,
+}
+
+struct SweepPoint {
+    concurrency: usize,
+    qps: f64,
+    latency_us: u64,
+}
+
+/// Latencies of successful ops measured from when --target-rate meant them
+/// to start. Well above the service-time percentiles, the backend stalled
+/// (or the client fell behind the schedule) and the raw numbers hide it.
+#[derive(Debug, Serialize, Deserialize)]
+struct CorrectedLatency {
+    target_rate: f64,
+    latency: PhaseLatency,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct PercentileLatency {
+    percentile: f64,
+    latency_us: u64,
+}
+
+impl PercentileLatency {
+    fn from_histogram(histogram: &Histogram<u64>, percentiles: &[f64]) -> Vec<Self> {
+        percentiles
+            .iter()
+            .map(|percentile| PercentileLatency { percentile: *percentile, latency_us: histogram.value_at_quantile(percentile / 100.0) })
+            .collect()
+    }
+}
+
+/// Ops whose latency fell below `upper_us` and at or above half of it.
+#[derive(Debug, Serialize, Deserialize)]
+struct LatencyBucket {
+    upper_us: u64,
+    count: u64,
+}
+
+impl LatencyBucket {
+    /// Buckets from the fastest to the slowest op, empty ones in between kept
+    /// so gaps between modes show.
+    fn from_histogram(histogram: &Histogram<u64>) -> Vec<Self> {
+        let mut counts = [0u64; 64];
+        for value in histogram.iter_recorded() {
+            let bucket = (64 - value.value_iterated_to().leading_zeros() as usize).min(63);
+            counts[bucket] += value.count_at_value();
+        }
+        let (Some(first), Some(last)) = (counts.iter().position(|&count| count > 0), counts.iter().rposition(|&count| count > 0)) else {
+            return Vec::new();
+        };
+        (first..=last).map(|bucket| LatencyBucket { upper_us: 1 << bucket, count: counts[bucket] }).collect()
+    }
+}
+
+/// Formats a latency bound compactly (μs, ms or s).
+fn format_latency_bound(us: u64) -> String {
+    if us < 1_000 {
+        format!("{}μs", us)
+    } else if us < 1_000_000 {
+        format!("{:.1}ms", us as f64 / 1_000.0)
+    } else {
+        format!("{:.1}s", us as f64 / 1_000_000.0)
+    }
+}
+
+/// Renders the buckets as one bar per line, scaled to the fullest bucket.
+fn render_latency_distribution(buckets: &[LatencyBucket]) -> String {
+    const BAR_WIDTH: usize = 40;
+    let total: u64 = buckets.iter().map(|bucket| bucket.count).sum();
+    let peak = buckets.iter().map(|bucket| bucket.count).max().unwrap_or(0);
+    let mut out = String::new();
+    for bucket in buckets {
+        let bar = if bucket.count == 0 { 0 } else { ((bucket.count as f64 / peak as f64 * BAR_WIDTH as f64).round() as usize).max(1) };
+        let _ = writeln!(
+            out,
+            "  {:>9} - {:<9} {:<width$} {:>10} ({:5.1}%)",
+            format_latency_bound(bucket.upper_us / 2),
+            format_latency_bound(bucket.upper_us),
+            "█".repeat(bar),
+            bucket.count,
+            bucket.count as f64 / total as f64 * 100.0,
+            width = BAR_WIDTH,
+        );
+    }
+    out
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct PhaseLatency {
+    ok_ops: u64,
+    latency_us_p50: u64,
+    latency_us_p95: u64,
+    latency_us_p99: u64,
+    latency_us_mean: u64,
+}
+
+impl PhaseLatency {
+    fn from_histogram(hist: &Histogram<u64>) -> Self {
+        PhaseLatency {
+            ok_ops: hist.len(),
+            latency_us_p50: hist.value_at_quantile(0.5),
+            latency_us_p95: hist.value_at_quantile(0.95),
+            latency_us_p99: hist.value_at_quantile(0.99),
+            latency_us_mean: hist.mean() as u64,
+        }
+    }
+}
+
+/// Client-side stat cache effectiveness (--stat-cache-ttl-ms). Compare the
+/// result's latencies with the miss latencies to see what the cache saves.
+#[derive(Debug, Serialize, Deserialize)]
+struct StatCacheReport {
+    ttl_ms: u64,
+    hits: u64,
+    misses: u64,
+    hit_rate: f64,
+    miss_latency_us_p50: u64,
+    miss_latency_us_p99: u64,
+}
+
+/// Where in the --op-timeout-ms budget successful ops completed: a large share
+/// finishing late in the budget means the timeout is close to cutting off
+/// healthy requests.
+#[derive(Debug, Serialize, Deserialize)]
+struct TimeoutBudget {
+    timeout_ms: u64,
+    /// Fraction of successful ops completing in each tenth of the budget
+    deciles: Vec<f64>,
+    /// Fraction completing in the last 10% of the budget
+    last_decile_fraction: f64,
+    /// Share of the budget used by the P99 op
+    p99_budget_used: f64,
+}
+
+impl TimeoutBudget {
+    fn from_histogram(histogram: &Histogram<u64>, timeout: Duration) -> Self {
+        let timeout_us = (timeout.as_micros() as u64).max(1);
+        let mut counts = [0u64; 10];
+        for value in histogram.iter_recorded() {
+            let decile = (value.value_iterated_to() * 10 / timeout_us).min(9) as usize;
+            counts[decile] += value.count_at_value();
+        }
+        let total = histogram.len().max(1) as f64;
+        let deciles: Vec<f64> = counts.iter().map(|count| *count as f64 / total).collect();
+        TimeoutBudget {
+            timeout_ms: timeout.as_millis() as u64,
+            last_decile_fraction: deciles[9],
+            deciles,
+            p99_budget_used: histogram.value_at_quantile(0.99) as f64 / timeout_us as f64,
+        }
+    }
+}
+
+/// How the two backends of a dual_write run compared.
+#[derive(Debug, Serialize, Deserialize)]
+struct DualWriteReport {
+    primary: VerbLatency,
+    mirror: VerbLatency,
+    /// Writes that succeeded on one backend but failed on the other
+    diverged: u64,
+    /// Objects read back from both backends, with --dual-write-verify
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    verified: Option<u64>,
+    /// Read-backs where either backend returned other bytes than were written
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    readback_mismatches: Option<u64>,
+}
+
+/// How reads of the same keys from two backends compared.
+#[derive(Debug, Serialize, Deserialize)]
+struct ReadCompareReport {
+    primary: VerbLatency,
+    mirror: VerbLatency,
+    /// Reads compared, and how many found the same content on both backends
+    compared: u64,
+    matched: u64,
+    /// Distinct keys that differed at least once
+    divergent_keys: u64,
+    /// The first divergent keys, in key order
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    divergences: Vec<Divergence>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Divergence {
+    key: String,
+    /// missing_on_primary, missing_on_mirror, missing_on_both, size or content
+    reason: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    primary_bytes: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    mirror_bytes: Option<u64>,
+}
+
+/// What --verify-reads found.
+#[derive(Debug, Serialize, Deserialize)]
+struct ReadVerifyReport {
+    /// Bytes per read with --range-read-bytes (whole objects otherwise)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    range_bytes: Option<usize>,
+    verified: u64,
+    mismatched: u64,
+    /// The first mismatches, in the order they happened
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    mismatches: Vec<RangeMismatch>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct RangeMismatch {
+    key: String,
+    /// The range requested
+    offset: u64,
+    length: u64,
+    returned_bytes: u64,
+    /// range_ignored (the whole object came back), length or content
+    reason: String,
+    /// Object offset of the first byte that differs, for content mismatches
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    first_bad_offset: Option<u64>,
+}
+
+/// The client's memory use during a run, which bounds how far it can push
+/// large-object workloads.
+#[derive(Debug, Serialize, Deserialize)]
+struct MemoryReport {
+    /// Resident set high-water mark during the run (Linux only)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    peak_rss_bytes: Option<u64>,
+    /// Heap allocations per op, counted across all threads (`alloc-stats` feature)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    allocations_per_op: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    allocated_bytes_per_op: Option<f64>,
+}
+
+/// Version of the BenchmarkResult layout, bumped when a field is renamed,
+/// removed or changes meaning, so archived results can be compared safely.
+const RESULT_SCHEMA_VERSION: u32 = 1;
+
+/// Identifies the run (and build) a result came from, so archived results
+/// stay attributable.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct RunMetadata {
+    /// Random per process; the results of a sweep, phases or read_write share it
+    run_id: String,
+    /// When the process started
+    started_at_unix_ms: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    hostname: Option<String>,
+    tool_version: String,
+    /// Commit the binary was built from, if known at build time
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    git_sha: Option<String>,
+}
+
+impl RunMetadata {
+    /// Metadata for a run starting now, under a new run id.
+    fn new() -> Self {
+        RunMetadata {
+            run_id: Uuid::new_v4().to_string(),
+            started_at_unix_ms: std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap_or_default().as_millis() as u64,
+            hostname: hostname(),
+            tool_version: env!("CARGO_PKG_VERSION").to_string(),
+            git_sha: option_env!("QPS_BENCH_GIT_SHA").map(str::to_string),
+        }
+    }
+}
+
+/// The current run's metadata.
+fn run_metadata() -> RunMetadata {
+    RUN.try_with(|run| run.metadata.clone()).unwrap_or_else(|_| RunMetadata::new())
+}
+
+/// How long the run took to get going, for short-lived workers where
+/// startup rather than steady-state QPS dominates. On the command line the
+/// run starts with the process.
+#[derive(Debug, Serialize, Deserialize)]
+struct StartupTiming {
+    /// Run start until the client was built (config, credentials setup)
+    client_ready_ms: f64,
+    /// Run start until the first backend request succeeded
+    first_success_ms: f64,
+    /// How long that first request took, including DNS, TLS and any
+    /// credential fetch done on first use
+    first_request_ms: f64,
+}
+
+/// Everything one run keeps beyond its benchmark states: stop requests,
+/// startup timings and the services its ops report to. A run is a
+/// command-line command or one call of the library's [`run`], so runs
+/// sharing a process don't see each other's state.
+struct RunContext {
+    metadata: RunMetadata,
+    /// When the run started
+    started: Instant,
+    /// Time from the start until the client was built
+    client_ready: std::sync::OnceLock<Duration>,
+    /// Time from the start until the first successful request, and that request's latency
+    first_success: std::sync::OnceLock<(Duration, Duration)>,
+    /// Set by the first stop request; the run stops issuing new operations once set
+    interrupted: std::sync::atomic::AtomicBool,
+    /// Set for runs that last until stopped, where a stop is the expected end
+    until_stopped: std::sync::atomic::AtomicBool,
+    /// When --max-run-time runs out
+    deadline: std::sync::OnceLock<Instant>,
+    /// Cleared for the library's `run`, which returns the results instead
+    print_reports: bool,
+    /// Set by --stream-events
+    stream_events: std::sync::atomic::AtomicBool,
+    /// Set while the --tui dashboard owns the terminal; console logging and
+    /// the printed results hold off until it closes
+    tui_active: std::sync::atomic::AtomicBool,
+    /// The latest progress and warning lines, for the dashboard's log panel
+    tui_log: std::sync::Mutex<std::collections::VecDeque<String>>,
+    /// Set when a service that reads them starts; live metrics aren't tracked otherwise
+    live_metrics: std::sync::OnceLock<Arc<LiveMetrics>>,
+    /// Op latencies since the last StatsD flush, when --statsd-addr is set
+    statsd_latencies: std::sync::OnceLock<std::sync::Mutex<Histogram<u64>>>,
+    /// Set when the --op-log writer starts
+    op_log: std::sync::OnceLock<OpLog>,
+    /// Set when --debug-errors is on
+    debug_errors: std::sync::OnceLock<DebugErrors>,
+    /// Set when the --event-sink publisher starts
+    op_events: std::sync::OnceLock<OpEventQueue>,
+    /// Set when the probe finds the endpoint turns down multi-object
+    /// deletes, so cleanup sends one DELETE per key instead
+    single_deletes: std::sync::atomic::AtomicBool,
+}
+
+impl RunContext {
+    fn new(print_reports: bool) -> Self {
+        RunContext {
+            metadata: RunMetadata::new(),
+            started: Instant::now(),
+            client_ready: Default::default(),
+            first_success: Default::default(),
+            interrupted: Default::default(),
+            until_stopped: Default::default(),
+            deadline: Default::default(),
+            print_reports,
+            stream_events: Default::default(),
+            tui_active: Default::default(),
+            tui_log: Default::default(),
+            live_metrics: Default::default(),
+            statsd_latencies: Default::default(),
+            op_log: Default::default(),
+            debug_errors: Default::default(),
+            op_events: Default::default(),
+            single_deletes: Default::default(),
+        }
+    }
+    
+    fn interrupted(&self) -> bool {
+        self.interrupted.load(std::sync::atomic::Ordering::Relaxed)
+    }
+    
+    /// Stops the run the way the first signal does.
+    fn request_stop(&self, reason: &str) {
+        if self.interrupted.swap(true, std::sync::atomic::Ordering::Relaxed) {
+            return;
+        }
+        if self.until_stopped.load(std::sync::atomic::Ordering::Relaxed) {
+            tracing::info!("⏹️  {}: finishing in-flight operations, then reporting results", reason);
+        } else {
+            tracing::warn!("⚠️  {}: finishing in-flight operations, then reporting partial results (signal again to exit immediately)", reason);
+        }
+    }
+}
+
+tokio::task_local! {
+    /// The run the current task belongs to.
+    static RUN: Arc<RunContext>;
+}
+
+/// Calls `f` with the current run, or returns the default outside one.
+fn with_run<T: Default>(f: impl FnOnce(&RunContext) -> T) -> T {
+    RUN.try_with(|run| f(run)).unwrap_or_default()
+}
+
+/// The current run, for the services that belong to it.
+fn current_run() -> Result<Arc<RunContext>> {
+    RUN.try_with(Arc::clone).map_err(|_| anyhow::anyhow!("Not within a run"))
+}
+
+/// Spawns `task` as part of the current run, if any; task-locals aren't
+/// inherited by spawned tasks otherwise.
+fn spawn_in_run<F>(task: F) -> tokio::task::JoinHandle<F::Output>
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    match RUN.try_with(Arc::clone) {
+        Ok(run) => tokio::spawn(RUN.scope(run, task)),
+        Err(_) => tokio::spawn(task),
+    }
+}
+
+/// Records a successful backend request that took `latency`, if it's the first.
+fn note_first_success(latency: Duration) {
+    with_run(|run| {
+        if run.first_success.get().is_none() {
+            let _ = run.first_success.set((run.started.elapsed(), latency));
+        }
+    });
+}
+
+/// Records that the run's client has been built.
+fn note_client_ready() {
+    with_run(|run| {
+        let _ = run.client_ready.set(run.started.elapsed());
+    });
+}
+
+fn startup_timing() -> Option<StartupTiming> {
+    let ms = |duration: &Duration| duration.as_secs_f64() * 1000.0;
+    with_run(|run| {
+        let (first_success, first_request) = run.first_success.get()?;
+        Some(StartupTiming {
+            client_ready_ms: run.client_ready.get().map_or(0.0, ms),
+            first_success_ms: ms(first_success),
+            first_request_ms: ms(first_request),
+        })
+    })
+}
+
+/// Time ops spent waiting inside the harness before their request went out,
+/// kept apart from the op latencies (which start when the request does).
+#[derive(Debug, Serialize, Deserialize)]
+struct QueueingDelay {
+    /// How far past its intended --target-rate start each op began because
+    /// every worker was busy; None without --target-rate, where a worker
+    /// starts its next op as soon as it's free
+    #[serde(default, alias = "semaphore", skip_serializing_if = "Option::is_none")]
+    schedule_lag: Option<PhaseLatency>,
+    /// From a worker starting the op to the spawned op running on a Tokio thread
+    scheduler: PhaseLatency,
+}
+
+/// Ops that succeeded or failed only after OpenDAL retried them.
+#[derive(Debug, Serialize, Deserialize)]
+struct RetryStats {
+    /// Ops that needed at least one retry
+    retried_ops: u64,
+    /// Retry attempts across all ops
+    retries: u64,
+    /// Latency of each op's first attempt, before any retry (the op latencies
+    /// include retries and their backoff)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    first_attempt: Option<PhaseLatency>,
+    /// Latency of every attempt, the retried ones included
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    attempts: Option<PhaseLatency>,
+}
+
+/// How much of the workers' time went to producing write payloads.
+#[derive(Debug, Serialize, Deserialize)]
+struct PayloadReport {
+    /// Pre-generated buffers reused round-robin (0: fresh per write)
+    pool_buffers: usize,
+    payloads: u64,
+    generation_ms: f64,
+    /// Fraction of worker time (run time × concurrency) spent generating payloads
+    generation_share: f64,
+    /// Generation took so much of the run that QPS is capped by the client
+    generation_bound: bool,
+}
+
+/// Share of the client's link speed above which a run is flagged as bounded
+/// by the NIC rather than the backend.
+const NIC_SATURATION: f64 = 0.8;
+
+/// Object throughput of a run against the client's link speed.
+#[derive(Debug, Serialize, Deserialize)]
+struct NicReport {
+    link_bytes_per_second: u64,
+    /// Object bytes moved per second (request and response overhead excluded)
+    throughput_bytes_per_second: f64,
+    utilization: f64,
+    saturated: bool,
+}
+
+impl NicReport {
+    /// None when the link speed is unknown or the mode moves no object bodies.
+    fn measure(endpoint: &str, throughput_bytes_per_second: f64) -> Option<Self> {
+        let link = nic_bytes_per_second(endpoint)?;
+        if throughput_bytes_per_second <= 0.0 {
+            return None;
+        }
+        let utilization = throughput_bytes_per_second / link as f64;
+        Some(NicReport {
+            link_bytes_per_second: link,
+            throughput_bytes_per_second,
+            utilization,
+            saturated: utilization > NIC_SATURATION,
+        })
+    }
+}
+
+/// QPS, errors, concurrency and latency over one interval of a run.
+#[derive(Debug, Serialize, Deserialize)]
+struct IntervalStat {
+    start_seconds: f64,
+    ok_ops: u64,
+    err_ops: u64,
+    qps: f64,
+    #[serde(default)]
+    error_pct: f64,
+    /// Most ops in flight at once during the interval
+    #[serde(default)]
+    in_flight: u64,
+    latency_us_p50: u64,
+    latency_us_p99: u64,
+    /// Percentiles over the PERCENTILE_WINDOW ending with this interval, which
+    /// show the tail degrading mid-run where one interval has too few samples
+    #[serde(default)]
+    window_latency_us_p50: u64,
+    #[serde(default)]
+    window_latency_us_p99: u64,
+    /// Ops that failed with a throttling error (HTTP 429/503, SlowDown)
+    #[serde(default)]
+    throttled_ops: u64,
+    /// Whether enough of the interval's ops were throttled to call it throttled
+    #[serde(default)]
+    throttled: bool,
+}
+
+/// Ops the backend turned away as throttled, and how much of the run it did so.
+#[derive(Debug, Serialize, Deserialize)]
+struct ThrottleReport {
+    throttled_ops: u64,
+    /// Share of all ops that were throttled
+    #[serde(default)]
+    throttle_rate_pct: f64,
+    /// Throttled ops by response: slow_down, too_many_requests, service_unavailable or rate_limited
+    #[serde(default)]
+    reasons: BTreeMap<String, u64>,
+    /// Seconds of the run where at least THROTTLED_SHARE of ops were throttled
+    throttled_seconds: u64,
+    throttled_pct: f64,
+    /// When the first op was throttled, in seconds into the run
+    #[serde(default)]
+    first_throttled_seconds: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    first_throttled_unix_ms: Option<u64>,
+}
+
+/// How the halves of a combined run share keys, recorded so results taken
+/// under different policies aren't compared as like for like.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum KeyIsolation {
+    /// Writers only create new keys; readers only read the dataset, so no
+    /// read ever races an overwrite
+    Disjoint,
+    /// Writers overwrite the keys readers read, as read_overwrite asks for
+    Overwrite,
+}
+
+impl KeyIsolation {
+    fn describe(self) -> &'static str {
+        match self {
+            KeyIsolation::Disjoint => "disjoint (writes create new keys, reads use the dataset)",
+            KeyIsolation::Overwrite => "overwrite (writes replace the keys being read)",
+        }
+    }
+}
+
+/// A worker's P99 this many times the median worker's makes it a straggler.
+const STRAGGLER_FACTOR: f64 = 5.0;
+
+/// What read_overwrite saw when reads raced overwrites of the same keys.
+#[derive(Debug, Serialize, Deserialize)]
+struct OverwriteReport {
+    keys: u64,
+    /// Overwrites by the background writers
+    overwrites: VerbLatency,
+    /// Reads that returned the last acknowledged version (or one acknowledged during the read)
+    current: u64,
+    /// Reads that returned a version whose write hadn't been acknowledged yet
+    new: u64,
+    /// Reads that returned a version older than one acknowledged before the read began
+    stale: u64,
+    /// Reads whose content matched no version written to the key
+    torn: u64,
+    /// Failed reads by error kind
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    read_errors: BTreeMap<String, u64>,
+    /// Reads that overlapped an overwrite of their key, and those that didn't
+    racing_reads: VerbLatency,
+    quiet_reads: VerbLatency,
+}
+
+/// Workers with fewer successful ops than this aren't judged; their P99 is noise.
+const STRAGGLER_MIN_OPS: u64 = 20;
+
+/// Per-worker counters with --worker-stats. A worker is one of the
+/// `concurrency` loops of a run, each running its ops one after another, so
+/// a slow connection or queue shows up under the worker that used it.
+struct WorkerStats {
+    /// Latencies and failed ops, by worker
+    workers: std::sync::Mutex<Vec<(Histogram<u64>, u64)>>,
+}
+
+impl WorkerStats {
+    /// Makes room for `concurrency` workers in the next run.
+    fn start(&self, concurrency: usize) {
+        let mut workers = self.workers.lock().unwrap();
+        if workers.len() < concurrency {
+            workers.resize_with(concurrency, || (Histogram::<u64>::new(2).unwrap(), 0));
+        }
+    }
+
+    /// Records an op `worker` ran, with its latency if it succeeded.
+    fn record(&self, worker: usize, latency_us: Option<u64>) {
+        let mut workers = self.workers.lock().unwrap();
+        let (histogram, err_ops) = &mut workers[worker];
+        match latency_us {
+            Some(latency_us) => {
+                histogram.record(latency_us).ok();
+            }
+            None => *err_ops += 1,
+        }
+    }
+
+    fn take_report(&self) -> WorkerReport {
+        let workers = std::mem::take(&mut *self.workers.lock().unwrap());
+        let workers: Vec<WorkerStat> = workers
+            .iter()
+            .enumerate()
+            .map(|(worker, (histogram, err_ops))| WorkerStat {
+                worker,
+                ok_ops: histogram.len(),
+                err_ops: *err_ops,
+                latency_us_p50: histogram.value_at_quantile(0.5),
+                latency_us_p99: histogram.value_at_quantile(0.99),
+            })
+            .collect();
+        let mut p99s: Vec<u64> = workers.iter().filter(|w| w.ok_ops >= STRAGGLER_MIN_OPS).map(|w| w.latency_us_p99).collect();
+        p99s.sort_unstable();
+        let median_p99_us = p99s.get(p99s.len() / 2).copied().unwrap_or_default();
+        let stragglers = workers
+            .iter()
+            .filter(|w| w.ok_ops >= STRAGGLER_MIN_OPS && median_p99_us > 0 && w.latency_us_p99 as f64 >= median_p99_us as f64 * STRAGGLER_FACTOR)
+            .map(|w| w.worker)
+            .collect();
+        WorkerReport { workers, median_p99_us, stragglers }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct WorkerReport {
+    workers: Vec<WorkerStat>,
+    /// Median of the P99s of workers with at least STRAGGLER_MIN_OPS ops
+    median_p99_us: u64,
+    /// Workers whose P99 is at least STRAGGLER_FACTOR times the median
+    stragglers: Vec<usize>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct WorkerStat {
+    worker: usize,
+    ok_ops: u64,
+    err_ops: u64,
+    latency_us_p50: u64,
+    latency_us_p99: u64,
+}
+
+/// How steady per-second throughput was over the run's whole seconds.
+#[derive(Debug, Serialize, Deserialize)]
+struct QpsStability {
+    mean_qps: f64,
+    stddev_qps: f64,
+    /// Coefficient of variation (stddev / mean): near 0 for a store that
+    /// holds its rate, large for one that oscillates around the same mean
+    cov: f64,
+}
+
+impl QpsStability {
+    /// None with fewer than two whole seconds to compare.
+    fn from_seconds(seconds: &[SecondCounts], run_time: Duration) -> Option<Self> {
+        // The last partial second and the drain after the deadline would read as dips
+        let whole = seconds.len().min(run_time.as_secs() as usize);
+        if whole < 2 {
+            return None;
+        }
+        let qps: Vec<f64> = seconds[..whole].iter().map(|second| second.ok_ops as f64).collect();
+        let mean_qps = qps.iter().sum::<f64>() / whole as f64;
+        let stddev_qps = (qps.iter().map(|q| (q - mean_qps).powi(2)).sum::<f64>() / whole as f64).sqrt();
+        Some(QpsStability {
+            mean_qps,
+            stddev_qps,
+            cov: if mean_qps > 0.0 { stddev_qps / mean_qps } else { 0.0 },
+        })
+    }
+}
+
+/// Share of an interval's ops that must fail throttled for it to count as
+/// throttled; a stray 503 doesn't make a throttled second.
+const THROTTLED_SHARE: f64 = 0.01;
+
+impl ThrottleReport {
+    /// How much of a `run_time` run started at `started_at_unix_ms` was
+    /// throttled, or None if nothing was.
+    fn from_seconds(seconds: &[SecondCounts], reasons: BTreeMap<String, u64>, run_time: Duration, started_at_unix_ms: Option<u64>) -> Option<Self> {
+        let throttled_ops: u64 = seconds.iter().map(|second| second.throttled_ops).sum();
+        let first_throttled_seconds = seconds.iter().position(|second| second.throttled_ops > 0)? as u64;
+        let ops: u64 = seconds.iter().map(|second| second.ok_ops + second.err_ops).sum();
+        let throttled_seconds = seconds
+            .iter()
+            .filter(|second| is_throttled(second.ok_ops + second.err_ops, second.throttled_ops))
+            .count() as u64;
+        Some(ThrottleReport {
+            throttled_ops,
+            throttle_rate_pct: throttled_ops as f64 / ops as f64 * 100.0,
+            reasons,
+            throttled_seconds,
+            throttled_pct: throttled_seconds as f64 / run_time.as_secs_f64().max(1.0) * 100.0,
+            first_throttled_seconds,
+            first_throttled_unix_ms: started_at_unix_ms.map(|started| started + first_throttled_seconds * 1000),
+        })
+    }
+}
+
+/// What kind of throttling response failed an op, or None if it wasn't
+/// throttled. S3 throttles with 503 SlowDown (HEAD responses have no body to
+/// name the code) and some S3-compatible services with 429; OpenDAL reports
+/// SlowDown as RateLimited.
+fn throttle_reason(error: &opendal::Error, response: Option<&ErrorResponse>) -> Option<&'static str> {
+    let status = response.map(|response| response.status);
+    let code = response.and_then(|response| response.code.as_deref());
+    let rate_limited = error.kind() == opendal::ErrorKind::RateLimited;
+    match (code, status) {
+        (Some("SlowDown"), _) => Some("slow_down"),
+        (Some("TooManyRequests"), _) | (_, Some(429)) => Some("too_many_requests"),
+        (_, Some(503)) if rate_limited => Some("slow_down"),
+        (_, Some(503)) => Some("service_unavailable"),
+        _ if rate_limited => Some("rate_limited"),
+        _ => None,
+    }
+}
+
+fn is_throttled(ops: u64, throttled_ops: u64) -> bool {
+    throttled_ops > 0 && throttled_ops as f64 >= ops as f64 * THROTTLED_SHARE
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct VerbLatency {
+    ok_ops: u64,
+    err_ops: u64,
+    latency_us_p50: u64,
+    latency_us_p95: u64,
+    latency_us_p99: u64,
+    latency_us_mean: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct BackendInfo {
+    service: String,
+    endpoint: String,
+    region: String,
+    bucket: String,
+    /// Results from before this was recorded were path-style, OpenDAL's default
+    #[serde(default = "default_addressing_style")]
+    addressing_style: String,
+    #[serde(default = "default_client")]
+    client: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    transfer_engine: Option<String>,
+}
+
+fn default_addressing_style() -> String {
+    "path".to_string()
+}
+
+/// Results from before --client was added all came from OpenDAL.
+fn default_client() -> String {
+    "opendal".to_string()
+}
+
+struct BenchmarkState {
+    client: Arc<dyn BenchClient>,
+    verbs: VerbStatsMap,
+    /// Draws new keys and --key-popularity ranks; seeded with `with_seed`
+    rng: std::sync::Mutex<StdRng>,
+    /// Objects written and deleted by write_small/delete, for handing the
+    /// resulting dataset to the next phase
+    written: std::sync::Mutex<Vec<String>>,
+    deleted: std::sync::Mutex<std::collections::HashSet<String>>,
+    keys: Arc<Vec<String>>,
+    object_size: usize,
+    prefix: String,
+    next_key_index: Arc<std::sync::atomic::AtomicUsize>,
+    phases: std::sync::Mutex<BTreeMap<&'static str, Histogram<u64>>>,
+    /// Per-interval buckets, when enabled with `with_intervals`
+    intervals: Option<std::sync::Mutex<Vec<IntervalBucket>>>,
+    series_interval: Duration,
+    /// Ops running now, for the per-interval series
+    in_flight: std::sync::atomic::AtomicU64,
+    /// Ops finishing in each second of the run
+    second_counts: std::sync::Mutex<Vec<SecondCounts>>,
+    throttle_reasons: std::sync::Mutex<BTreeMap<&'static str, u64>>,
+    /// How long the last run lasted, if a signal stopped it early
+    interrupted_after: std::sync::Mutex<Option<Duration>>,
+    /// Wall time of the runs since the last result, from the first op's start
+    /// until the last in-flight op finished
+    elapsed: std::sync::Mutex<Option<Duration>>,
+    payloads: PayloadSource,
+    /// Cumulative weights by key rank, from --key-popularity
+    key_popularity: Option<Arc<Vec<f64>>>,
+    /// Per-key stats for stat_storm, keyed by the key without the prefix
+    key_latencies: std::sync::Mutex<BTreeMap<String, VerbStats>>,
+    /// List ops cut short by --list-max-entries
+    truncated_lists: std::sync::atomic::AtomicU64,
+    /// Wall-clock start of the first run on this state
+    started_at: std::sync::Mutex<Option<std::time::SystemTime>>,
+    /// Allocation counters when that run started, with `alloc-stats`
+    alloc_baseline: std::sync::Mutex<Option<(u64, u64)>>,
+    /// Second backend written alongside the primary by dual_write
+    dual_write: Option<Arc<DualWriteTarget>>,
+    /// Second backend read alongside the primary by read_compare
+    read_compare: Option<Arc<ReadCompareTarget>>,
+    /// How read_small reads, with --range-read-bytes or --verify-reads
+    read_check: Option<Arc<ReadCheck>>,
+    op_timeout: Option<Duration>,
+    timeouts: std::sync::atomic::AtomicU64,
+    retried_ops: std::sync::atomic::AtomicU64,
+    retries: std::sync::atomic::AtomicU64,
+    /// First-attempt and per-attempt latencies, for telling the backend's tail
+    /// from the time added by retries
+    attempt_latencies: std::sync::Mutex<(Histogram<u64>, Histogram<u64>)>,
+    /// Gap between intended op starts with --target-rate
+    target_interval: Option<Duration>,
+    /// Latencies from intended start, with --target-rate
+    corrected_latencies: std::sync::Mutex<Histogram<u64>>,
+    /// Lag behind the --target-rate schedule and waits for the scheduler before each op
+    queue_latencies: std::sync::Mutex<(Histogram<u64>, Histogram<u64>)>,
+    /// Per-worker counters, with --worker-stats
+    workers: Option<WorkerStats>,
+    /// Keys overwritten while read, in read_overwrite
+    overwrite: Option<Arc<OverwriteRace>>,
+    /// Background object turnover, with --churn-pct-per-minute
+    churn: Option<Arc<Churn>>,
+}
+
+/// Width of one bucket in per-interval series, unless --timeseries-interval-seconds is set.
+const SERIES_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Span of the rolling percentiles in per-interval series.
+const PERCENTILE_WINDOW: Duration = Duration::from_secs(10);
+
+#[derive(Clone, Default)]
+struct SecondCounts {
+    ok_ops: u64,
+    err_ops: u64,
+    throttled_ops: u64,
+}
+
+struct IntervalBucket {
+    histogram: Histogram<u64>,
+    err_ops: u64,
+    throttled_ops: u64,
+    max_in_flight: u64,
+}
+
+fn generate_key(prefix: &str, index: usize, rng: &mut StdRng) -> String {
+    // Use randomized distribution: prefix + <2 hex chars>/<uuid>, the UUID
+    // drawn from `rng` so a seeded run writes the same keys
+    let hex_part = format!("{:02x}", index % 256);
+    let uuid_part = uuid::Builder::from_random_bytes(rng.gen()).into_uuid().to_string();
+    format!("{}/{}/{}", prefix, hex_part, uuid_part)
+}
+
+/// Objects created between manifest checkpoints; at most this many objects
+/// are untracked if dataset creation is killed.
+const DATASET_CHECKPOINT_INTERVAL: usize = 1000;
+
+/// Creates objects until `manifest` holds `count` keys, continuing from any
+/// keys it already has, with up to `concurrency` uploads in flight. With
+/// `checkpoint`, the manifest is saved there (marked incomplete) as creation
+/// progresses so an interrupted run can resume.
+async fn create_dataset(client: &dyn BenchClient, manifest: &mut DatasetManifest, count: usize, concurrency: usize, checkpoint: Option<&std::path::Path>) -> Result<()> {
+    let size = manifest.object_size_bytes;
+    if manifest.keys.is_empty() {
+        tracing::info!("Creating dataset: {} objects of {} bytes each...", count, size);
+    } else {
+        tracing::info!("Resuming dataset creation: {}/{} objects already exist...", manifest.keys.len(), count);
+    }
+    manifest.incomplete = true;
+    if let Some(path) = checkpoint {
+        manifest.save(path)?;
+    }
+    // Datasets from before seeded content are resumed with zeros, as they began
+    let seeded = manifest.seeded;
+    let prefix = manifest.prefix.clone();
+    let first = manifest.keys.len();
+    let start = Instant::now();
+    // The prefix is drawn from the run's seed, so the keys follow from it too
+    let mut rng = StdRng::seed_from_u64(fnv1a(prefix.as_bytes()) ^ first as u64);
+    
+    let mut uploads = futures::stream::iter(first..count)
+        .take_while(|_| std::future::ready(!interrupted()))
+        .map(|i| {
+            let key = generate_key(&prefix, i, &mut rng);
+            let data = dataset_content(seeded, &key, size);
+            async move {
+                let start = Instant::now();
+                let result = client.write(&key, data).await;
+                if result.is_ok() {
+                    note_first_success(start.elapsed());
+                }
+                (i, key, result)
+            }
+        })
+        .buffer_unordered(concurrency.max(1));
+    let mut attempted = first;
+    while let Some((i, key, result)) = uploads.next().await {
+        attempted += 1;
+        match result {
+            Ok(()) => manifest.keys.push(key),
+            Err(e) => tracing::warn!(object = i, error = %e, "Failed to create object"),
+        }
+        if attempted % DATASET_CHECKPOINT_INTERVAL == 0 {
+            tracing::info!("  Created {}/{} objects...", manifest.keys.len(), count);
+            if let Some(path) = checkpoint {
+                manifest.save(path)?;
+            }
+        }
+    }
+    
+    // An interrupted creation stays a checkpoint that the next run resumes
+    manifest.incomplete = interrupted();
+    let elapsed = start.elapsed().as_secs_f64();
+    let created = manifest.keys.len() - first;
+    tracing::info!("Dataset created: {} objects ({} new in {:.1}s, {:.1} objects/s)",
+                   manifest.keys.len(), created, elapsed, created as f64 / elapsed.max(f64::EPSILON));
+    Ok(())
+}
+
+/// Whether the current run was asked to stop (by a signal, the dashboard,
+/// the control socket or a limit).
+fn interrupted() -> bool {
+    with_run(RunContext::interrupted)
+}
+
+/// Whether the run was cut short, leaving partial results.
+fn stopped_early() -> bool {
+    with_run(|run| run.interrupted() && !run.until_stopped.load(std::sync::atomic::Ordering::Relaxed))
+}
+
+/// Stops the current run the way the first signal does.
+fn request_stop(reason: &str) {
+    with_run(|run| run.request_stop(reason));
+}
+
+/// Share of --max-run-time kept for cleanup; creation and the benchmark are
+/// stopped once the rest has passed.
+const MAX_RUN_TIME_CLEANUP_SHARE: f64 = 0.1;
+
+/// Stops the run like a signal once all but the cleanup share of
+/// `max_run_time` (counted from the run's start) has passed.
+fn start_run_deadline(max_run_time: Duration) -> tokio::task::JoinHandle<()> {
+    let start = RUN
+        .try_with(|run| {
+            let _ = run.deadline.set(run.started + max_run_time);
+            run.started
+        })
+        .unwrap_or_else(|_| Instant::now());
+    let stop_at = start + max_run_time.mul_f64(1.0 - MAX_RUN_TIME_CLEANUP_SHARE);
+    spawn_in_run(async move {
+        tokio::time::sleep_until(stop_at.into()).await;
+        request_stop(&format!("--max-run-time of {}s nearly used up", max_run_time.as_secs()));
+    })
+}
+
+/// Time left before --max-run-time runs out, if it is set.
+fn run_time_left() -> Option<Duration> {
+    with_run(|run| run.deadline.get().map(|deadline| deadline.saturating_duration_since(Instant::now())))
+}
+
+/// Notified by a second stop request; `run_cli` then abandons the command,
+/// skipping cleanup.
+static ABANDONED: tokio::sync::Notify = tokio::sync::Notify::const_new();
+
+/// Stops the run on a repeated request, or gives it up if it was already
+/// stopping.
+fn interrupt(reason: &str) {
+    if interrupted() {
+        tracing::warn!("⛔ Interrupted again; exiting without cleanup");
+        ABANDONED.notify_one();
+    } else {
+        request_stop(reason);
+    }
+}
+
+/// The first SIGINT/SIGTERM stops the run gracefully: in-flight operations
+/// drain, partial results are reported and cleanup still runs. A second
+/// signal abandons the command, skipping cleanup.
+fn install_signal_handler() -> Result<()> {
+    #[cfg(unix)]
+    let mut terminate = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+        .map_err(|e| anyhow::anyhow!("Failed to install the SIGTERM handler: {}", e))?;
+    spawn_in_run(async move {
+        loop {
+            #[cfg(unix)]
+            tokio::select! {
+                _ = tokio::signal::ctrl_c() => {}
+                _ = terminate.recv() => {}
+            }
+            #[cfg(not(unix))]
+            let _ = tokio::signal::ctrl_c().await;
+            interrupt("Interrupted");
+        }
+    });
+    Ok(())
+}
+
+/// Bookkeeping for the op running in a task, filled in by the client layers.
+#[derive(Default)]
+struct OpContext {
+    /// Retries made so far
+    retries: std::cell::Cell<u32>,
+    /// Wall-clock time at least one of the op's requests spent waiting for
+    /// --operator-rate-limit, excluded from latencies. Overlapping waits of
+    /// parallel requests count once.
+    throttled: std::cell::Cell<Duration>,
+    /// Requests of the op waiting for the rate limiter right now
+    waiting: std::cell::Cell<u32>,
+    /// When `waiting` last went from zero to one
+    waiting_since: std::cell::Cell<Option<Instant>>,
+    /// Start of the op, for clients whose requests don't report attempts
+    started: std::cell::Cell<Option<Instant>>,
+    /// Latencies of the attempts the op's requests completed, failed or not
+    attempts: std::cell::RefCell<Vec<Duration>>,
+    /// Verb and key of the op's first request, kept for --event-sink
+    request: std::cell::RefCell<Option<(&'static str, String)>>,
+    /// The last failed response one of the op's requests got
+    error_response: std::cell::RefCell<Option<ErrorResponse>>,
+}
+
+impl OpContext {
+    /// Latencies of every attempt of the op so far.
+    fn attempt_latencies(&self) -> Vec<Duration> {
+        let attempts = self.attempts.borrow();
+        match self.started.get() {
+            Some(started) if attempts.is_empty() => vec![Instant::now().saturating_duration_since(started)],
+            _ => attempts.clone(),
+        }
+    }
+    
+    /// Rate limiter wait so far, including a wait still in progress.
+    fn throttled(&self) -> Duration {
+        match self.waiting_since.get() {
+            Some(since) if self.waiting.get() > 0 => self.throttled.get() + since.elapsed(),
+            _ => self.throttled.get(),
+        }
+    }
+}
+
+tokio::task_local! {
+    static OP_CONTEXT: OpContext;
+    /// Start of the current attempt of one request (after the backoff, for a
+    /// retry); scoped per request so parallel requests of an op don't share it.
+    static REQUEST_ATTEMPT: std::cell::Cell<Instant>;
+    /// Set by StatCacheClient when it answers a stat locally, so the
+    /// MeteredClient above it doesn't count a HEAD.
+    static STAT_CACHE_HIT: std::cell::Cell<bool>;
+}
+
+/// Time the current op has waited on the rate limiter so far (zero outside a benchmark op).
+fn op_throttled() -> Duration {
+    OP_CONTEXT.try_with(OpContext::throttled).unwrap_or_default()
+}
+
+/// HTTP status and S3 error code of a failed response, noted by the client
+/// that received it: OpenDAL errors only carry them as text.
+#[derive(Debug, Clone, Default)]
+struct ErrorResponse {
+    status: u16,
+    code: Option<String>,
+}
+
+/// Records a failed response against the current op (ignored outside one).
+fn note_error_response(status: u16, code: Option<String>) {
+    let _ = OP_CONTEXT.try_with(|context| *context.error_response.borrow_mut() = Some(ErrorResponse { status, code }));
+}
+
+/// Takes the failed response noted for the current op, if any.
+fn take_error_response() -> Option<ErrorResponse> {
+    OP_CONTEXT.try_with(|context| context.error_response.take()).ok().flatten()
+}
+
+/// Hands the operator's requests to `inner` and notes the status of every
+/// unsuccessful response in the op's context.
+struct StatusRecorder(opendal::raw::HttpClient);
+
+impl opendal::raw::HttpFetch for StatusRecorder {
+    async fn fetch(&self, request: http::Request<opendal::Buffer>) -> opendal::Result<http::Response<opendal::raw::HttpBody>> {
+        let response = self.0.fetch(request).await?;
+        if !response.status().is_success() {
+            note_error_response(response.status().as_u16(), None);
+        }
+        Ok(response)
+    }
+}
+
+/// Runs one request of a benchmark op, recording the latency of each of its
+/// attempts in the op's OpContext.
+async fn attempt_scope<T>(fut: impl Future<Output = T>) -> T {
+    if OP_CONTEXT.try_with(|_| ()).is_err() {
+        return fut.await;
+    }
+    REQUEST_ATTEMPT
+        .scope(std::cell::Cell::new(Instant::now()), async move {
+            let output = fut.await;
+            let last = REQUEST_ATTEMPT.with(|started| Instant::now().saturating_duration_since(started.get()));
+            OP_CONTEXT.with(|context| context.attempts.borrow_mut().push(last));
+            output
+        })
+        .await
+}
+
+/// Counts OpenDAL RetryLayer retries against the op whose task made them.
+struct RetryCounter;
+
+impl opendal::layers::RetryInterceptor for RetryCounter {
+    fn intercept(&self, _err: &opendal::Error, dur: Duration) {
+        // Retries outside a benchmark op (dataset setup, cleanup) aren't counted
+        let _ = OP_CONTEXT.try_with(|context| {
+            context.retries.set(context.retries.get() + 1);
+            // The failed attempt of this request ends now; the next starts after the backoff
+            let _ = REQUEST_ATTEMPT.try_with(|started| {
+                let now = Instant::now();
+                context.attempts.borrow_mut().push(now.saturating_duration_since(started.get()));
+                started.set(now + dur);
+            });
+        });
+    }
+}
+
+/// Upper bounds, in seconds, of the live latency histogram buckets.
+const LIVE_LATENCY_BUCKETS: [f64; 13] = [0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0];
+
+/// Process-wide counters behind --prometheus-listen. They accumulate across
+/// modes and phases; the current mode is published as a label on an info metric.
+struct LiveMetrics {
+    mode: std::sync::Mutex<String>,
+    ok_ops: std::sync::atomic::AtomicU64,
+    err_ops: std::sync::atomic::AtomicU64,
+    in_flight: std::sync::atomic::AtomicI64,
+    /// Non-cumulative per-bucket counts; the last one is +Inf
+    buckets: [std::sync::atomic::AtomicU64; LIVE_LATENCY_BUCKETS.len() + 1],
+    latency_sum_us: std::sync::atomic::AtomicU64,
+    /// When tracking began; the start of every cumulative series
+    #[cfg(feature = "otlp-metrics")]
+    started: std::time::SystemTime,
+}
+
+impl LiveMetrics {
+    fn new() -> Self {
+        LiveMetrics {
+            mode: std::sync::Mutex::new(String::new()),
+            ok_ops: Default::default(),
+            err_ops: Default::default(),
+            in_flight: Default::default(),
+            buckets: Default::default(),
+            latency_sum_us: Default::default(),
+            #[cfg(feature = "otlp-metrics")]
+            started: std::time::SystemTime::now(),
+        }
+    }
+}
+
+/// Starts tracking live metrics for the current run, if not already.
+fn live_metrics() -> Arc<LiveMetrics> {
+    RUN.try_with(|run| run.live_metrics.get_or_init(|| Arc::new(LiveMetrics::new())).clone())
+        .unwrap_or_else(|_| Arc::new(LiveMetrics::new()))
+}
+
+/// Calls `f` with the current run's live metrics, if they're tracked.
+fn with_live_metrics(f: impl FnOnce(&LiveMetrics)) {
+    with_run(|run| {
+        if let Some(live) = run.live_metrics.get() {
+            f(live);
+        }
+    });
+}
+
+/// Marks the start of a mode's run (a phase, sweep step or read_write half).
+fn set_live_mode(mode: &str, concurrency: usize) {
+    with_live_metrics(|live| *live.mode.lock().unwrap() = mode.to_string());
+    emit_event("phase", serde_json::json!({ "mode": mode, "concurrency": concurrency }));
+}
+
+/// Whether the current run has --stream-events on.
+fn stream_events() -> bool {
+    with_run(|run| run.stream_events.load(std::sync::atomic::Ordering::Relaxed))
+}
+
+/// Prints one --stream-events event to stdout as a JSON line, tagged with
+/// `event` and the time.
+fn emit_event(event: &str, mut fields: serde_json::Value) {
+    if !stream_events() {
+        return;
+    }
+    if let Some(object) = fields.as_object_mut() {
+        let unix_ms = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap_or_default().as_millis() as u64;
+        object.insert("event".to_string(), event.into());
+        object.insert("unix_ms".to_string(), unix_ms.into());
+    }
+    println!("{}", fields);
+}
+
+/// Emits an `interval` event with the live counters' progress every `period`.
+fn start_event_stream(period: Duration) -> tokio::task::JoinHandle<()> {
+    let live = live_metrics();
+    spawn_in_run(async move {
+        let load = |counter: &std::sync::atomic::AtomicU64| counter.load(std::sync::atomic::Ordering::Relaxed);
+        let mut ticker = tokio::time::interval(period);
+        ticker.tick().await;
+        let (mut ok_ops, mut err_ops, mut latency_sum_us) = (load(&live.ok_ops), load(&live.err_ops), load(&live.latency_sum_us));
+        loop {
+            ticker.tick().await;
+            let (ok, err, sum) = (load(&live.ok_ops), load(&live.err_ops), load(&live.latency_sum_us));
+            let mode = live.mode.lock().unwrap().clone();
+            emit_event("interval", serde_json::json!({
+                "mode": mode,
+                "ok_ops": ok - ok_ops,
+                "err_ops": err - err_ops,
+                "qps": (ok - ok_ops) as f64 / period.as_secs_f64(),
+                "latency_us_mean": if ok > ok_ops { (sum - latency_sum_us) / (ok - ok_ops) } else { 0 },
+                "in_flight": live.in_flight.load(std::sync::atomic::Ordering::Relaxed),
+            }));
+            (ok_ops, err_ops, latency_sum_us) = (ok, err, sum);
+        }
+    })
+}
+
+/// Writes one line of `report!` output, unless the --tui dashboard owns the
+/// terminal (the results are printed again once it closes) or the run is a
+/// library call.
+fn report_line(line: std::fmt::Arguments<'_>) {
+    if tui_active() || !with_run(|run| run.print_reports) {
+        return;
+    }
+    let _ = std::io::Write::write_fmt(&mut human_output(), format_args!("{}\n", line));
+}
+
+/// Where logs and report output go: stdout, unless --stream-events keeps it
+/// for events alone.
+fn human_output() -> Box<dyn std::io::Write> {
+    if stream_events() {
+        Box::new(std::io::stderr())
+    } else {
+        Box::new(std::io::stdout())
+    }
+}
+
+/// Forwards warnings to --stream-events as `warning` events.
+struct StreamEventsLayer;
+
+impl<S: tracing::Subscriber> tracing_subscriber::Layer<S> for StreamEventsLayer {
+    fn on_event(&self, event: &tracing::Event<'_>, _ctx: tracing_subscriber::layer::Context<'_, S>) {
+        if *event.metadata().level() > tracing::Level::WARN || !stream_events() {
+            return;
+        }
+        emit_event("warning", serde_json::json!({ "level": event.metadata().level().as_str(), "message": event_message(event) }));
+    }
+}
+
+/// The formatted message of a log event.
+fn event_message(event: &tracing::Event<'_>) -> String {
+    struct Message(String);
+    impl tracing::field::Visit for Message {
+        fn record_debug(&mut self, field: &tracing::field::Field, value: &dyn std::fmt::Debug) {
+            if field.name() == "message" {
+                self.0 = format!("{:?}", value);
+            }
+        }
+    }
+    let mut message = Message(String::new());
+    event.record(&mut message);
+    message.0
+}
+
+/// Whether the --tui dashboard owns the terminal for the current run.
+fn tui_active() -> bool {
+    with_run(|run| run.tui_active.load(std::sync::atomic::Ordering::Relaxed))
+}
+
+/// How many of the latest log lines the dashboard shows.
+const TUI_LOG_LINES: usize = 6;
+
+/// Keeps the log lines the console would have shown while the dashboard is up.
+struct TuiLogLayer;
+
+impl<S: tracing::Subscriber> tracing_subscriber::Layer<S> for TuiLogLayer {
+    fn on_event(&self, event: &tracing::Event<'_>, _ctx: tracing_subscriber::layer::Context<'_, S>) {
+        let metadata = event.metadata();
+        if !tui_active() || *metadata.level() > tracing::Level::INFO || !metadata.target().starts_with("qps_bench") {
+            return;
+        }
+        let message = event_message(event);
+        with_run(|run| {
+            let mut log = run.tui_log.lock().unwrap();
+            if log.len() == TUI_LOG_LINES {
+                log.pop_front();
+            }
+            log.push_back(message);
+        });
+    }
+}
+
+/// Records a finished op, with its latency if it succeeded.
+fn record_live_op(latency_us: Option<u64>) {
+    with_run(|run| {
+        let Some(live) = run.live_metrics.get() else {
+            return;
+        };
+        match latency_us {
+            Some(latency_us) => {
+                let seconds = latency_us as f64 / 1_000_000.0;
+                let bucket = LIVE_LATENCY_BUCKETS.iter().position(|bound| seconds <= *bound).unwrap_or(LIVE_LATENCY_BUCKETS.len());
+                live.buckets[bucket].fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+                live.latency_sum_us.fetch_add(latency_us, std::sync::atomic::Ordering::Relaxed);
+                live.ok_ops.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+                if let Some(latencies) = run.statsd_latencies.get() {
+                    latencies.lock().unwrap().record(latency_us).ok();
+                }
+            }
+            None => {
+                live.err_ops.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+            }
+        }
+    });
+}
+
+/// Resident set high-water mark (VmHWM) of the process, on Linux.
+fn peak_rss_bytes() -> Option<u64> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|line| line.starts_with("VmHWM:"))?;
+    let kib: u64 = line.split_whitespace().nth(1)?.parse().ok()?;
+    Some(kib * 1024)
+}
+
+/// MemAvailable from /proc/meminfo, on Linux.
+fn available_memory_bytes() -> Option<u64> {
+    let meminfo = std::fs::read_to_string("/proc/meminfo").ok()?;
+    let line = meminfo.lines().find(|line| line.starts_with("MemAvailable:"))?;
+    let kib: u64 = line.split_whitespace().nth(1)?.parse().ok()?;
+    Some(kib * 1024)
+}
+
+/// Link speed of the network interface traffic to `endpoint` leaves through,
+/// in bytes per second, on Linux. Falls back to the fastest interface (other
+/// than loopback) when the route can't be determined. Virtual interfaces
+/// don't report a speed.
+fn nic_bytes_per_second(endpoint: &str) -> Option<u64> {
+    let speed = |interface: &std::ffi::OsStr| {
+        std::fs::read_to_string(std::path::Path::new("/sys/class/net").join(interface).join("speed"))
+            .ok()
+            .and_then(|speed| speed.trim().parse::<i64>().ok())
+            .filter(|mbit| *mbit > 0)
+            .map(|mbit| mbit as u64 * 1_000_000 / 8)
+    };
+    if let Some(interface) = route_interface(endpoint) {
+        return speed(std::ffi::OsStr::new(&interface));
+    }
+    std::fs::read_dir("/sys/class/net")
+        .ok()?
+        .flatten()
+        .filter(|entry| entry.file_name() != "lo")
+        .filter_map(|entry| speed(&entry.file_name()))
+        .max()
+}
+
+/// The interface the kernel routes `endpoint`'s (first IPv4) address through,
+/// by longest prefix match over /proc/net/route.
+fn route_interface(endpoint: &str) -> Option<String> {
+    use std::net::ToSocketAddrs;
+    let uri: http::Uri = endpoint.parse().ok()?;
+    let host = uri.host()?;
+    let port = uri.port_u16().unwrap_or(if uri.scheme_str() == Some("http") { 80 } else { 443 });
+    let ip = (host, port).to_socket_addrs().ok()?.find_map(|addr| match addr.ip() {
+        std::net::IpAddr::V4(ip) => Some(u32::from(ip)),
+        std::net::IpAddr::V6(_) => None,
+    })?;
+    let routes = std::fs::read_to_string("/proc/net/route").ok()?;
+    routes
+        .lines()
+        .skip(1)
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            // Destination and Mask are the network-order bytes printed as a host-order hex word
+            let address = |hex: &str| u32::from_str_radix(hex, 16).ok().map(|word| u32::from_be_bytes(word.to_ne_bytes()));
+            let destination = address(fields.get(1)?)?;
+            let mask = address(fields.get(7)?)?;
+            (ip & mask == destination).then(|| (mask.count_ones(), fields[0].to_string()))
+        })
+        .max_by_key(|(prefix, _)| *prefix)
+        .map(|(_, interface)| interface)
+}
+
+/// Resets the high-water mark so it covers only the run about to start.
+/// Best-effort: elsewhere than Linux, the peak covers the whole process.
+fn reset_peak_rss() {
+    let _ = std::fs::write("/proc/self/clear_refs", "5");
+}
+
+/// Counts heap allocations for the `memory` results, at the cost of two
+/// relaxed atomic adds per allocation.
+#[cfg(feature = "alloc-stats")]
+struct CountingAllocator;
+
+#[cfg(feature = "alloc-stats")]
+static ALLOCATIONS: std::sync::atomic::AtomicU64 = std::sync::atomic::AtomicU64::new(0);
+#[cfg(feature = "alloc-stats")]
+static ALLOCATED_BYTES: std::sync::atomic::AtomicU64 = std::sync::atomic::AtomicU64::new(0);
+
+#[cfg(feature = "alloc-stats")]
+impl CountingAllocator {
+    fn count(size: usize) {
+        ALLOCATIONS.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        ALLOCATED_BYTES.fetch_add(size as u64, std::sync::atomic::Ordering::Relaxed);
+    }
+}
+
+#[cfg(feature = "alloc-stats")]
+unsafe impl std::alloc::GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: std::alloc::Layout) -> *mut u8 {
+        Self::count(layout.size());
+        std::alloc::GlobalAlloc::alloc(&std::alloc::System, layout)
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: std::alloc::Layout) -> *mut u8 {
+        Self::count(layout.size());
+        std::alloc::GlobalAlloc::alloc_zeroed(&std::alloc::System, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: std::alloc::Layout, new_size: usize) -> *mut u8 {
+        Self::count(new_size);
+        std::alloc::GlobalAlloc::realloc(&std::alloc::System, ptr, layout, new_size)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: std::alloc::Layout) {
+        std::alloc::GlobalAlloc::dealloc(&std::alloc::System, ptr, layout)
+    }
+}
+
+#[cfg(feature = "alloc-stats")]
+#[global_allocator]
+static GLOBAL_ALLOCATOR: CountingAllocator = CountingAllocator;
+
+/// Allocations and bytes allocated so far.
+#[cfg(feature = "alloc-stats")]
+fn allocation_counters() -> Option<(u64, u64)> {
+    Some((
+        ALLOCATIONS.load(std::sync::atomic::Ordering::Relaxed),
+        ALLOCATED_BYTES.load(std::sync::atomic::Ordering::Relaxed),
+    ))
+}
+
+#[cfg(not(feature = "alloc-stats"))]
+fn allocation_counters() -> Option<(u64, u64)> {
+    None
+}
+
+/// Failed requests captured so far with --debug-errors.
+struct DebugErrors {
+    limit: usize,
+    captured: std::sync::atomic::AtomicUsize,
+}
+
+/// Prints everything known about a failed request while fewer than
+/// --debug-errors have been captured, and stops the run at the last one.
+fn capture_debug_error(verb: &'static str, key: &str, latency_us: u64, error: &opendal::Error) {
+    let capture = with_run(|run| {
+        let capture = run.debug_errors.get()?;
+        Some((capture.captured.fetch_add(1, std::sync::atomic::Ordering::Relaxed), capture.limit))
+    });
+    let Some((index, limit)) = capture else {
+        return;
+    };
+    if index >= limit {
+        return;
+    }
+    let (retries, attempts) = OP_CONTEXT
+        .try_with(|context| (context.retries.get(), context.attempt_latencies()))
+        .unwrap_or_default();
+    let attempts: Vec<String> = attempts.iter().map(|attempt| format!("{:.2}", attempt.as_secs_f64() * 1000.0)).collect();
+    let unix_ms = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap_or_default().as_millis();
+    tracing::error!(
+        "🐞 Error {}/{}: {} {} failed after {:.2} ms at unix ms {} ({} retries, attempts [{}] ms)\n  kind: {}, temporary: {}\n{:#?}",
+        index + 1, limit, verb, key, latency_us as f64 / 1000.0, unix_ms, retries, attempts.join(", "),
+        error.kind(), error.is_temporary(), error,
+    );
+    if index + 1 == limit {
+        request_stop(&format!("Captured {} failed requests (--debug-errors)", limit));
+    }
+}
+
+/// Stands in for the duration of a run until stopped; far enough off to
+/// never end it, near enough that `Instant` arithmetic can't overflow.
+const UNTIL_STOPPED_LIMIT: Duration = Duration::from_secs(365 * 24 * 3600);
+
+/// Live latency bucket counts over a sliding window of samples.
+struct LiveLatencyWindow {
+    last: Vec<u64>,
+    /// Per-sample bucket counts, oldest first
+    samples: std::collections::VecDeque<Vec<u64>>,
+    capacity: usize,
+}
+
+impl LiveLatencyWindow {
+    fn new(live: &LiveMetrics, capacity: usize) -> Self {
+        LiveLatencyWindow {
+            last: live.buckets.iter().map(|count| count.load(std::sync::atomic::Ordering::Relaxed)).collect(),
+            samples: std::collections::VecDeque::new(),
+            capacity: capacity.max(1),
+        }
+    }
+
+    fn sample(&mut self, live: &LiveMetrics) {
+        let buckets: Vec<u64> = live.buckets.iter().map(|count| count.load(std::sync::atomic::Ordering::Relaxed)).collect();
+        if self.samples.len() == self.capacity {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(buckets.iter().zip(&self.last).map(|(now, before)| now - before).collect());
+        self.last = buckets;
+    }
+
+    /// The `quantile` of the latencies in the window, as the upper bound of
+    /// the live histogram bucket it falls in.
+    fn percentile(&self, quantile: f64) -> String {
+        let mut counts = vec![0u64; LIVE_LATENCY_BUCKETS.len() + 1];
+        for sample in &self.samples {
+            for (count, delta) in counts.iter_mut().zip(sample) {
+                *count += delta;
+            }
+        }
+        let total: u64 = counts.iter().sum();
+        if total == 0 {
+            return "-".to_string();
+        }
+        let target = ((total as f64 * quantile).ceil() as u64).max(1);
+        let mut cumulative = 0;
+        let bucket = counts.iter().position(|count| {
+            cumulative += count;
+            cumulative >= target
+        });
+        match bucket.and_then(|bucket| LIVE_LATENCY_BUCKETS.get(bucket)) {
+            Some(bound) => format!("≤ {} ms", (bound * 10_000.0).round() / 10.0),
+            None => format!("> {} ms", (LIVE_LATENCY_BUCKETS[LIVE_LATENCY_BUCKETS.len() - 1] * 1000.0).round()),
+        }
+    }
+}
+
+/// Logs a status event every `period` (ops so far, QPS, P99 over the last
+/// PERCENTILE_WINDOW or period, errors, in flight), so a long run doesn't
+/// look hung; with --log-format json each is a structured record.
+fn start_progress_reporter(period: Duration) -> tokio::task::JoinHandle<()> {
+    let live = live_metrics();
+    spawn_in_run(async move {
+        let load = |counter: &std::sync::atomic::AtomicU64| counter.load(std::sync::atomic::Ordering::Relaxed);
+        let mut ticker = tokio::time::interval(period);
+        ticker.tick().await;
+        let started = Instant::now();
+        let samples = PERCENTILE_WINDOW.as_millis().div_ceil(period.as_millis().max(1)) as usize;
+        let mut latencies = LiveLatencyWindow::new(&live, samples);
+        let (first_ok, first_err) = (load(&live.ok_ops), load(&live.err_ops));
+        let mut ok_ops = first_ok;
+        loop {
+            ticker.tick().await;
+            latencies.sample(&live);
+            let (ok, err) = (load(&live.ok_ops), load(&live.err_ops));
+            if !tui_active() {
+                tracing::info!(
+                    elapsed_s = started.elapsed().as_secs(),
+                    ops = ok - first_ok,
+                    qps = (ok - ok_ops) as f64 / period.as_secs_f64(),
+                    p99 = %latencies.percentile(0.99),
+                    errors = err - first_err,
+                    in_flight = live.in_flight.load(std::sync::atomic::Ordering::Relaxed),
+                    "progress"
+                );
+            }
+            ok_ops = ok;
+        }
+    })
+}
+
+/// How many seconds of QPS the dashboard's sparkline keeps.
+#[cfg(feature = "tui")]
+const TUI_HISTORY_SECONDS: usize = 600;
+
+/// The running --tui dashboard: a thread that redraws it and watches for keys.
+#[cfg(feature = "tui")]
+struct Tui {
+    stop: Arc<std::sync::atomic::AtomicBool>,
+    thread: std::thread::JoinHandle<()>,
+}
+
+#[cfg(not(feature = "tui"))]
+type Tui = ();
+
+/// What the dashboard has seen so far, sampled from the live metrics once a
+/// second.
+#[cfg(feature = "tui")]
+struct TuiView {
+    started: Instant,
+    ok_ops: u64,
+    err_ops: u64,
+    /// The last PERCENTILE_WINDOW, a second per sample
+    latencies: LiveLatencyWindow,
+    /// Per-second QPS, oldest first
+    qps: std::collections::VecDeque<u64>,
+    errors_per_second: u64,
+}
+
+#[cfg(feature = "tui")]
+impl TuiView {
+    fn new(live: &LiveMetrics) -> Self {
+        let load = |counter: &std::sync::atomic::AtomicU64| counter.load(std::sync::atomic::Ordering::Relaxed);
+        TuiView {
+            started: Instant::now(),
+            ok_ops: load(&live.ok_ops),
+            err_ops: load(&live.err_ops),
+            latencies: LiveLatencyWindow::new(live, PERCENTILE_WINDOW.as_secs() as usize),
+            qps: std::collections::VecDeque::new(),
+            errors_per_second: 0,
+        }
+    }
+
+    fn sample(&mut self, live: &LiveMetrics) {
+        let load = |counter: &std::sync::atomic::AtomicU64| counter.load(std::sync::atomic::Ordering::Relaxed);
+        let (ok_ops, err_ops) = (load(&live.ok_ops), load(&live.err_ops));
+        if self.qps.len() == TUI_HISTORY_SECONDS {
+            self.qps.pop_front();
+        }
+        self.qps.push_back(ok_ops - self.ok_ops);
+        self.errors_per_second = err_ops - self.err_ops;
+        self.latencies.sample(live);
+        (self.ok_ops, self.err_ops) = (ok_ops, err_ops);
+    }
+
+    fn render(&self, frame: &mut ratatui::Frame, live: &LiveMetrics) {
+        use ratatui::layout::{Constraint, Layout};
+        use ratatui::widgets::{Block, Paragraph, Sparkline};
+        
+        let [header, stats, chart, log] = Layout::vertical([
+            Constraint::Length(3),
+            Constraint::Length(3),
+            Constraint::Min(5),
+            Constraint::Length(TUI_LOG_LINES as u16 + 2),
+        ])
+        .areas(frame.area());
+        let mode = live.mode.lock().unwrap().clone();
+        let status = if interrupted() { "stopping…" } else { "q to stop" };
+        let header_text = format!("{}  ·  {:.0}s elapsed  ·  {} in flight  ·  {}", mode, self.started.elapsed().as_secs_f64(),
+                                  live.in_flight.load(std::sync::atomic::Ordering::Relaxed), status);
+        frame.render_widget(Paragraph::new(header_text).block(Block::bordered().title(" qps-bench ")), header);
+        
+        let stats_text = format!("QPS {}   P50 {}   P99 {}   ok {}   errors {} ({}/s)", self.qps.back().copied().unwrap_or(0),
+                                 self.latencies.percentile(0.5), self.latencies.percentile(0.99), self.ok_ops, self.err_ops, self.errors_per_second);
+        let stats_title = format!(" Last second (percentiles over {}s) ", PERCENTILE_WINDOW.as_secs());
+        frame.render_widget(Paragraph::new(stats_text).block(Block::bordered().title(stats_title)), stats);
+        
+        // The sparkline draws from the left, so keep just what fits
+        let width = chart.width.saturating_sub(2) as usize;
+        let history: Vec<u64> = self.qps.iter().skip(self.qps.len().saturating_sub(width)).copied().collect();
+        let peak = history.iter().max().copied().unwrap_or(0);
+        let chart_title = format!(" QPS, last {}s (peak {}) ", history.len(), peak);
+        frame.render_widget(Sparkline::default().block(Block::bordered().title(chart_title)).data(&history), chart);
+        
+        let lines: Vec<String> = with_run(|run| run.tui_log.lock().unwrap().iter().cloned().collect());
+        frame.render_widget(Paragraph::new(lines.join("\n")).block(Block::bordered().title(" Log ")), log);
+    }
+}
+
+/// Takes over the terminal with the --tui dashboard until finish_tui.
+#[cfg(feature = "tui")]
+fn start_tui() -> Result<Tui> {
+    use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+    use std::io::IsTerminal;
+    
+    if !std::io::stdout().is_terminal() {
+        return Err(RunFailure::new(FailureCategory::Config, "--tui needs stdout to be a terminal").into());
+    }
+    let run = current_run()?;
+    let live = live_metrics();
+    let mut terminal = ratatui::try_init().map_err(|e| anyhow::anyhow!("Failed to start the dashboard: {}", e))?;
+    run.tui_active.store(true, std::sync::atomic::Ordering::Relaxed);
+    let stop = Arc::new(std::sync::atomic::AtomicBool::new(false));
+    let stopping = stop.clone();
+    // The dashboard's thread stops and logs as part of the run
+    let thread = std::thread::spawn(move || RUN.sync_scope(run, || {
+        let mut view = TuiView::new(&live);
+        let mut next_sample = Instant::now() + Duration::from_secs(1);
+        while !stopping.load(std::sync::atomic::Ordering::Relaxed) {
+            if Instant::now() >= next_sample {
+                view.sample(&live);
+                next_sample += Duration::from_secs(1);
+            }
+            let _ = terminal.draw(|frame| view.render(frame, &live));
+            // Raw mode turns Ctrl-C into a key press rather than SIGINT
+            if !event::poll(Duration::from_millis(200)).unwrap_or(false) {
+                continue;
+            }
+            let Ok(Event::Key(key)) = event::read() else {
+                continue;
+            };
+            let ctrl_c = key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c');
+            if key.kind != KeyEventKind::Press || !(ctrl_c || key.code == KeyCode::Char('q')) {
+                continue;
+            }
+            if ctrl_c && interrupted() {
+                ratatui::restore();
+                with_run(|run| run.tui_active.store(false, std::sync::atomic::Ordering::Relaxed));
+                interrupt("Interrupted");
+                return;
+            }
+            request_stop("Stop requested from the dashboard");
+        }
+    }));
+    Ok(Tui { stop, thread })
+}
+
+#[cfg(not(feature = "tui"))]
+fn start_tui() -> Result<Tui> {
+    Err(RunFailure::new(FailureCategory::Config, "--tui requires building with the `tui` cargo feature").into())
+}
+
+/// Closes the dashboard and gives the terminal back to the log.
+#[cfg(feature = "tui")]
+fn finish_tui(tui: Tui) {
+    tui.stop.store(true, std::sync::atomic::Ordering::Relaxed);
+    let _ = tui.thread.join();
+    ratatui::restore();
+    with_run(|run| run.tui_active.store(false, std::sync::atomic::Ordering::Relaxed));
+}
+
+#[cfg(not(feature = "tui"))]
+fn finish_tui(_tui: Tui) {}
+
+/// Serves --control-socket until the run ends.
+#[cfg(unix)]
+fn start_control_socket(path: &std::path::Path) -> Result<tokio::task::JoinHandle<()>> {
+    // A socket left behind by an earlier run would make the bind fail
+    let _ = std::fs::remove_file(path);
+    let listener = tokio::net::UnixListener::bind(path)
+        .map_err(|e| RunFailure::new(FailureCategory::Config, format!("Failed to listen on {}: {}", path.display(), e)))?;
+    tracing::info!("🎛️  Listening for control commands on {}", path.display());
+    let live = live_metrics();
+    Ok(spawn_in_run(async move {
+        use tokio::io::{AsyncBufReadExt, AsyncWriteExt};
+        while let Ok((stream, _)) = listener.accept().await {
+            let live = live.clone();
+            spawn_in_run(async move {
+                let (reader, mut writer) = stream.into_split();
+                let mut lines = tokio::io::BufReader::new(reader).lines();
+                while let Ok(Some(line)) = lines.next_line().await {
+                    let reply = match line.trim() {
+                        "stop" => {
+                            request_stop("Stop requested on the control socket");
+                            "ok".to_string()
+                        }
+                        "status" => serde_json::json!({
+                            "mode": *live.mode.lock().unwrap(),
+                            "ok_ops": live.ok_ops.load(std::sync::atomic::Ordering::Relaxed),
+                            "err_ops": live.err_ops.load(std::sync::atomic::Ordering::Relaxed),
+                            "in_flight": live.in_flight.load(std::sync::atomic::Ordering::Relaxed),
+                            "stopping": interrupted(),
+                        })
+                        .to_string(),
+                        other => format!("error: unknown command '{}' (expected stop or status)", other),
+                    };
+                    if writer.write_all(format!("{}\n", reply).as_bytes()).await.is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+    }))
+}
+
+#[cfg(not(unix))]
+fn start_control_socket(_path: &std::path::Path) -> Result<tokio::task::JoinHandle<()>> {
+    Err(RunFailure::new(FailureCategory::Config, "--control-socket needs a Unix platform").into())
+}
+
+/// Counts an op as in flight until dropped.
+struct LiveInFlight;
+
+impl LiveInFlight {
+    fn start() -> Self {
+        with_live_metrics(|live| {
+            live.in_flight.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        });
+        LiveInFlight
+    }
+}
+
+impl Drop for LiveInFlight {
+    fn drop(&mut self) {
+        with_live_metrics(|live| {
+            live.in_flight.fetch_sub(1, std::sync::atomic::Ordering::Relaxed);
+        });
+    }
+}
+
+/// Renders the live metrics in the Prometheus text exposition format.
+fn render_live_metrics(live: &LiveMetrics) -> String {
+    let load = |counter: &std::sync::atomic::AtomicU64| counter.load(std::sync::atomic::Ordering::Relaxed);
+    let mut text = String::new();
+    text.push_str("# HELP qps_bench_run_info Mode currently running.\n# TYPE qps_bench_run_info gauge\n");
+    text.push_str(&format!("qps_bench_run_info{{mode=\"{}\"}} 1\n", prometheus_label(&live.mode.lock().unwrap())));
+    text.push_str("# HELP qps_bench_ops_total Completed benchmark operations.\n# TYPE qps_bench_ops_total counter\n");
+    text.push_str(&format!("qps_bench_ops_total{{result=\"ok\"}} {}\n", load(&live.ok_ops)));
+    text.push_str(&format!("qps_bench_ops_total{{result=\"error\"}} {}\n", load(&live.err_ops)));
+    text.push_str("# HELP qps_bench_in_flight_ops Operations currently in flight.\n# TYPE qps_bench_in_flight_ops gauge\n");
+    text.push_str(&format!("qps_bench_in_flight_ops {}\n", live.in_flight.load(std::sync::atomic::Ordering::Relaxed)));
+    text.push_str("# HELP qps_bench_op_latency_seconds Latency of successful operations.\n# TYPE qps_bench_op_latency_seconds histogram\n");
+    let mut cumulative = 0;
+    for (bound, count) in LIVE_LATENCY_BUCKETS.iter().zip(&live.buckets) {
+        cumulative += load(count);
+        text.push_str(&format!("qps_bench_op_latency_seconds_bucket{{le=\"{}\"}} {}\n", bound, cumulative));
+    }
+    cumulative += load(&live.buckets[LIVE_LATENCY_BUCKETS.len()]);
+    text.push_str(&format!("qps_bench_op_latency_seconds_bucket{{le=\"+Inf\"}} {}\n", cumulative));
+    text.push_str(&format!("qps_bench_op_latency_seconds_sum {}\n", load(&live.latency_sum_us) as f64 / 1_000_000.0));
+    text.push_str(&format!("qps_bench_op_latency_seconds_count {}\n", cumulative));
+    text
+}
+
+/// Starts tracking live metrics and serves them at `addr`/metrics until exit.
+async fn start_metrics_server(addr: &str) -> Result<tokio::task::JoinHandle<()>> {
+    use http_body_util::Full;
+    
+    let listener = tokio::net::TcpListener::bind(addr)
+        .await
+        .map_err(|e| RunFailure::new(FailureCategory::Config, format!("Failed to listen on {}: {}", addr, e)))?;
+    let live = live_metrics();
+    tracing::info!("📈 Serving Prometheus metrics at http://{}/metrics", addr);
+    
+    let service = hyper::service::service_fn(move |request: hyper::Request<hyper::body::Incoming>| {
+        let live = live.clone();
+        async move {
+            let response = hyper::Response::builder();
+            match (request.method(), request.uri().path()) {
+                (&hyper::Method::GET, "/metrics") => response
+                    .header(hyper::header::CONTENT_TYPE, "text/plain; version=0.0.4")
+                    .body(Full::new(bytes::Bytes::from(render_live_metrics(&live)))),
+                (_, "/metrics") => response.status(hyper::StatusCode::METHOD_NOT_ALLOWED).body(Full::default()),
+                _ => response.status(hyper::StatusCode::NOT_FOUND).body(Full::default()),
+            }
+        }
+    });
+    Ok(spawn_in_run(async move {
+        loop {
+            let Ok((stream, _)) = listener.accept().await else {
+                continue;
+            };
+            tokio::spawn(hyper::server::conn::http1::Builder::new().serve_connection(hyper_util::rt::TokioIo::new(stream), service.clone()));
+        }
+    }))
+}
+
+/// Resolves the effective addressing style to "path" or "virtual".
+///
+/// AWS serves virtual-hosted requests natively (path-style is deprecated there),
+/// while most S3-compatible services (MinIO, Ceph) expect path-style.
+fn resolve_addressing_style(backend: &BackendArgs) -> Result<&'static str> {
+    if backend.force_path_style {
+        return Ok("path");
+    }
+    match backend.addressing_style.as_str() {
+        "path" => Ok("path"),
+        "virtual" => Ok("virtual"),
+        "auto" if backend.endpoint.contains("amazonaws.com") => Ok("virtual"),
+        "auto" => Ok("path"),
+        other => anyhow::bail!("Unknown addressing style: {}. Supported styles: auto, path, virtual", other),
+    }
+}
+
+fn backend_info(backend: &BackendArgs) -> BackendInfo {
+    BackendInfo {
+        service: backend.service.clone(),
+        endpoint: backend.endpoint.clone(),
+        region: backend.region.clone(),
+        bucket: backend.bucket.clone(),
+        addressing_style: resolve_addressing_style(backend).unwrap_or("path").to_string(),
+        client: backend.client.clone(),
+        transfer_engine: backend.transfer_engine.clone(),
+    }
+}
+
+/// Version of the reproducibility bundle layout.
+const BUNDLE_FORMAT_VERSION: u32 = 1;
+
+#[derive(Debug, Serialize, Deserialize)]
+struct BundleInfo {
+    format_version: u32,
+    tool_version: String,
+    created_at_unix: u64,
+    seed: u64,
+    prefix: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct DatasetManifest {
+    prefix: String,
+    object_size_bytes: usize,
+    keys: Vec<String>,
+    /// Set while the dataset is still being created; such a manifest is a
+    /// checkpoint that the next run resumes from
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    incomplete: bool,
+    /// Objects hold `seeded_content` rather than zeros, so --verify-reads can check them
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    seeded: bool,
+}
+
+impl DatasetManifest {
+    fn new(prefix: String, object_size_bytes: usize) -> Self {
+        DatasetManifest { prefix, object_size_bytes, keys: Vec::new(), incomplete: false, seeded: true }
+    }
+
+    fn load(path: &std::path::Path) -> Result<Self> {
+        let file = std::fs::File::open(path)
+            .map_err(|e| anyhow::anyhow!("Failed to open dataset manifest {}: {}", path.display(), e))?;
+        Ok(serde_json::from_reader(std::io::BufReader::new(file))?)
+    }
+
+    fn save(&self, path: &std::path::Path) -> Result<()> {
+        let file = std::fs::File::create(path)
+            .map_err(|e| anyhow::anyhow!("Failed to create dataset manifest {}: {}", path.display(), e))?;
+        serde_json::to_writer_pretty(std::io::BufWriter::new(file), self)?;
+        Ok(())
+    }
+}
+
+#[cfg(feature = "bundle")]
+#[derive(Debug, Serialize, Deserialize)]
+struct EnvironmentInfo {
+    hostname: Option<String>,
+    os: String,
+    arch: String,
+    cpus: usize,
+}
+
+fn hostname() -> Option<String> {
+    std::env::var("HOSTNAME").ok().or_else(|| {
+        std::fs::read_to_string("/etc/hostname")
+            .ok()
+            .map(|s| s.trim().to_string())
+    })
+}
+
+#[cfg(feature = "bundle")]
+impl EnvironmentInfo {
+    fn collect() -> Self {
+        EnvironmentInfo {
+            hostname: hostname(),
+            os: std::env::consts::OS.to_string(),
+            arch: std::env::consts::ARCH.to_string(),
+            cpus: std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1),
+        }
+    }
+}
+
+#[cfg(feature = "bundle")]
+fn append_bundle_file<W: std::io::Write>(tar: &mut tar::Builder<W>, name: &str, value: &impl Serialize) -> Result<()> {
+    let contents = serde_json::to_vec_pretty(value)?;
+    let mut header = tar::Header::new_gnu();
+    header.set_size(contents.len() as u64);
+    header.set_mode(0o644);
+    header.set_mtime(
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs(),
+    );
+    tar.append_data(&mut header, name, contents.as_slice())?;
+    Ok(())
+}
+
+/// Writes everything needed to reproduce or audit a run into one
+/// zstd-compressed tarball. Credentials, headers and proxy settings are never
+/// included.
+#[cfg(feature = "bundle")]
+fn export_bundle(path: &std::path::Path, args: &Args, seed: u64, manifest: &DatasetManifest, results: &serde_json::Value) -> Result<()> {
+    let info = BundleInfo {
+        format_version: BUNDLE_FORMAT_VERSION,
+        tool_version: env!("CARGO_PKG_VERSION").to_string(),
+        created_at_unix: std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs(),
+        seed,
+        prefix: manifest.prefix.clone(),
+    };
+    
+    let file = std::fs::File::create(path)
+        .map_err(|e| anyhow::anyhow!("Failed to create bundle {}: {}", path.display(), e))?;
+    let encoder = zstd::stream::write::Encoder::new(file, 3)?.auto_finish();
+    let mut tar = tar::Builder::new(encoder);
+    append_bundle_file(&mut tar, "bundle.json", &info)?;
+    append_bundle_file(&mut tar, "config.json", args)?;
+    append_bundle_file(&mut tar, "manifest.json", manifest)?;
+    append_bundle_file(&mut tar, "results.json", results)?;
+    append_bundle_file(&mut tar, "environment.json", &EnvironmentInfo::collect())?;
+    tar.into_inner()?;
+    
+    tracing::info!("📦 Wrote reproducibility bundle to {}", path.display());
+    Ok(())
+}
+
+#[cfg(not(feature = "bundle"))]
+fn export_bundle(_path: &std::path::Path, _args: &Args, _seed: u64, _manifest: &DatasetManifest, _results: &serde_json::Value) -> Result<()> {
+    Err(RunFailure::new(FailureCategory::Config, "--export-bundle requires building with the `bundle` feature").into())
+}
+
+/// Reads every member of a bundle written by `export_bundle`.
+#[cfg(feature = "bundle")]
+fn read_bundle(path: &std::path::Path) -> Result<BTreeMap<String, Vec<u8>>> {
+    let file = std::fs::File::open(path)
+        .map_err(|e| anyhow::anyhow!("Failed to open bundle {}: {}", path.display(), e))?;
+    let mut archive = tar::Archive::new(zstd::stream::read::Decoder::new(file)?);
+    let mut files = BTreeMap::new();
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let name = entry.path()?.to_string_lossy().into_owned();
+        let mut contents = Vec::new();
+        entry.read_to_end(&mut contents)?;
+        files.insert(name, contents);
+    }
+    Ok(files)
+}
+
+#[cfg(not(feature = "bundle"))]
+fn read_bundle(_path: &std::path::Path) -> Result<BTreeMap<String, Vec<u8>>> {
+    Err(RunFailure::new(FailureCategory::Config, "Reading bundles requires building with the `bundle` feature").into())
+}
+
+fn bundle_member<T: serde::de::DeserializeOwned>(files: &BTreeMap<String, Vec<u8>>, name: &str) -> Result<T> {
+    let contents = files
+        .get(name)
+        .ok_or_else(|| anyhow::anyhow!("Bundle is missing {}", name))?;
+    serde_json::from_slice(contents).map_err(|e| anyhow::anyhow!("Invalid {} in bundle: {}", name, e))
+}
+
+/// The run settings with nothing given: the declared command-line defaults.
+/// QPS_BENCH_* variables are left to the command line itself.
+fn default_args() -> Result<Args> {
+    let command = <Args as clap::Args>::augment_args(clap::Command::new("qps-bench")).mut_args(|arg| arg.env(None));
+    Ok(Args::from_arg_matches(&command.try_get_matches_from(["qps-bench"])?)?)
+}
+
+/// Reads a config.json written by this or an earlier build. Settings added
+/// since the file was written take their command-line defaults, so older
+/// bundles keep loading without a format version bump per new flag.
+fn args_from_config(config: serde_json::Value) -> Result<Args> {
+    let mut merged = serde_json::to_value(default_args()?)?;
+    if let (Some(merged), serde_json::Value::Object(config)) = (merged.as_object_mut(), config) {
+        merged.extend(config);
+    }
+    serde_json::from_value(merged).map_err(|e| anyhow::anyhow!("Invalid config.json in bundle: {}", e))
+}
+
+/// Re-executes the workload recorded in a bundle (same config and seed)
+/// against the backend given on the command line and compares the outcome
+/// with the bundled results.
+async fn rerun_bundle(rerun: RerunArgs) -> Result<()> {
+    let files = read_bundle(&rerun.bundle)?;
+    let info: BundleInfo = bundle_member(&files, "bundle.json")?;
+    if info.format_version != BUNDLE_FORMAT_VERSION {
+        anyhow::bail!("Unsupported bundle format version {} (expected {})", info.format_version, BUNDLE_FORMAT_VERSION);
+    }
+    let mut args = args_from_config(bundle_member(&files, "config.json")?)?;
+    let baseline: serde_json::Value = bundle_member(&files, "results.json")?;
+    
+    args.backend = rerun.backend;
+    args.seed = Some(info.seed);
+    tracing::info!("🔁 Re-running bundle {} (recorded by qps-bench {}, seed {})", rerun.bundle.display(), info.tool_version, info.seed);
+    
+    check_workload_settings(&args)?;
+    let current = run_benchmark(&args, None).await?;
+    let comparisons = compare_results(&baseline, &current)?;
+    
+    report!();
+    report!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+    report!("📊 Comparison vs Bundle (JSON)");
+    report!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+    report!("{}", serde_json::to_string_pretty(&comparisons)?);
+    
+    report!();
+    report!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+    report!("📊 Comparison vs Bundle (Human-readable)");
+    report!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+    for comparison in &comparisons {
+        report!("{} ({} -> {})", comparison.label, comparison.baseline_endpoint, comparison.current_endpoint);
+        for delta in &comparison.metrics {
+            let change = delta
+                .change_pct
+                .map(|pct| format!("{:+.1}%", pct))
+                .unwrap_or_else(|| "n/a".to_string());
+            report!("  {:<18} {:>14.2} {:>14.2} {:>10}", delta.metric, delta.baseline, delta.current, change);
+        }
+    }
+    
+    Ok(())
+}
+
+/// The OpenTelemetry layer of the global subscriber, set once trace export starts.
+#[cfg(feature = "otlp-traces")]
+type TraceExportLayer = tracing_opentelemetry::OpenTelemetryLayer<tracing_subscriber::Registry, opentelemetry_sdk::trace::Tracer>;
+
+#[cfg(feature = "otlp-traces")]
+static TRACE_EXPORT: std::sync::OnceLock<tracing_subscriber::reload::Handle<Option<TraceExportLayer>, tracing_subscriber::Registry>> =
+    std::sync::OnceLock::new();
+
+/// Exports spans of a `ratio` sample of ops to the collector's OTLP/HTTP
+/// traces endpoint. Sampling is decided at each op's root span, so an op's
+/// OpenDAL spans are kept or dropped together.
+#[cfg(feature = "otlp-traces")]
+fn init_trace_export(endpoint: &str, ratio: f64) -> Result<opentelemetry_sdk::trace::TracerProvider> {
+    use opentelemetry::trace::TracerProvider as _;
+    use opentelemetry_otlp::WithExportConfig;
+    use opentelemetry_sdk::trace::Sampler;
+    
+    let Some(trace_export) = TRACE_EXPORT.get() else {
+        anyhow::bail!("Logging must be initialized before trace export");
+    };
+    let exporter = opentelemetry_otlp::SpanExporter::builder()
+        .with_http()
+        .with_endpoint(format!("{}/v1/traces", endpoint.trim_end_matches('/')))
+        .build()?;
+    let provider = opentelemetry_sdk::trace::TracerProvider::builder()
+        .with_batch_exporter(exporter, opentelemetry_sdk::runtime::Tokio)
+        .with_sampler(Sampler::ParentBased(Box::new(Sampler::TraceIdRatioBased(ratio))))
+        .with_resource(opentelemetry_sdk::Resource::new([
+            opentelemetry::KeyValue::new("service.name", "qps-bench"),
+            opentelemetry::KeyValue::new("service.version", env!("CARGO_PKG_VERSION")),
+        ]))
+        .build();
+    trace_export.reload(Some(tracing_opentelemetry::layer().with_tracer(provider.tracer("qps-bench"))))?;
+    tracing::info!("🔭 Tracing {:.3}% of ops to {}", ratio * 100.0, endpoint);
+    Ok(provider)
+}
+
+#[cfg(not(feature = "otlp-traces"))]
+fn init_trace_export(_endpoint: &str, _ratio: f64) -> Result<()> {
+    Err(RunFailure::new(FailureCategory::Config, "--trace-sample-ratio requires building with the `otlp-traces` feature").into())
+}
+
+/// Flushes the spans still buffered for export and takes the exporter back
+/// out of the global subscriber, which outlives the run.
+#[cfg(feature = "otlp-traces")]
+fn shutdown_trace_export(provider: opentelemetry_sdk::trace::TracerProvider) {
+    if let Err(e) = provider.shutdown() {
+        tracing::warn!(error = %e, "Failed to flush traces");
+    }
+    if let Some(trace_export) = TRACE_EXPORT.get() {
+        let _ = trace_export.reload(None);
+    }
+}
+
+#[cfg(not(feature = "otlp-traces"))]
+fn shutdown_trace_export(_provider: ()) {}
+
+/// Samples the process's stacks this many times per second for --pprof.
+#[cfg(feature = "pprof")]
+const PPROF_FREQUENCY: i32 = 99;
+
+#[cfg(feature = "pprof")]
+fn start_profiler() -> Result<pprof::ProfilerGuard<'static>> {
+    Ok(pprof::ProfilerGuardBuilder::default()
+        .frequency(PPROF_FREQUENCY)
+        .blocklist(&["libc", "libgcc", "pthread", "vdso"])
+        .build()?)
+}
+
+#[cfg(not(feature = "pprof"))]
+fn start_profiler() -> Result<()> {
+    Err(RunFailure::new(FailureCategory::Config, "--pprof requires building with the `pprof` cargo feature").into())
+}
+
+/// Writes the profile collected since `start_profiler` to `path`, with how
+/// many cores the client kept busy on average: close to the pod's CPU limit
+/// means the client, not the backend, bounded the run.
+#[cfg(feature = "pprof")]
+fn finish_profiler(profiler: pprof::ProfilerGuard<'static>, path: &std::path::Path, elapsed: Duration) -> Result<()> {
+    use pprof::protos::Message;
+    
+    let report = profiler.report().build()?;
+    let samples: isize = report.data.values().sum();
+    let mut content = Vec::new();
+    report.pprof()?.encode(&mut content)?;
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        std::fs::create_dir_all(dir).map_err(|e| anyhow::anyhow!("Failed to create {}: {}", dir.display(), e))?;
+    }
+    std::fs::write(path, content).map_err(|e| anyhow::anyhow!("Failed to write {}: {}", path.display(), e))?;
+    let busy_cores = samples as f64 / PPROF_FREQUENCY as f64 / elapsed.as_secs_f64().max(f64::EPSILON);
+    tracing::info!("🔬 Wrote CPU profile to {} (client kept {:.2} cores busy on average)", path.display(), busy_cores);
+    Ok(())
+}
+
+#[cfg(not(feature = "pprof"))]
+fn finish_profiler(_profiler: (), _path: &std::path::Path, _elapsed: Duration) -> Result<()> {
+    Ok(())
+}
+
+/// Rejects a run whose settings `config_conflicts` finds a problem with, for
+/// the command line and library runs alike, before anything is written to the
+/// backend.
+fn check_workload_settings(args: &Args) -> Result<()> {
+    let problems = config_conflicts(args);
+    if problems.is_empty() {
+        return Ok(());
+    }
+    Err(RunFailure::new(FailureCategory::Config, problems.join("; ")).into())
+}
+
+/// The background services a run started. `finish` stops and flushes them
+/// once the run is over; dropping them before that (an early error, the
+/// --max-run-time cutoff) aborts whatever is left and removes the control
+/// socket.
+#[derive(Default)]
+struct RunServices {
+    tasks: Vec<tokio::task::JoinHandle<()>>,
+    control_socket: Option<std::path::PathBuf>,
+    /// The exporter task, with the settings its last export is sent with
+    #[cfg(feature = "otlp-metrics")]
+    otlp_exporter: Option<(tokio::task::JoinHandle<()>, Box<Args>)>,
+    event_sink: Option<(tokio::sync::oneshot::Sender<()>, tokio::task::JoinHandle<()>)>,
+    statsd: Option<(Arc<tokio::sync::Mutex<StatsdSink>>, tokio::task::JoinHandle<()>)>,
+    op_log: Option<(std::thread::JoinHandle<std::io::Result<()>>, std::path::PathBuf)>,
+}
+
+impl RunServices {
+    async fn finish(&mut self) {
+        for task in self.tasks.drain(..) {
+            task.abort();
+        }
+        if let Some(path) = self.control_socket.take() {
+            let _ = std::fs::remove_file(path);
+        }
+        #[cfg(feature = "otlp-metrics")]
+        if let Some((exporter, args)) = self.otlp_exporter.take() {
+            exporter.abort();
+            // Flush what happened since the last tick
+            if let Some(endpoint) = &args.otlp_endpoint {
+                export_otlp(endpoint, &render_otlp_metrics(&args, &live_metrics())).await;
+            }
+        }
+        if let Some((stop, publisher)) = self.event_sink.take() {
+            let _ = stop.send(());
+            let _ = publisher.await;
+            let dropped = with_run(|run| run.op_events.get().map_or(0, |queue| queue.dropped.load(std::sync::atomic::Ordering::Relaxed)));
+            if dropped > 0 {
+                tracing::warn!("⚠️  {} op events dropped: the event sink could not keep up (raise --event-sink-buffer)", dropped);
+            }
+        }
+        if let Some((sink, flusher)) = self.statsd.take() {
+            flusher.abort();
+            // Flush what happened since the last tick
+            sink.lock().await.flush().await;
+        }
+        if let Some((writer, path)) = self.op_log.take() {
+            let dropped = with_run(|run| {
+                let log = run.op_log.get()?;
+                let _ = log.sender.send(None);
+                Some(log.dropped.load(std::sync::atomic::Ordering::Relaxed))
+            });
+            if let Some(dropped) = dropped.filter(|dropped| *dropped > 0) {
+                tracing::warn!(dropped, "⚠️  The op log writer fell behind; {} requests weren't logged", dropped);
+            }
+            match writer.join() {
+                Ok(Ok(())) => tracing::info!("💾 Wrote op log to {}", path.display()),
+                Ok(Err(e)) => tracing::warn!(path = %path.display(), error = %e, "Failed to write op log"),
+                Err(_) => tracing::warn!("The op log writer panicked"),
+            }
+        }
+    }
+}
+
+impl Drop for RunServices {
+    fn drop(&mut self) {
+        for task in &self.tasks {
+            task.abort();
+        }
+        if let Some(path) = &self.control_socket {
+            let _ = std::fs::remove_file(path);
+        }
+        #[cfg(feature = "otlp-metrics")]
+        if let Some((exporter, _)) = &self.otlp_exporter {
+            exporter.abort();
+        }
+        if let Some((_, publisher)) = &self.event_sink {
+            publisher.abort();
+        }
+        if let Some((_, flusher)) = &self.statsd {
+            flusher.abort();
+        }
+        // The writer thread drains what was queued and exits
+        if self.op_log.is_some() {
+            with_run(|run| {
+                if let Some(log) = run.op_log.get() {
+                    let _ = log.sender.send(None);
+                }
+            });
+        }
+    }
+}
+
+/// Runs the benchmark, evaluates the SLA thresholds and hands the outcome to
+/// the result sinks. Every setting is checked before any service is started.
+async fn run_and_notify(args: &Args, dataset: Option<&DatasetManifest>) -> Result<()> {
+    check_workload_settings(args)?;
+    // Named before the run so the directory and uploaded keys carry its start time
+    let run_name = results_run_name(args);
+    let run_dir = args.results_dir.as_deref().map(|dir| dir.join(&run_name));
+    let sinks = result_sinks(args, &run_name, run_dir.as_deref())?;
+    let run = current_run()?;
+    if args.until_stopped() {
+        run.until_stopped.store(true, std::sync::atomic::Ordering::Relaxed);
+    }
+    if let Some(limit) = args.debug_errors {
+        let _ = run.debug_errors.set(DebugErrors { limit, captured: std::sync::atomic::AtomicUsize::new(0) });
+    }
+    
+    let mut services = RunServices::default();
+    if let Some(addr) = &args.prometheus_listen {
+        services.tasks.push(start_metrics_server(addr).await?);
+    }
+    if let Some(seconds) = args.max_run_time {
+        // Tracked from the start so a run cut off at the deadline can still report
+        live_metrics();
+        services.tasks.push(start_run_deadline(Duration::from_secs(seconds)));
+    }
+    if args.progress_interval_seconds > 0 {
+        services.tasks.push(start_progress_reporter(Duration::from_secs(args.progress_interval_seconds)));
+    }
+    if args.stream_events {
+        run.stream_events.store(true, std::sync::atomic::Ordering::Relaxed);
+        services.tasks.push(start_event_stream(args.timeseries_interval_seconds.map_or(SERIES_INTERVAL, |seconds| Duration::from_secs(seconds.max(1)))));
+    }
+    if let Some(path) = &args.control_socket {
+        services.tasks.push(start_control_socket(path)?);
+        services.control_socket = Some(path.clone());
+    }
+    if let (Some(base), Some(seconds)) = (&args.pushgateway_url, args.pushgateway_interval_seconds) {
+        let live = live_metrics();
+        let url = pushgateway_group_url(args, base);
+        services.tasks.push(spawn_in_run(async move {
+            let mut ticker = tokio::time::interval(Duration::from_secs(seconds.max(1)));
+            loop {
+                ticker.tick().await;
+                push_metrics(&url, render_live_metrics(&live)).await;
+            }
+        }));
+    }
+    #[cfg(feature = "otlp-metrics")]
+    if let Some(endpoint) = &args.otlp_endpoint {
+        let live = live_metrics();
+        let (task_args, task_endpoint) = (args.clone(), endpoint.clone());
+        let exporter = spawn_in_run(async move {
+            let mut ticker = tokio::time::interval(Duration::from_secs(task_args.otlp_interval_seconds.max(1)));
+            loop {
+                ticker.tick().await;
+                export_otlp(&task_endpoint, &render_otlp_metrics(&task_args, &live)).await;
+            }
+        });
+        services.otlp_exporter = Some((exporter, Box::new(args.clone())));
+    }
+    if let Some(url) = &args.event_sink {
+        services.event_sink = Some(start_event_sink(url, args.event_sink_buffer).await?);
+    }
+    if let Some(path) = &args.op_log {
+        services.op_log = Some((start_op_log(path)?, path.clone()));
+    }
+    if args.statsd_addr.is_some() {
+        let sink = Arc::new(tokio::sync::Mutex::new(StatsdSink::connect(args).await?));
+        let ticking = sink.clone();
+        let flusher = spawn_in_run(async move {
+            let mut ticker = tokio::time::interval(Duration::from_secs(1));
+            loop {
+                ticker.tick().await;
+                ticking.lock().await.flush().await;
+            }
+        });
+        services.statsd = Some((sink, flusher));
+    }
+    let tracer_provider = match (args.backend.trace_sample_ratio, &args.otlp_endpoint) {
+        (Some(ratio), Some(endpoint)) => Some(init_trace_export(endpoint, ratio)?),
+        _ => None,
+    };
+    let profiler = match &args.pprof {
+        Some(_) => Some((start_profiler()?, Instant::now())),
+        None => None,
+    };
+    let mut tui = if args.tui { Some(start_tui()?) } else { None };
+    let tail = async {
+        let results = run_benchmark(args, dataset).await;
+        if let Some(tui) = tui.take() {
+            finish_tui(tui);
+            if let Ok(results) = &results {
+                for (_, result) in labelled_results(results)? {
+                    print_result(&result)?;
+                }
+            }
+        }
+        if let (Some((profiler, started)), Some(path)) = (profiler, &args.pprof) {
+            let path = match &run_dir {
+                Some(dir) if path.is_relative() => dir.join(path),
+                _ => path.clone(),
+            };
+            if let Err(e) = finish_profiler(profiler, &path, started.elapsed()) {
+                tracing::warn!(error = %e, "Failed to write CPU profile");
+            }
+        }
+        if let Some(provider) = tracer_provider {
+            shutdown_trace_export(provider);
+        }
+        services.finish().await;
+    
+        let results = match results {
+            Ok(results) => results,
+            Err(e) => {
+                emit_event("finished", serde_json::json!({ "status": "aborted", "error": format!("{:#}", e) }));
+                if args.gha {
+                    println!("::error title=qps-bench::{}", gha_escape(&format!("{} run aborted: {:#}", args.mode, e), false));
+                }
+                for sink in &sinks {
+                    sink.abort(args, &e).await;
+                }
+                return Err(e);
+            }
+        };
+    
+        let checks = check_sla(args, &results)?;
+        let breaches: Vec<SlaCheck> = checks.iter().filter(|check| !check.passed).cloned().collect();
+        let outcome = RunOutcome { args, results: &results, breaches: &breaches };
+        for sink in &sinks {
+            sink.write(&outcome).await?;
+        }
+    
+        if !args.sla.is_empty() {
+            report!();
+            report!("SLA checks:");
+            for check in &checks {
+                report!("  {} {} {} = {:.2} (limit {:.2})", if check.passed { "✅" } else { "❌" }, check.label, check.metric, check.actual, check.threshold);
+            }
+        }
+        for breach in &breaches {
+            tracing::warn!("❌ SLA breach: {} {} = {:.2} (limit {:.2})", breach.label, breach.metric, breach.actual, breach.threshold);
+        }
+        if args.stream_events {
+            for (label, result) in labelled_results(&results)? {
+                emit_event("result", serde_json::json!({ "label": label, "result": result }));
+            }
+            let status = if !breaches.is_empty() {
+                "sla_breach"
+            } else if stopped_early() {
+                "interrupted"
+            } else {
+                "completed"
+            };
+            emit_event("finished", serde_json::json!({ "status": status, "sla_breaches": breaches }));
+        }
+        if args.gha {
+            report_github_actions(&results, &breaches)?;
+        }
+    
+        if !breaches.is_empty() {
+            let message = format!("{} SLA threshold(s) breached", breaches.len());
+            return Err(RunFailure::new(FailureCategory::SlaBreach, message).with_results(results).into());
+        }
+        if stopped_early() {
+            return Err(RunFailure::new(FailureCategory::Interrupted, "Run interrupted; results are partial").with_results(results).into());
+        }
+        Ok::<(), anyhow::Error>(())
+    };
+    // At --max-run-time itself, whatever is still running (in-flight ops with
+    // no --op-timeout, result sinks, uploads) is abandoned for a partial report
+    let Some(left) = run_time_left() else {
+        return tail.await;
+    };
+    let finished = tokio::time::timeout(left, tail).await;
+    match finished {
+        Ok(result) => result,
+        Err(_) => {
+            if let Some(tui) = tui.take() {
+                finish_tui(tui);
+            }
+            let report = write_deadline_report(args, run_dir.as_deref());
+            emit_event("finished", serde_json::json!({ "status": "deadline_exceeded" }));
+            let message = format!("--max-run-time of {}s ran out before the run finished; results are partial", args.max_run_time.unwrap_or_default());
+            Err(RunFailure::new(FailureCategory::DeadlineExceeded, message).with_results(report).into())
+        }
+    }
+}
+
+/// What --max-run-time cut short: the live counters so far, written to the
+/// run directory, or to `qps-bench-partial-<run id>.json` without one.
+fn write_deadline_report(args: &Args, run_dir: Option<&std::path::Path>) -> serde_json::Value {
+    let live = live_metrics();
+    let report = serde_json::json!({
+        "status": "deadline_exceeded",
+        "metadata": run_metadata(),
+        "max_run_time_s": args.max_run_time,
+        "mode": *live.mode.lock().unwrap(),
+        "ok_ops": live.ok_ops.load(std::sync::atomic::Ordering::Relaxed),
+        "err_ops": live.err_ops.load(std::sync::atomic::Ordering::Relaxed),
+        "in_flight": live.in_flight.load(std::sync::atomic::Ordering::Relaxed),
+    });
+    let path = match run_dir {
+        Some(dir) => dir.join("partial.json"),
+        None => std::path::PathBuf::from(format!("qps-bench-partial-{}.json", run_metadata().run_id)),
+    };
+    let written = run_dir
+        .map_or(Ok(()), std::fs::create_dir_all)
+        .and_then(|()| std::fs::write(&path, serde_json::to_string_pretty(&report).unwrap_or_default()));
+    match written {
+        Ok(()) => tracing::warn!(path = %path.display(), "⏱️  --max-run-time ran out; wrote a partial report"),
+        Err(e) => tracing::warn!(path = %path.display(), error = %e, "failed to write the partial report"),
+    }
+    report
+}
+
+/// Routes progress messages, warnings and the `log` records of OpenDAL's
+/// LoggingLayer through a `tracing` subscriber, filtered by `--log-level` or
+/// RUST_LOG and formatted per `--log-format`.
+fn init_logging(level: Option<&str>, format: &str) -> Result<()> {
+    use tracing_subscriber::fmt::writer::MakeWriterExt;
+    use tracing_subscriber::layer::SubscriberExt;
+    use tracing_subscriber::util::SubscriberInitExt;
+    use tracing_subscriber::Layer;
+    
+    let filter = match (level, std::env::var("RUST_LOG")) {
+        (Some(filter), _) => tracing_subscriber::EnvFilter::try_new(filter)
+            .map_err(|e| RunFailure::new(FailureCategory::Config, format!("Invalid --log-level {}: {}", filter, e)))?,
+        // RUST_LOG directives refine the default rather than silencing progress
+        (None, Ok(rust_log)) => tracing_subscriber::EnvFilter::try_new(format!("qps_bench=info,{}", rust_log))?,
+        (None, Err(_)) => tracing_subscriber::EnvFilter::new("qps_bench=info"),
+    };
+    // Warnings go to stderr and progress to stdout, as with plain prints, or
+    // also to stderr under --stream-events
+    let writer = std::io::stderr.with_max_level(tracing::Level::WARN).or_else(human_output);
+    
+    #[cfg(feature = "otlp-traces")]
+    let registry = {
+        let (layer, handle) = tracing_subscriber::reload::Layer::new(None);
+        TRACE_EXPORT.set(handle).ok();
+        tracing_subscriber::registry().with(layer)
+    };
+    #[cfg(not(feature = "otlp-traces"))]
+    let registry = tracing_subscriber::registry();
+    
+    let console = match format {
+        "text" => tracing_subscriber::fmt::layer().without_time().with_target(false).with_level(false).with_writer(writer).boxed(),
+        "json" => tracing_subscriber::fmt::layer().json().flatten_event(true).with_writer(writer).boxed(),
+        other => {
+            let message = format!("Unknown --log-format: {} (expected text or json)", other);
+            return Err(RunFailure::new(FailureCategory::Config, message).into());
+        }
+    };
+    // The dashboard shows the log itself; console lines would tear its screen
+    let console = console.with_filter(filter).with_filter(tracing_subscriber::filter::filter_fn(|_| !tui_active()));
+    registry.with(console).with(StreamEventsLayer).with(TuiLogLayer).try_init()?;
+    Ok(())
+}
+
+/// What this binary was built with, so automation can check a deployed build
+/// supports a scenario before launching a long run.
+fn capabilities() -> serde_json::Value {
+    let mut clients = vec!["opendal"];
+    if cfg!(feature = "raw-client") {
+        clients.push("raw");
+    }
+    if cfg!(feature = "aws-sdk") {
+        clients.push("aws-sdk");
+    }
+    let mut sinks = vec!["stdout", "results_dir", "prometheus", "statsd", "nats"];
+    if cfg!(feature = "http-sinks") {
+        sinks.extend(["webhook", "pushgateway", "influxdb"]);
+    } else {
+        // Line protocol still goes to --influx-file
+        sinks.push("influxdb");
+    }
+    if cfg!(feature = "otlp-metrics") {
+        sinks.push("otlp");
+    }
+    if cfg!(feature = "bundle") {
+        sinks.push("export_bundle");
+    }
+    if cfg!(feature = "kafka") {
+        sinks.push("kafka");
+    }
+    if cfg!(feature = "results-db") {
+        sinks.push("results_db");
+    }
+    serde_json::json!({
+        "tool_version": env!("CARGO_PKG_VERSION"),
+        "clients": clients,
+        "services": ["s3"],
+        "modes": MODES,
+        "layers": ["logging", "http_client", "retry", "rate_limit", "stat_cache"],
+        "transfer_engines": TRANSFER_ENGINES,
+        "sinks": sinks,
+        "features": {
+            "aws_sdk": cfg!(feature = "aws-sdk"),
+            "raw_client": cfg!(feature = "raw-client"),
+            "bundle": cfg!(feature = "bundle"),
+            "tui": cfg!(feature = "tui"),
+            "results_db": cfg!(feature = "results-db"),
+            "http_sinks": cfg!(feature = "http-sinks"),
+            "http_options": cfg!(feature = "http-options"),
+            "toml_config": cfg!(feature = "toml-config"),
+            "yaml_config": cfg!(feature = "yaml-config"),
+            "init": cfg!(feature = "init"),
+            "otlp": cfg!(feature = "otlp-metrics"),
+            "otlp_traces": cfg!(feature = "otlp-traces"),
+            "pprof": cfg!(feature = "pprof"),
+            "alloc_stats": cfg!(feature = "alloc-stats"),
+            "transfer_engines": cfg!(feature = "transfer-engines"),
+        },
+    })
+}
+
+/// Why a command failed, as reported in the exit summary and exit code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum FailureCategory {
+    /// Invalid flags, config file or preset
+    Config,
+    /// The backend rejected the credentials or denied access
+    Credentials,
+    /// The endpoint could not be reached
+    Connectivity,
+    /// The backend returned errors the run couldn't get past
+    Backend,
+    /// The backend doesn't support an operation the mode needs
+    Unsupported,
+    SlaBreach,
+    /// Stopped by SIGINT/SIGTERM
+    Interrupted,
+    /// --max-run-time ran out before the run (or its reporting) finished
+    DeadlineExceeded,
+    Internal,
+}
+
+impl FailureCategory {
+    fn exit_code(self) -> i32 {
+        match self {
+            FailureCategory::Internal => 1,
+            FailureCategory::Config => 2,
+            FailureCategory::Credentials => 3,
+            FailureCategory::Connectivity => 4,
+            FailureCategory::Backend => 5,
+            FailureCategory::SlaBreach => 6,
+            FailureCategory::Unsupported => 7,
+            FailureCategory::DeadlineExceeded => 8,
+            FailureCategory::Interrupted => 130,
+        }
+    }
+
+    /// Classifies an error from its chain; errors raised as a RunFailure keep
+    /// their category.
+    fn of(error: &anyhow::Error) -> Self {
+        let mut category = FailureCategory::Internal;
+        for cause in error.chain() {
+            if let Some(failure) = cause.downcast_ref::<RunFailure>() {
+                return failure.category;
+            }
+            #[cfg(feature = "http-options")]
+            if let Some(error) = cause.downcast_ref::<reqwest::Error>() {
+                if error.is_connect() || error.is_timeout() {
+                    return FailureCategory::Connectivity;
+                }
+            }
+            if let Some(error) = cause.downcast_ref::<std::io::Error>() {
+                use std::io::ErrorKind::*;
+                if matches!(error.kind(), ConnectionRefused | ConnectionReset | ConnectionAborted | TimedOut | NotConnected) {
+                    return FailureCategory::Connectivity;
+                }
+            }
+            if let Some(error) = cause.downcast_ref::<opendal::Error>() {
+                match error.kind() {
+                    opendal::ErrorKind::PermissionDenied => return FailureCategory::Credentials,
+                    opendal::ErrorKind::ConfigInvalid => return FailureCategory::Config,
+                    // OpenDAL wraps transport failures too; the cause further down tells them apart
+                    _ => category = FailureCategory::Backend,
+                }
+            }
+        }
+        category
+    }
+}
+
+/// An error with a known failure category, plus the (partial) results of the
+/// run when there are any.
+#[derive(Debug)]
+struct RunFailure {
+    category: FailureCategory,
+    message: String,
+    results: Option<serde_json::Value>,
+}
+
+impl RunFailure {
+    fn new(category: FailureCategory, message: impl Into<String>) -> Self {
+        RunFailure { category, message: message.into(), results: None }
+    }
+
+    fn with_results(mut self, results: serde_json::Value) -> Self {
+        self.results = Some(results);
+        self
+    }
+}
+
+impl std::fmt::Display for RunFailure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for RunFailure {}
+
+/// Final machine-readable record printed when a command fails, so orchestration
+/// can branch on the reason without parsing logs.
+#[derive(Debug, Serialize)]
+struct ExitSummary<'a> {
+    event: &'static str,
+    category: FailureCategory,
+    exit_code: i32,
+    error: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    results: Option<&'a serde_json::Value>,
+}
+
+/// Prints the exit summary for `error` and exits with its category's code.
+fn exit_with_summary(error: anyhow::Error) -> ! {
+    let category = FailureCategory::of(&error);
+    eprintln!("Error: {:?}", error);
+    let summary = ExitSummary {
+        event: "qps_bench_exit",
+        category,
+        exit_code: category.exit_code(),
+        error: format!("{:#}", error),
+        results: error.downcast_ref::<RunFailure>().and_then(|failure| failure.results.as_ref()),
+    };
+    println!("{}", serde_json::to_string(&summary).unwrap_or_default());
+    std::process::exit(category.exit_code());
+}
+
+/// Named workload presets and the settings they expand into. Every setting
+/// can still be overridden by the config file, environment or command line.
+const PRESETS: &[(&str, &[(&str, &str)])] = &[
+    // Metadata-path QPS: HEAD requests against many small objects
+    ("small-object-metadata", &[
+        ("mode", "stat"),
+        ("objects", "10000"),
+        ("object_size_bytes", "1024"),
+        ("concurrency", "64"),
+        ("duration_seconds", "60"),
+    ]),
+    // Bandwidth: whole-object GETs of 16 MiB objects
+    ("throughput", &[
+        ("mode", "read_small"),
+        ("objects", "200"),
+        ("object_size_bytes", "16777216"),
+        ("concurrency", "16"),
+        ("duration_seconds", "120"),
+    ]),
+    // Small-record OLTP-style traffic: 8 KiB reads, then 8 KiB writes
+    ("mixed-oltp", &[
+        ("mode", "read_write"),
+        ("objects", "5000"),
+        ("object_size_bytes", "8192"),
+        ("concurrency", "64"),
+        ("duration_seconds", "60"),
+    ]),
+];
+
+fn preset_settings(name: &str) -> Result<Vec<(String, serde_json::Value)>> {
+    let (_, settings) = PRESETS
+        .iter()
+        .find(|(preset, _)| *preset == name)
+        .ok_or_else(|| {
+            let names: Vec<&str> = PRESETS.iter().map(|(preset, _)| *preset).collect();
+            anyhow::anyhow!("Unknown preset: {}. Supported presets: {}", name, names.join(", "))
+        })?;
+    Ok(settings
+        .iter()
+        .map(|(key, value)| (key.to_string(), serde_json::Value::String(value.to_string())))
+        .collect())
+}
+
+#[cfg(feature = "toml-config")]
+fn parse_toml_config(text: &str, path: &std::path::Path) -> Result<serde_json::Value> {
+    toml::from_str(text).map_err(|e| anyhow::anyhow!("Invalid TOML in {}: {}", path.display(), e))
+}
+
+#[cfg(not(feature = "toml-config"))]
+fn parse_toml_config(_text: &str, path: &std::path::Path) -> Result<serde_json::Value> {
+    let message = format!("Reading {} requires building with the `toml-config` feature", path.display());
+    Err(RunFailure::new(FailureCategory::Config, message).into())
+}
+
+#[cfg(feature = "yaml-config")]
+fn parse_yaml_config(text: &str, path: &std::path::Path) -> Result<serde_json::Value> {
+    serde_yaml::from_str(text).map_err(|e| anyhow::anyhow!("Invalid YAML in {}: {}", path.display(), e))
+}
+
+#[cfg(not(feature = "yaml-config"))]
+fn parse_yaml_config(_text: &str, path: &std::path::Path) -> Result<serde_json::Value> {
+    let message = format!("Reading {} requires building with the `yaml-config` feature", path.display());
+    Err(RunFailure::new(FailureCategory::Config, message).into())
+}
+
+/// Renders a config file written by `calibrate` or `init`.
+#[cfg(feature = "toml-config")]
+fn render_toml_config(config: &serde_json::Value) -> Result<String> {
+    Ok(toml::to_string_pretty(config)?)
+}
+
+#[cfg(not(feature = "toml-config"))]
+fn render_toml_config(_config: &serde_json::Value) -> Result<String> {
+    Err(RunFailure::new(FailureCategory::Config, "Writing config files requires building with the `toml-config` feature").into())
+}
+
+/// Loads a TOML or YAML run spec into a flat list of settings.
+fn load_config_settings(path: &std::path::Path) -> Result<Vec<(String, serde_json::Value)>> {
+    let text = std::fs::read_to_string(path)
+        .map_err(|e| anyhow::anyhow!("Failed to read config file {}: {}", path.display(), e))?;
+    let is_yaml = matches!(path.extension().and_then(|e| e.to_str()), Some("yaml" | "yml"));
+    let value = if is_yaml { parse_yaml_config(&text, path)? } else { parse_toml_config(&text, path)? };
+    let serde_json::Value::Object(root) = value else {
+        anyhow::bail!("Config file {} must contain a table of settings", path.display());
+    };
+    
+    // Tables (e.g. [backend], [workload], [output]) only group settings
+    let mut settings = Vec::new();
+    for (key, value) in root {
+        match value {
+            serde_json::Value::Object(table) => settings.extend(table),
+            value => settings.push((key, value)),
+        }
+    }
+    Ok(settings)
+}
+
+/// Turns every setting not already given on the command line or through the
+/// environment into the equivalent flags.
+fn settings_to_args(settings: Vec<(String, serde_json::Value)>, matches: &clap::ArgMatches) -> Result<Vec<String>> {
+    let command = Cli::command();
+    let mut argv = Vec::new();
+    for (key, value) in settings {
+        let arg = command
+            .get_arguments()
+            .find(|arg| arg.get_id().as_str() == key && arg.get_long().is_some() && key != "config")
+            .ok_or_else(|| anyhow::anyhow!("Unknown setting '{}' in config file", key))?;
+        // Precedence: command line, then environment, then config file, then preset
+        if matches!(
+            matches.value_source(&key),
+            Some(clap::parser::ValueSource::CommandLine | clap::parser::ValueSource::EnvVariable)
+        ) {
+            continue;
+        }
+        let flag = format!("--{}", arg.get_long().unwrap());
+        let values = match value {
+            serde_json::Value::Array(values) => values,
+            value => vec![value],
+        };
+        for value in values {
+            match value {
+                // Booleans that take a value (e.g. default true) need an explicit `=false`
+                serde_json::Value::Bool(b) if arg.get_action().takes_values() => argv.push(format!("{}={}", flag, b)),
+                serde_json::Value::Bool(true) => argv.push(flag.clone()),
+                serde_json::Value::Bool(false) | serde_json::Value::Null => {}
+                serde_json::Value::String(s) => argv.extend([flag.clone(), s]),
+                serde_json::Value::Number(n) => argv.extend([flag.clone(), n.to_string()]),
+                other => anyhow::bail!("Unsupported value for '{}' in config file: {}", key, other),
+            }
+        }
+    }
+    Ok(argv)
+}
+
+/// Levenshtein distance, to suggest the setting a misspelt key was meant to be.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = diagonal + usize::from(ca != *cb);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+    row[b.len()]
+}
+
+/// Settings that parse individually but can't be used together, or that a run
+/// would only reject after connecting. `validate` lists them for a config file
+/// and every run checks them before it touches the backend.
+fn config_conflicts(args: &Args) -> Vec<String> {
+    let mut problems = Vec::new();
+    let modes: Vec<&str> = if args.phases.is_empty() {
+        vec![args.mode.as_str()]
+    } else {
+        args.phases.iter().map(|phase| phase.mode.as_str()).collect()
+    };
+    for mode in &modes {
+        if !MODES.contains(mode) {
+            problems.push(format!("unknown mode '{}' (supported: {})", mode, MODES.join(", ")));
+        }
+    }
+    if args.phases.iter().any(|phase| phase.mode == "read_write") {
+        problems.push("read_write cannot be a phase; chain read_small and write_small phases instead".to_string());
+    }
+    
+    for spec in &args.sinks {
+        if let Err(e) = parse_sink_spec(spec) {
+            problems.push(e.to_string());
+        }
+    }
+    if !args.phases.is_empty() && !args.sweep_concurrency.is_empty() {
+        problems.push("sweep_concurrency is not supported with phases".to_string());
+    }
+    if args.mode == "read_write" && !args.sweep_concurrency.is_empty() {
+        problems.push("sweep_concurrency is not supported in read_write mode".to_string());
+    }
+    if args.sweep_concurrency.contains(&0) {
+        problems.push("sweep_concurrency levels must be at least 1".to_string());
+    }
+    if args.latency_goal_ms.is_some() && args.sweep_concurrency.is_empty() {
+        problems.push("latency_goal_ms only applies to a sweep_concurrency run".to_string());
+    }
+    if args.backend.retries > 0 && args.backend.client != "opendal" {
+        problems.push("retries uses OpenDAL's RetryLayer and needs client = \"opendal\"".to_string());
+    }
+    if let Some(ratio) = args.backend.trace_sample_ratio {
+        if args.backend.client != "opendal" {
+            problems.push("trace_sample_ratio uses OpenDAL's TracingLayer and needs client = \"opendal\"".to_string());
+        }
+        if args.otlp_endpoint.is_none() {
+            problems.push("trace_sample_ratio needs otlp_endpoint".to_string());
+        }
+        if !(0.0..=1.0).contains(&ratio) {
+            problems.push("trace_sample_ratio must be between 0 and 1".to_string());
+        }
+    }
+    if args.backend.operator_rate_limit.is_some_and(|limit| limit <= 0.0) {
+        problems.push("operator_rate_limit must be positive".to_string());
+    }
+    if !matches!(args.webhook_format.as_str(), "full" | "summary") {
+        problems.push(format!("unknown webhook_format '{}' (expected full or summary)", args.webhook_format));
+    }
+    if !matches!(args.format.as_str(), "json" | "csv") {
+        problems.push(format!("unknown format '{}' (expected json or csv)", args.format));
+    }
+    if args.target_rate.is_some_and(|rate| rate <= 0.0) {
+        problems.push("target_rate must be positive".to_string());
+    }
+    if args.max_run_time == Some(0) {
+        problems.push("max_run_time must be at least 1".to_string());
+    }
+    if args.debug_errors == Some(0) {
+        problems.push("debug_errors must be at least 1".to_string());
+    }
+    if args.tui && args.stream_events {
+        problems.push("tui and stream_events both need stdout".to_string());
+    }
+    if args.until_stopped() && (!args.phases.is_empty() || !args.sweep_concurrency.is_empty() || args.mode == "read_write") {
+        problems.push("run_until_signal (or duration_seconds = 0) needs a single mode, not phases, a sweep or read_write".to_string());
+    }
+    if args.percentiles.iter().any(|p| !(*p > 0.0 && *p <= 100.0)) {
+        problems.push("percentiles must be above 0 and at most 100".to_string());
+    }
+    for target in &args.sla {
+        if !MODES.contains(&target.op.as_str()) && !SLA_VERBS.contains(&target.op.to_ascii_uppercase().as_str()) {
+            problems.push(format!("unknown sla op '{}' (a mode, or one of {})", target.op, SLA_VERBS.join(", ")));
+        }
+        if target.p50_ms.is_none() && target.p95_ms.is_none() && target.p99_ms.is_none() && target.max_error_pct.is_none() {
+            problems.push(format!("sla target for '{}' sets no threshold", target.op));
+        }
+    }
+    
+    // Mirror settings only mean something to the modes that use a mirror
+    let mirrored = modes.iter().any(|mode| matches!(*mode, "dual_write" | "read_compare"));
+    if mirrored && args.mirror_endpoint.is_none() {
+        problems.push("dual_write and read_compare need mirror_endpoint".to_string());
+    }
+    if !mirrored && (args.mirror_endpoint.is_some() || args.mirror_bucket.is_some()) {
+        problems.push("mirror_* settings are ignored outside the dual_write and read_compare modes".to_string());
+    }
+    if args.dual_write_verify && !modes.contains(&"dual_write") {
+        problems.push("dual_write_verify needs mode = \"dual_write\"".to_string());
+    }
+    if args.range_read_bytes.is_some() || args.verify_reads {
+        if !modes.iter().any(|mode| matches!(*mode, "read_small" | "read_write")) {
+            problems.push("range_read_bytes and verify_reads only apply to read_small reads".to_string());
+        }
+        if args.backend.client != "opendal" {
+            problems.push("range_read_bytes and verify_reads need client = \"opendal\"".to_string());
+        }
+    }
+    if modes.contains(&"stat_storm") && !(1..=10).contains(&args.storm_keys) {
+        problems.push("storm_keys must be between 1 and 10".to_string());
+    }
+    if modes.iter().any(|mode| matches!(*mode, "scatter_write" | "commit")) && args.fanout == 0 {
+        problems.push("fanout must be at least 1".to_string());
+    }
+    if modes.contains(&"list") && args.list_max_entries == 0 {
+        problems.push("list_max_entries must be at least 1".to_string());
+    }
+    if modes.contains(&"read_overwrite") {
+        if args.overwrite_keys == 0 || args.overwriters == 0 {
+            problems.push("overwrite_keys and overwriters must be at least 1".to_string());
+        }
+        if args.object_size_bytes < OVERWRITE_HEADER_LEN {
+            problems.push(format!("read_overwrite needs object_size_bytes of at least {}", OVERWRITE_HEADER_LEN));
+        }
+        if args.backend.client != "opendal" {
+            problems.push("read_overwrite reads content back and needs client = \"opendal\"".to_string());
+        }
+    }
+    if let Some(pct) = args.churn_pct_per_minute {
+        if !(pct > 0.0 && pct.is_finite()) {
+            problems.push("churn_pct_per_minute must be positive".to_string());
+        }
+        if !modes.iter().any(|mode| is_churn_mode(mode)) {
+            problems.push("churn_pct_per_minute only applies to stat and read_small".to_string());
+        }
+    }
+    if args.results_db.is_some() && !cfg!(feature = "results-db") {
+        problems.push("results_db requires building with the `results-db` feature".to_string());
+    }
+    if !cfg!(feature = "http-sinks") {
+        for (name, set) in [("webhook_url", args.webhook_url.is_some()), ("pushgateway_url", args.pushgateway_url.is_some()), ("influx_url", args.influx_url.is_some())] {
+            if set {
+                problems.push(format!("{} requires building with the `http-sinks` feature", name));
+            }
+        }
+    }
+    // Without metrics export the endpoint is only good for traces
+    if args.otlp_endpoint.is_some() && !cfg!(feature = "otlp-metrics") && args.backend.trace_sample_ratio.is_none() {
+        problems.push("otlp_endpoint requires building with the `otlp-metrics` feature".to_string());
+    }
+    let backend = &args.backend;
+    if !cfg!(feature = "http-options")
+        && (!backend.headers.is_empty() || backend.proxy.is_some() || backend.ca_cert.is_some() || backend.insecure_skip_tls_verify)
+    {
+        problems.push("header, proxy, ca_cert and insecure_skip_tls_verify require building with the `http-options` feature".to_string());
+    }
+    problems
+}
+
+/// Checks a config file the way `--config` would load it, reporting every
+/// problem found rather than stopping at the first.
+fn validate_config(validate: ValidateArgs) -> Result<()> {
+    let path = &validate.config;
+    let mut settings = load_config_settings(path)?;
+    let mut problems = Vec::new();
+    
+    let command = Cli::command();
+    let known: Vec<&str> = command
+        .get_arguments()
+        .filter(|arg| arg.get_long().is_some() && arg.get_id().as_str() != "config")
+        .map(|arg| arg.get_id().as_str())
+        .chain(["phases", "sla"])
+        .collect();
+    settings.retain(|(key, _)| {
+        if known.contains(&key.as_str()) {
+            return true;
+        }
+        let closest = known.iter().min_by_key(|name| edit_distance(key, name));
+        match closest {
+            Some(name) if edit_distance(key, name) <= 3 => {
+                problems.push(format!("unknown setting '{}' (did you mean '{}'?)", key, name));
+            }
+            _ => problems.push(format!("unknown setting '{}'", key)),
+        }
+        false
+    });
+    let preset = settings
+        .iter()
+        .find(|(key, _)| key == "preset")
+        .and_then(|(_, value)| value.as_str().map(str::to_string));
+    if let Some(preset) = preset {
+        match preset_settings(&preset) {
+            Ok(preset) => {
+                for (key, value) in preset {
+                    if !settings.iter().any(|(existing, _)| *existing == key) {
+                        settings.push((key, value));
+                    }
+                }
+            }
+            Err(e) => problems.push(e.to_string()),
+        }
+    }
+    let phases: Vec<PhaseSpec> = match settings.iter().position(|(key, _)| key == "phases") {
+        Some(index) => serde_json::from_value(settings.remove(index).1).unwrap_or_else(|e| {
+            problems.push(format!("invalid phases: {}", e));
+            Vec::new()
+        }),
+        None => Vec::new(),
+    };
+    let sla: Vec<SlaTarget> = match settings.iter().position(|(key, _)| key == "sla") {
+        Some(index) => serde_json::from_value(settings.remove(index).1).unwrap_or_else(|e| {
+            problems.push(format!("invalid sla: {}", e));
+            Vec::new()
+        }),
+        None => Vec::new(),
+    };
+    
+    // Each setting is converted alone so one bad value doesn't hide the others
+    let matches = Cli::command().try_get_matches_from(["qps-bench"])?;
+    let mut argv = vec!["qps-bench".to_string()];
+    for setting in settings {
+        let key = setting.0.clone();
+        let flags = match settings_to_args(vec![setting], &matches) {
+            Ok(flags) => flags,
+            Err(e) => {
+                problems.push(e.to_string());
+                continue;
+            }
+        };
+        let mut single = vec!["qps-bench".to_string()];
+        single.extend(flags.iter().cloned());
+        match Cli::try_parse_from(single) {
+            Ok(_) => argv.extend(flags),
+            Err(e) => {
+                let error = e.to_string();
+                let first_line = error.lines().next().unwrap_or_default().trim_start_matches("error: ");
+                problems.push(format!("invalid value for '{}': {}", key, first_line));
+            }
+        }
+    }
+    match Cli::try_parse_from(argv) {
+        Ok(mut cli) => {
+            cli.run.phases = phases;
+            cli.run.sla = sla;
+            // A run can take these from elsewhere, but a config meant to run alone needs them
+            for (flag, value) in [
+                ("endpoint", &cli.run.backend.endpoint),
+                ("bucket", &cli.run.backend.bucket),
+                ("access_key (or QPS_BENCH_ACCESS_KEY)", &cli.run.backend.access_key),
+                ("secret_key (or QPS_BENCH_SECRET_KEY)", &cli.run.backend.secret_key),
+            ] {
+                if value.is_empty() {
+                    problems.push(format!("{} is missing", flag));
+                }
+            }
+            if cli.run.mode == "read_compare" && cli.run.manifest.is_none() {
+                problems.push("read_compare needs the manifest of a dataset already copied to the mirror".to_string());
+            }
+            problems.extend(config_conflicts(&cli.run));
+        }
+        Err(e) => problems.push(e.to_string().lines().next().unwrap_or_default().trim_start_matches("error: ").to_string()),
+    }
+    
+    if problems.is_empty() {
+        report!("✅ {} is valid", path.display());
+        return Ok(());
+    }
+    report!("❌ {}:", path.display());
+    for problem in &problems {
+        report!("  - {}", problem);
+    }
+    let message = format!("{} problem(s) in {}", problems.len(), path.display());
+    Err(RunFailure::new(FailureCategory::Config, message).into())
+}
+
+/// Passes a usage error on to the exit summary; --help and --version still
+/// print and exit the way clap does.
+fn clap_result<T>(result: Result<T, clap::Error>) -> Result<T> {
+    result.map_err(|e| match e.kind() {
+        clap::error::ErrorKind::DisplayHelp | clap::error::ErrorKind::DisplayVersion => e.exit(),
+        _ => anyhow::anyhow!("{}", e.render().to_string().trim_end()),
+    })
+}
+
+/// Parses the command line, filling in values from `--config` and `--preset`
+/// for every argument that was not given explicitly.
+fn parse_cli() -> Result<Cli> {
+    let argv: Vec<std::ffi::OsString> = std::env::args_os().collect();
+    let matches = clap_result(Cli::command().try_get_matches_from(&argv))?;
+    // Benchmark settings live at the top level or under the `run` subcommand,
+    // which must come first on the command line
+    let (subcommand, run_matches) = match matches.subcommand() {
+        Some(("run", run_matches)) => (Some("run"), run_matches),
+        _ => (None, &matches),
+    };
+    
+    let mut settings = match run_matches.get_one::<std::path::PathBuf>("config") {
+        Some(path) => load_config_settings(path)?,
+        None => Vec::new(),
+    };
+    let preset = run_matches.get_one::<String>("preset").cloned().or_else(|| {
+        settings
+            .iter()
+            .find(|(key, _)| key == "preset")
+            .and_then(|(_, value)| value.as_str().map(str::to_string))
+    });
+    if let Some(preset) = preset {
+        for (key, value) in preset_settings(&preset)? {
+            if !settings.iter().any(|(existing, _)| *existing == key) {
+                settings.push((key, value));
+            }
+        }
+    }
+    // Phases and SLA targets are structured, so they bypass the flag conversion
+    let phases: Vec<PhaseSpec> = match settings.iter().position(|(key, _)| key == "phases") {
+        Some(index) => serde_json::from_value(settings.remove(index).1)
+            .map_err(|e| anyhow::anyhow!("Invalid phases in config file: {}", e))?,
+        None => Vec::new(),
+    };
+    let sla: Vec<SlaTarget> = match settings.iter().position(|(key, _)| key == "sla") {
+        Some(index) => serde_json::from_value(settings.remove(index).1)
+            .map_err(|e| anyhow::anyhow!("Invalid sla in config file: {}", e))?,
+        None => Vec::new(),
+    };
+    
+    let mut cli = if settings.is_empty() {
+        Cli::from_arg_matches(&matches)?
+    } else {
+        let mut merged = vec![argv[0].clone()];
+        merged.extend(subcommand.map(std::ffi::OsString::from));
+        merged.extend(settings_to_args(settings, run_matches)?.into_iter().map(std::ffi::OsString::from));
+        merged.extend(argv.into_iter().skip(1 + usize::from(subcommand.is_some())));
+        clap_result(Cli::try_parse_from(merged))?
+    };
+    match &mut cli.command {
+        None => {
+            cli.run.phases = phases;
+            cli.run.sla = sla;
+        }
+        Some(Command::Run(run)) => {
+            run.run.phases = phases;
+            run.run.sla = sla;
+        }
+        Some(_) => {}
+    }
+    Ok(cli)
+}
+
+/// Runs the `qps-bench` command line: parses the arguments, runs the command
+/// and, on failure, prints the exit summary and exits with its code.
+pub async fn run_cli() {
+    let run = Arc::new(RunContext::new(true));
+    let result = tokio::select! {
+        result = RUN.scope(run, run_command()) => result,
+        _ = ABANDONED.notified() => Err(RunFailure::new(FailureCategory::Interrupted, "Interrupted again; exited without cleanup").into()),
+    };
+    if let Err(error) = result {
+        exit_with_summary(error);
+    }
+}
+
+async fn run_command() -> Result<()> {
+    let cli = parse_cli().map_err(|e| RunFailure::new(FailureCategory::Config, format!("{:#}", e)))?;
+    if cli.capabilities {
+        println!("{}", serde_json::to_string_pretty(&capabilities())?);
+        return Ok(());
+    }
+    init_logging(cli.log_level.as_deref(), &cli.log_format)?;
+    install_signal_handler().map_err(|e| RunFailure::new(FailureCategory::Internal, format!("{:#}", e)))?;
+    
+    match cli.command {
+        None => run_and_notify(&cli.run, None).await?,
+        Some(Command::Prepare(prepare)) => prepare_dataset(prepare).await?,
+        Some(Command::Run(run)) => run_and_notify(&run.run, Some(&DatasetManifest::load(&run.dataset)?)).await?,
+        Some(Command::Cleanup(cleanup)) => cleanup_dataset(cleanup).await?,
+        Some(Command::Gc(gc)) => gc_remainder(gc).await?,
+        Some(Command::Report(report)) => report_results(report)?,
+        Some(Command::Trend(trend)) => print_trend(trend)?,
+        Some(Command::Calibrate(calibrate)) => calibrate_config(calibrate)?,
+        Some(Command::Doctor(doctor)) => run_doctor(doctor).await?,
+        Some(Command::Validate(validate)) => validate_config(validate)?,
+        Some(Command::Init(init)) => init_config(init)?,
+        Some(Command::Rerun(rerun)) => rerun_bundle(rerun).await?,
+        Some(Command::GrafanaDashboard(dashboard)) => write_grafana_dashboard(dashboard)?,
+    }
+    
+    // Commands other than a run stop early on a signal but otherwise succeed
+    if stopped_early() {
+        return Err(RunFailure::new(FailureCategory::Interrupted, "Interrupted before finishing").into());
+    }
+    Ok(())
+}
+
+async fn prepare_dataset(prepare: PrepareArgs) -> Result<()> {
+    prepare.backend.validate()?;
+    let client = create_client(&prepare.backend)?;
+    let mut manifest = if prepare.dataset.exists() {
+        let manifest = DatasetManifest::load(&prepare.dataset)?;
+        if !manifest.incomplete {
+            tracing::info!("✅ Dataset in {} is already complete ({} objects)", prepare.dataset.display(), manifest.keys.len());
+            return Ok(());
+        }
+        manifest
+    } else {
+        let seed = prepare.seed.unwrap_or_else(rand::random);
+        DatasetManifest::new(dataset_prefix(&prepare.prefix, seed), prepare.object_size_bytes)
+    };
+    tracing::info!("Using prefix: {}", manifest.prefix);
+    
+    create_dataset(client.as_ref(), &mut manifest, prepare.objects, prepare.prepare_concurrency, Some(&prepare.dataset)).await?;
+    persist_manifest(client.as_ref(), &manifest, &prepare.dataset, prepare.upload_manifest).await
+}
+
+async fn cleanup_dataset(cleanup: CleanupArgs) -> Result<()> {
+    cleanup.backend.validate()?;
+    let client = create_client(&cleanup.backend)?;
+    let manifest = DatasetManifest::load(&cleanup.dataset)?;
+    cleanup_objects(client.as_ref(), &manifest.prefix, manifest.object_size_bytes, &manifest.keys, &cleanup.options).await?;
+    // Deleting a missing key succeeds, so this is safe when no copy was uploaded
+    client.delete(&format!("{}{}", manifest.prefix, MANIFEST_OBJECT)).await?;
+    Ok(())
+}
+
+/// Maps an S3 access-log operation (REST.GET.OBJECT) or a metrics name
+/// (GetRequests, GET) to the benchmark mode that issues it.
+fn calibration_mode(operation: &str) -> Option<&'static str> {
+    let operation = operation.trim().to_ascii_lowercase();
+    let operation = operation.strip_suffix("requests").unwrap_or(&operation);
+    match operation {
+        "rest.head.object" | "head" => Some("stat"),
+        "rest.get.object" | "get" => Some("read_small"),
+        "rest.put.object" | "rest.copy.object" | "rest.post.upload" | "put" | "post" => Some("write_small"),
+        "rest.delete.object" | "rest.post.multi_object_delete" | "delete" => Some("delete"),
+        "rest.get.bucket" | "list" => Some("list"),
+        _ => None,
+    }
+}
+
+/// Splits an S3 server access log line into fields; `[...]` and `"..."` are single fields.
+fn access_log_fields(line: &str) -> Vec<&str> {
+    let mut fields = Vec::new();
+    let mut rest = line.trim_start();
+    while !rest.is_empty() {
+        let (field, next) = match rest.as_bytes()[0] {
+            b'[' => rest[1..].split_once(']').unwrap_or((&rest[1..], "")),
+            b'"' => rest[1..].split_once('"').unwrap_or((&rest[1..], "")),
+            _ => rest.split_once(' ').unwrap_or((rest, "")),
+        };
+        fields.push(field);
+        rest = next.trim_start();
+    }
+    fields
+}
+
+/// Requests per mode and the object sizes seen, gathered from the inputs.
+#[derive(Default)]
+struct ObservedTraffic {
+    requests: BTreeMap<&'static str, u64>,
+    /// Requests whose operation doesn't map to a mode
+    other: u64,
+    sizes: Option<Histogram<u64>>,
+}
+
+impl ObservedTraffic {
+    fn record_size(&mut self, size: u64, count: u64) {
+        let sizes = self.sizes.get_or_insert_with(|| Histogram::<u64>::new(3).unwrap());
+        sizes.record_n(size.max(1), count).ok();
+    }
+
+    fn add_access_log(&mut self, text: &str) {
+        // owner bucket [time] ip requester request-id operation key "uri" status error bytes-sent object-size ...
+        for fields in text.lines().map(access_log_fields).filter(|fields| fields.len() > 12) {
+            match calibration_mode(fields[6]) {
+                Some(mode) => {
+                    *self.requests.entry(mode).or_default() += 1;
+                    // object-size is "-" for requests without one
+                    if let (true, Ok(size)) = (matches!(mode, "read_small" | "write_small"), fields[12].parse::<u64>()) {
+                        self.record_size(size, 1);
+                    }
+                }
+                None => self.other += 1,
+            }
+        }
+    }
+
+    fn add_csv(&mut self, text: &str, path: &std::path::Path) -> Result<()> {
+        let mut lines = text.lines().filter(|line| !line.trim().is_empty());
+        let header: Vec<String> = lines
+            .next()
+            .ok_or_else(|| anyhow::anyhow!("{} is empty", path.display()))?
+            .split(',')
+            .map(|column| column.trim().to_ascii_lowercase())
+            .collect();
+        let column = |name: &str| header.iter().position(|column| column == name);
+        let (Some(operation), Some(count)) = (column("operation"), column("count")) else {
+            anyhow::bail!("{} needs `operation` and `count` columns", path.display());
+        };
+        let size = column("avg_object_size_bytes");
+        for line in lines {
+            let values: Vec<&str> = line.split(',').map(str::trim).collect();
+            let requests: u64 = values
+                .get(count)
+                .and_then(|value| value.parse::<f64>().ok())
+                .ok_or_else(|| anyhow::anyhow!("Invalid count in {}: {}", path.display(), line))? as u64;
+            match values.get(operation).and_then(|value| calibration_mode(value)) {
+                Some(mode) => {
+                    *self.requests.entry(mode).or_default() += requests;
+                    if let Some(size) = size.and_then(|i| values.get(i)).and_then(|value| value.parse::<f64>().ok()) {
+                        self.record_size(size as u64, requests);
+                    }
+                }
+                None => self.other += requests,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Builds a run config from observed traffic: the object size is the median
+/// size read or written, and `duration_seconds` is split into one phase per
+/// mode in proportion to its share of requests. Phases run back to back, so the
+/// config reproduces the mix over the run rather than at every instant.
+fn calibrate_config(calibrate: CalibrateArgs) -> Result<()> {
+    let mut traffic = ObservedTraffic::default();
+    for path in &calibrate.files {
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| anyhow::anyhow!("Failed to read {}: {}", path.display(), e))?;
+        let csv = match calibrate.format.as_str() {
+            "auto" => path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("csv")),
+            "csv" => true,
+            "access-log" => false,
+            other => anyhow::bail!("Unknown format: {}. Supported formats: auto, access-log, csv", other),
+        };
+        if csv {
+            traffic.add_csv(&text, path)?;
+        } else {
+            traffic.add_access_log(&text);
+        }
+    }
+    
+    let total: u64 = traffic.requests.values().sum();
+    if total == 0 {
+        anyhow::bail!("No object or bucket operations found in the input");
+    }
+    
+    eprintln!("Observed {} requests ({} with operations the benchmark doesn't model):", total + traffic.other, traffic.other);
+    for (mode, requests) in &traffic.requests {
+        eprintln!("  {:<12} {:>10} ({:.1}%)", mode, requests, *requests as f64 / total as f64 * 100.0);
+    }
+    
+    let mut config = serde_json::Map::new();
+    let mut workload = serde_json::Map::new();
+    let (dominant, _) = traffic.requests.iter().max_by_key(|(_, requests)| **requests).unwrap();
+    workload.insert("mode".into(), serde_json::Value::from(*dominant));
+    if let Some(sizes) = &traffic.sizes {
+        eprintln!("  object size  P50 {} B, P90 {} B, P99 {} B, max {} B",
+                  sizes.value_at_quantile(0.5), sizes.value_at_quantile(0.9), sizes.value_at_quantile(0.99), sizes.max());
+        workload.insert("object_size_bytes".into(), serde_json::Value::from(sizes.value_at_quantile(0.5)));
+    }
+    config.insert("workload".into(), serde_json::Value::Object(workload));
+    
+    let phases = traffic
+        .requests
+        .iter()
+        .map(|(mode, requests)| {
+            let share = *requests as f64 / total as f64;
+            let mut phase = serde_json::Map::new();
+            phase.insert("mode".into(), serde_json::Value::from(*mode));
+            phase.insert("duration_seconds".into(), serde_json::Value::from(((calibrate.duration_seconds as f64 * share).round() as i64).max(1)));
+            serde_json::Value::Object(phase)
+        })
+        .collect();
+    config.insert("phases".into(), serde_json::Value::Array(phases));
+    
+    let text = format!(
+        "# Calibrated by `qps-bench calibrate` from {} observed requests.\n# Add a [backend] table or pass the backend flags to run it.\n\n{}",
+        total,
+        render_toml_config(&serde_json::Value::Object(config))?
+    );
+    match &calibrate.output {
+        Some(path) => {
+            std::fs::write(path, text).map_err(|e| anyhow::anyhow!("Failed to write {}: {}", path.display(), e))?;
+            tracing::info!("📝 Wrote calibrated config to {}", path.display());
+        }
+        None => print!("{}", text),
+    }
+    Ok(())
+}
+
+/// Providers offered by `init`: name, default region, endpoint template
+/// (`{region}` is substituted) and addressing style.
+#[cfg(feature = "init")]
+const INIT_PROVIDERS: &[(&str, &str, &str, &str)] = &[
+    ("aws", "us-east-1", "https://s3.{region}.amazonaws.com", "virtual"),
+    ("gcs", "auto", "https://storage.googleapis.com", "path"),
+    ("oss", "cn-hangzhou", "https://oss-{region}.aliyuncs.com", "virtual"),
+    ("minio", "us-east-1", "http://localhost:9000", "path"),
+    ("other", "us-east-1", "", "auto"),
+];
+
+/// Asks `question` on stdout and reads the answer from stdin; an empty answer
+/// (or end of input) takes `default`.
+#[cfg(feature = "init")]
+fn prompt(question: &str, default: Option<&str>) -> Result<String> {
+    loop {
+        match default {
+            Some(default) if !default.is_empty() => print!("{} [{}]: ", question, default),
+            _ => print!("{}: ", question),
+        }
+        std::io::Write::flush(&mut std::io::stdout())?;
+        let mut answer = String::new();
+        let read = std::io::stdin().read_line(&mut answer)?;
+        let answer = answer.trim();
+        match (answer.is_empty(), default) {
+            (false, _) => return Ok(answer.to_string()),
+            (true, Some(default)) => return Ok(default.to_string()),
+            (true, None) if read == 0 => anyhow::bail!("No answer for '{}' before the end of input", question),
+            (true, None) => println!("  An answer is required"),
+        }
+    }
+}
+
+/// Like [`prompt`] with no default, but without echoing the answer.
+#[cfg(feature = "init")]
+fn prompt_secret(question: &str) -> Result<String> {
+    loop {
+        let answer = rpassword::prompt_password(format!("{}: ", question))?;
+        if !answer.trim().is_empty() {
+            return Ok(answer.trim().to_string());
+        }
+        println!("  An answer is required");
+    }
+}
+
+/// Like [`prompt`], re-asking until the answer is one of `choices`.
+#[cfg(feature = "init")]
+fn prompt_choice(question: &str, choices: &[&str], default: &str) -> Result<String> {
+    loop {
+        let answer = prompt(&format!("{} ({})", question, choices.join("/")), Some(default))?;
+        if choices.contains(&answer.as_str()) {
+            return Ok(answer);
+        }
+        println!("  Expected one of: {}", choices.join(", "));
+    }
+}
+
+#[cfg(feature = "init")]
+fn init_config(init: InitArgs) -> Result<()> {
+    if init.output.exists() && !init.force {
+        let message = format!("{} already exists (pass --force to overwrite it)", init.output.display());
+        return Err(RunFailure::new(FailureCategory::Config, message).into());
+    }
+    println!("This writes a qps-bench config to {}. Press Enter to take the [default].", init.output.display());
+    println!();
+    
+    let names: Vec<&str> = INIT_PROVIDERS.iter().map(|(name, ..)| *name).collect();
+    let provider = prompt_choice("Provider", &names, "aws")?;
+    let &(_, region, endpoint, addressing_style) = INIT_PROVIDERS.iter().find(|(name, ..)| *name == provider).unwrap();
+    let region = prompt("Region", Some(region))?;
+    let endpoint = match endpoint.replace("{region}", &region) {
+        endpoint if endpoint.is_empty() => prompt("Endpoint URL", None)?,
+        endpoint => prompt("Endpoint URL", Some(&endpoint))?,
+    };
+    let bucket = prompt("Bucket", None)?;
+    let credentials = prompt_choice("Credentials from the environment or stored in the config file", &["env", "file"], "env")?;
+    let presets: Vec<&str> = PRESETS.iter().map(|(name, _)| *name).collect();
+    let preset = prompt_choice("Workload preset", &presets, presets[0])?;
+    
+    let mut backend = serde_json::Map::new();
+    backend.insert("endpoint".into(), serde_json::Value::String(endpoint));
+    backend.insert("region".into(), serde_json::Value::String(region));
+    backend.insert("bucket".into(), serde_json::Value::String(bucket));
+    backend.insert("addressing_style".into(), serde_json::Value::from(addressing_style));
+    if credentials == "file" {
+        backend.insert("access_key".into(), serde_json::Value::String(prompt("Access key ID", None)?));
+        backend.insert("secret_key".into(), serde_json::Value::String(prompt_secret("Secret access key")?));
+    }
+    let mut workload = serde_json::Map::new();
+    workload.insert("preset".into(), serde_json::Value::String(preset));
+    let mut config = serde_json::Map::new();
+    config.insert("backend".into(), serde_json::Value::Object(backend));
+    config.insert("workload".into(), serde_json::Value::Object(workload));
+    
+    let header = if credentials == "env" {
+        "# Written by `qps-bench init`. Export QPS_BENCH_ACCESS_KEY and QPS_BENCH_SECRET_KEY,\n# then run `qps-bench --config <this file>`.\n\n"
+    } else {
+        "# Written by `qps-bench init`. Holds credentials: keep it out of version control.\n# Run `qps-bench --config <this file>`.\n\n"
+    };
+    let text = format!("{}{}", header, render_toml_config(&serde_json::Value::Object(config))?);
+    // Created owner-only from the start so stored credentials are never readable by others
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    if credentials == "file" {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    if init.force {
+        match std::fs::remove_file(&init.output) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                anyhow::bail!("Failed to replace {}: {}", init.output.display(), e);
+            }
+            _ => {}
+        }
+    }
+    options.open(&init.output)
+        .and_then(|mut file| std::io::Write::write_all(&mut file, text.as_bytes()))
+        .map_err(|e| anyhow::anyhow!("Failed to write {}: {}", init.output.display(), e))?;
+    println!();
+    tracing::info!("📝 Wrote {}; check it with `qps-bench validate --config {}`", init.output.display(), init.output.display());
+    Ok(())
+}
+
+#[cfg(not(feature = "init"))]
+fn init_config(_init: InitArgs) -> Result<()> {
+    Err(RunFailure::new(FailureCategory::Config, "`init` requires building with the `init` feature").into())
+}
+
+/// The doctor's probe directory holds one object at most.
+const DOCTOR_LIST_MAX_ENTRIES: usize = 1000;
+
+struct DoctorCheck {
+    operation: &'static str,
+    status: &'static str,
+    latency_ms: Option<f64>,
+    detail: String,
+}
+
+impl DoctorCheck {
+    fn skipped(operation: &'static str, detail: &str) -> Self {
+        DoctorCheck { operation, status: "skipped", latency_ms: None, detail: detail.to_string() }
+    }
+}
+
+/// Runs one preflight probe, returning its check row and the value on success.
+async fn doctor_probe<T>(operation: &'static str, fut: impl Future<Output = opendal::Result<T>>) -> (DoctorCheck, Option<T>) {
+    let start = Instant::now();
+    let result = fut.await;
+    let latency_ms = Some(start.elapsed().as_secs_f64() * 1000.0);
+    match result {
+        Ok(value) => (DoctorCheck { operation, status: "ok", latency_ms, detail: String::new() }, Some(value)),
+        Err(e) => {
+            let hint = match e.kind() {
+                opendal::ErrorKind::NotFound if operation == "LIST" => " (does the bucket exist?)",
+                opendal::ErrorKind::PermissionDenied => " (check credentials and bucket policy)",
+                _ => "",
+            };
+            (DoctorCheck { operation, status: "failed", latency_ms, detail: format!("{}{}", e, hint) }, None)
+        }
+    }
+}
+
+/// Probes the backend with a single small object and prints which operations
+/// work, so a misconfigured run fails up front instead of piling up err_ops.
+async fn run_doctor(doctor: DoctorArgs) -> Result<()> {
+    doctor.backend.validate()?;
+    let addressing = resolve_addressing_style(&doctor.backend)?;
+    tracing::info!(
+        service = %doctor.backend.service,
+        endpoint = %doctor.backend.endpoint,
+        bucket = %doctor.backend.bucket,
+        client = %doctor.backend.client,
+        addressing = %addressing,
+        "🩺 Checking the backend"
+    );
+    
+    let client = create_client(&doctor.backend)?;
+    let dir = format!("{}/doctor-{}/", doctor.prefix, Uuid::new_v4());
+    let key = format!("{}probe", dir);
+    let payload = b"qps-bench doctor probe".to_vec();
+    let payload_len = payload.len();
+    let mut checks = Vec::new();
+    
+    // LIST first: it needs no objects, so it isolates connectivity and bucket problems
+    checks.push(doctor_probe("LIST", client.list(&dir, DOCTOR_LIST_MAX_ENTRIES)).await.0);
+    let (check, written) = doctor_probe("PUT", client.write(&key, payload)).await;
+    checks.push(check);
+    if written.is_some() {
+        checks.push(doctor_probe("HEAD", client.stat(&key)).await.0);
+        let (mut check, read) = doctor_probe("GET", client.read(&key)).await;
+        if let Some(len) = read.filter(|len| *len != payload_len) {
+            check.status = "failed";
+            check.detail = format!("read {} bytes, expected {}", len, payload_len);
+        }
+        checks.push(check);
+    } else {
+        checks.push(DoctorCheck::skipped("HEAD", "PUT failed"));
+        checks.push(DoctorCheck::skipped("GET", "PUT failed"));
+    }
+    // S3 acknowledges deletes of missing keys, so this checks permission even without the probe
+    checks.push(doctor_probe("DELETE", client.delete(&key)).await.0);
+    
+    report!("{:<8} {:<10} {:>12}  Detail", "Op", "Status", "Latency");
+    for check in &checks {
+        let icon = match check.status {
+            "ok" => "✅",
+            "failed" => "❌",
+            _ => "⏭️ ",
+        };
+        let latency = check.latency_ms.map(|ms| format!("{:.2} ms", ms)).unwrap_or_default();
+        report!("{:<8} {} {:<7} {:>12}  {}", check.operation, icon, check.status, latency, check.detail);
+    }
+    
+    let failed = checks.iter().filter(|check| check.status != "ok").count();
+    if failed > 0 {
+        anyhow::bail!("{} preflight check(s) did not pass", failed);
+    }
+    tracing::info!("✅ All preflight checks passed");
+    Ok(())
+}
+
+/// Builds a fresh dataset prefix under `base` from the time and `seed`.
+fn dataset_prefix(base: &str, seed: u64) -> String {
+    let timestamp = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    let mut rng = StdRng::seed_from_u64(seed);
+    let random: u64 = rng.next_u64();
+    format!("{}/{}-{}/", base, timestamp, random)
+}
+
+/// Name of the manifest copy stored under the dataset prefix by --upload-manifest.
+const MANIFEST_OBJECT: &str = "qps-bench-manifest.json";
+
+/// Writes `manifest` to `path` and, if `upload` is set, to the bucket.
+async fn persist_manifest(client: &dyn BenchClient, manifest: &DatasetManifest, path: &std::path::Path, upload: bool) -> Result<()> {
+    manifest.save(path)?;
+    tracing::info!("💾 Dataset manifest written to {}", path.display());
+    if upload {
+        let key = format!("{}{}", manifest.prefix, MANIFEST_OBJECT);
+        client.write(&key, serde_json::to_vec_pretty(manifest)?).await?;
+        tracing::info!("💾 Dataset manifest uploaded to {}", key);
+    }
+    Ok(())
+}
+
+/// Returns the keys of an existing `dataset`, or creates a new one under
+/// `prefix` (persisting its manifest when --manifest is set).
+async fn dataset_keys(client: &dyn BenchClient, dataset: Option<&DatasetManifest>, prefix: &str, args: &Args, seed: u64) -> Result<Vec<String>> {
+    match dataset {
+        Some(dataset) if dataset.keys.is_empty() => anyhow::bail!("Dataset under {} has no objects", dataset.prefix),
+        Some(dataset) if dataset.incomplete => anyhow::bail!("Dataset under {} is incomplete; run `prepare` again to finish it", dataset.prefix),
+        Some(dataset) => Ok(shuffle_keys(dataset.keys.clone(), args, seed)),
+        None => {
+            let mut manifest = DatasetManifest::new(prefix.to_string(), args.object_size_bytes);
+            create_dataset(client, &mut manifest, args.objects, args.prepare_concurrency, args.manifest.as_deref()).await?;
+            if let Some(path) = &args.manifest {
+                persist_manifest(client, &manifest, path, args.upload_manifest).await?;
+            }
+            Ok(shuffle_keys(manifest.keys, args, seed))
+        }
+    }
+}
+
+/// Puts `keys` in an order drawn from `seed`, unless --no-shuffle-keys; the
+/// manifest keeps creation order, so the same seed visits keys the same way.
+fn shuffle_keys(mut keys: Vec<String>, args: &Args, seed: u64) -> Vec<String> {
+    if !args.no_shuffle_keys {
+        use rand::seq::SliceRandom;
+        keys.shuffle(&mut StdRng::seed_from_u64(seed));
+    }
+    keys
+}
+
+/// Deletes `keys` in batches, with up to `concurrency` batches in flight, and
+/// returns the keys left over: those in failed batches plus, once `budget`
+/// runs out, those not attempted.
+async fn delete_objects(client: &dyn BenchClient, keys: &[String], budget: Option<Duration>, concurrency: usize) -> Vec<String> {
+    let single_deletes = with_run(|run| run.single_deletes.load(std::sync::atomic::Ordering::Relaxed));
+    let batch_size = if single_deletes { 1 } else { client.delete_batch_size().max(1) };
+    tracing::info!("🧹 Cleaning up {} objects ({} per request, {} in flight)...", keys.len(), batch_size, concurrency.max(1));
+    let start = Instant::now();
+    let mut batches = futures::stream::iter(keys.chunks(batch_size))
+        .map(|batch| async move {
+            if budget.is_some_and(|budget| start.elapsed() >= budget) {
+                return (batch, false);
+            }
+            let result = if batch_size == 1 { client.delete(&batch[0]).await } else { client.delete_batch(batch).await };
+            (batch, result.is_ok())
+        })
+        .buffer_unordered(concurrency.max(1));
+    
+    let mut cleaned = 0;
+    let mut remaining = Vec::new();
+    let mut budget_exhausted = false;
+    let mut next_report = 1000;
+    while let Some((batch, deleted)) = batches.next().await {
+        if deleted {
+            cleaned += batch.len();
+            if cleaned >= next_report {
+                tracing::info!("  Deleted {}/{} objects ({:.0} objects/s)...", cleaned, keys.len(), cleaned as f64 / start.elapsed().as_secs_f64());
+                next_report = cleaned + 1000;
+            }
+        } else {
+            budget_exhausted |= budget.is_some_and(|budget| start.elapsed() >= budget);
+            remaining.extend_from_slice(batch);
+        }
+    }
+    
+    let elapsed = start.elapsed().as_secs_f64();
+    if budget_exhausted {
+        tracing::warn!("⏱️  Cleanup time budget exhausted after {:.1}s", elapsed);
+    }
+    tracing::info!("✅ Cleaned up {} objects in {:.1}s ({:.1} objects/s)", cleaned, elapsed, cleaned as f64 / elapsed.max(f64::EPSILON));
+    remaining
+}
+
+/// Deletes `keys` within the configured time budget and records whatever is
+/// left in the remainder file for `gc`.
+async fn cleanup_objects(client: &dyn BenchClient, prefix: &str, object_size_bytes: usize, keys: &[String], options: &CleanupOptions) -> Result<()> {
+    // Whichever of --cleanup-timeout and what's left of --max-run-time ends first
+    let budget = match (options.cleanup_timeout.map(Duration::from_secs), run_time_left()) {
+        (Some(timeout), Some(left)) => Some(timeout.min(left)),
+        (timeout, left) => timeout.or(left),
+    };
+    let remaining = delete_objects(client, keys, budget, options.cleanup_concurrency).await;
+    if remaining.is_empty() {
+        return Ok(());
+    }
+    let mut remainder = DatasetManifest {
+        prefix: prefix.to_string(),
+        object_size_bytes,
+        keys: remaining,
+        incomplete: false,
+        seeded: false,
+    };
+    // Per run, so concurrent runs don't overwrite each other's leftovers; the
+    // steps of a sweep share the run ID, so add to what earlier steps left
+    let path = options.cleanup_remainder.clone()
+        .unwrap_or_else(|| format!("qps-bench-remainder-{}.json", run_metadata().run_id).into());
+    if path.exists() {
+        let mut keys = DatasetManifest::load(&path)?.keys;
+        keys.append(&mut remainder.keys);
+        remainder.keys = keys;
+    }
+    remainder.save(&path)?;
+    tracing::warn!("⚠️  {} objects left behind, recorded in {}; delete them with `qps-bench gc {}`",
+                   remainder.keys.len(), path.display(), path.display());
+    Ok(())
+}
+
+async fn gc_remainder(gc: GcArgs) -> Result<()> {
+    gc.backend.validate()?;
+    let client = create_client(&gc.backend)?;
+    let mut remainder = DatasetManifest::load(&gc.remainder)?;
+    remainder.keys = delete_objects(client.as_ref(), &remainder.keys, gc.cleanup_timeout.map(Duration::from_secs), gc.cleanup_concurrency).await;
+    if remainder.keys.is_empty() {
+        std::fs::remove_file(&gc.remainder)?;
+        tracing::info!("🗑️  All objects deleted; removed {}", gc.remainder.display());
+    } else {
+        remainder.save(&gc.remainder)?;
+        tracing::warn!("⚠️  {} objects still left, recorded in {}", remainder.keys.len(), gc.remainder.display());
+    }
+    Ok(())
+}
+
+/// Runs the configured benchmark and returns the results document. Without
+/// `dataset` the run creates its own dataset and (with --cleanup) deletes it.
+async fn run_benchmark(args: &Args, dataset: Option<&DatasetManifest>) -> Result<serde_json::Value> {
+    args.backend.validate()?;
+    let capped = concurrency_guardrail(args);
+    let args = capped.as_ref().unwrap_or(args);
+    
+    let workload = if args.phases.is_empty() {
+        args.mode.clone()
+    } else {
+        args.phases.iter().map(|phase| phase.mode.as_str()).collect::<Vec<_>>().join(" → ")
+    };
+    let duration = if args.until_stopped() { "until stopped".to_string() } else { format!("{}s", args.duration_seconds) };
+    let addressing = resolve_addressing_style(&args.backend)?;
+    tracing::info!(
+        mode = %workload,
+        service = %args.backend.service,
+        endpoint = %args.backend.endpoint,
+        bucket = %args.backend.bucket,
+        region = %args.backend.region,
+        addressing = %addressing,
+        client = %args.backend.client,
+        concurrency = args.concurrency,
+        duration = %duration,
+        "🚀 OpenDAL QPS Benchmark"
+    );
+    if let Some(preset) = &args.preset {
+        tracing::info!(preset = %preset, "Using a preset");
+    }
+    if let Some(engine) = &args.backend.transfer_engine {
+        tracing::info!(engine = %engine, part_bytes = args.backend.transfer_chunk_bytes, parts_at_once = args.backend.transfer_concurrency, "Using a transfer engine");
+    }
+    if args.backend.proxy.is_some() {
+        tracing::info!(proxy = "--proxy", "Using a proxy");
+    } else if env_proxy_configured() {
+        tracing::info!(proxy = "environment", "Using a proxy");
+    }
+    if args.backend.insecure_skip_tls_verify {
+        tracing::warn!("⚠️  TLS certificate verification is disabled");
+    }
+    if matches!(args.mode.as_str(), "scatter_write" | "commit") {
+        tracing::info!(fanout = args.fanout, "Writing with fanout");
+    }
+    if matches!(args.mode.as_str(), "dual_write" | "read_compare") {
+        tracing::info!(
+            mirror = args.mirror_endpoint.as_deref().unwrap_or("--mirror-endpoint not set"),
+            mirror_bucket = args.mirror_bucket.as_deref().unwrap_or(&args.backend.bucket),
+            "Mirroring to a second backend"
+        );
+    }
+    
+    let client = create_client(&args.backend)?;
+    note_client_ready();
+    let mut operations = Vec::new();
+    let modes: Vec<&str> = if args.phases.is_empty() { vec![args.mode.as_str()] } else { args.phases.iter().map(|phase| phase.mode.as_str()).collect() };
+    for operation in modes.into_iter().flat_map(|mode| mode_operations(args, mode)) {
+        if !operations.contains(&operation) {
+            operations.push(operation);
+        }
+    }
+    if args.cleanup && client.delete_batch_size() > 1 {
+        operations.push(BackendOperation::BatchDelete);
+    }
+    let mut unsupported = probe_operations(client.as_ref(), &args.prefix, &operations).await;
+    if let Some(capability) = client.capability() {
+        log_capabilities(&capability);
+        for operation in &operations {
+            if !operation.supported_by(&capability) && !unsupported.contains(operation) {
+                unsupported.push(*operation);
+            }
+        }
+    }
+    if unsupported.contains(&BackendOperation::BatchDelete) {
+        tracing::warn!("⚠️  The backend doesn't support multi-object deletes; cleanup deletes one object per request");
+        with_run(|run| run.single_deletes.store(true, std::sync::atomic::Ordering::Relaxed));
+    }
+    let missing = missing_operations(args, &args.mode, &unsupported);
+    if args.phases.is_empty() && !missing.is_empty() {
+        let message = format!("{} needs {}, which the backend doesn't support", args.mode, missing.join(", "));
+        return Err(RunFailure::new(FailureCategory::Unsupported, message).into());
+    }
+    
+    let loaded;
+    let dataset = match (dataset, &args.manifest) {
+        (None, Some(path)) if path.exists() => {
+            let mut manifest = DatasetManifest::load(path)?;
+            if manifest.incomplete {
+                create_dataset(client.as_ref(), &mut manifest, args.objects, args.prepare_concurrency, Some(path)).await?;
+                persist_manifest(client.as_ref(), &manifest, path, args.upload_manifest).await?;
+            }
+            tracing::info!("📂 Reusing dataset from {} ({} objects)", path.display(), manifest.keys.len());
+            loaded = manifest;
+            Some(&loaded)
+        }
+        (dataset, _) => dataset,
+    };
+    if args.mode == "read_compare" && dataset.is_none() {
+        let message = "read_compare compares existing objects: pass the --manifest (or `run --dataset`) of a dataset already copied to the mirror";
+        return Err(RunFailure::new(FailureCategory::Config, message).into());
+    }
+    if args.verify_reads && dataset.is_some_and(|d| !d.seeded) {
+        let message = "--verify-reads needs seeded content: recreate the dataset with this version of `qps-bench prepare`";
+        return Err(RunFailure::new(FailureCategory::Config, message).into());
+    }
+    // Throughput, CSV and the read_write halves all size objects from --object-size-bytes
+    if let Some(dataset) = dataset.filter(|d| d.object_size_bytes != args.object_size_bytes) {
+        let message = format!("the dataset holds {}-byte objects but --object-size-bytes is {}: pass --object-size-bytes {}",
+                              dataset.object_size_bytes, args.object_size_bytes, dataset.object_size_bytes);
+        return Err(RunFailure::new(FailureCategory::Config, message).into());
+    }
+    // Datasets that were reused, or persisted for reuse, outlive the run
+    let keep_dataset = dataset.is_some() || args.manifest.is_some();
+    
+    let seed = args.seed.unwrap_or_else(rand::random);
+    let prefix = match dataset {
+        Some(dataset) => dataset.prefix.clone(),
+        None => dataset_prefix(&args.prefix, seed),
+    };
+    let object_size_bytes = args.object_size_bytes;
+    
+    tracing::info!("Using prefix: {}", prefix);
+    tracing::info!("Seed: {}", seed);
+    
+    let duration = if args.until_stopped() { UNTIL_STOPPED_LIMIT } else { Duration::from_secs(args.duration_seconds) };
+    let not_found_retry = Duration::from_millis(args.not_found_retry_ms);
+    let key_popularity = match &args.key_popularity {
+        Some(path) => {
+            let popularity = load_key_popularity(path)?;
+            tracing::info!("Key popularity: {} ranked keys from {}", popularity.len(), path.display());
+            if popularity.len() > args.objects && dataset.is_none() {
+                tracing::warn!("⚠️  More ranked keys than --objects ({}); ranks beyond the dataset share objects", args.objects);
+            }
+            Some(Arc::new(popularity))
+        }
+        None => None,
+    };
+    
+    // Handle combined read_write mode
+    if args.mode == "read_write" {
+        tracing::info!(duration_s = args.duration_seconds, "Running combined READ + WRITE benchmark, each half for the duration");
+        
+        let keys = dataset_keys(client.as_ref(), dataset, &prefix, args, seed).await?;
+        let read_state = Arc::new(BenchmarkState::new(client.clone(), keys, args.object_size_bytes, prefix.clone())
+            .with_seed(seed)
+            .with_intervals()
+            .with_series_interval(args.timeseries_interval_seconds)
+            .with_op_timeout(args.op_timeout_ms)
+            .with_target_rate(args.target_rate)
+            .with_worker_stats(args.worker_stats)
+            .with_key_popularity(key_popularity)
+            .with_read_check(ReadCheck::for_args(args)?));
+        
+        // Run read benchmark
+        tracing::info!(half = "read", concurrency = args.concurrency, "📊 Running READ benchmark");
+        set_live_mode("read_small", args.concurrency);
+        let (read_ok, read_err, read_hist) = run_read_benchmark(read_state.clone(), duration, args.concurrency, not_found_retry).await;
+        let mut read_result = BenchmarkResult::from_run(args, "read_small", args.concurrency, read_ok, read_err, &read_hist, &read_state);
+        read_result.key_isolation = Some(KeyIsolation::Disjoint);
+        
+        report!("{}", serde_json::to_string_pretty(&read_result)?);
+        tracing::info!(
+            half = "read",
+            qps = read_result.qps,
+            p50_ms = read_result.latency_us_p50 as f64 / 1000.0,
+            p95_ms = read_result.latency_us_p95 as f64 / 1000.0,
+            p99_ms = read_result.latency_us_p99 as f64 / 1000.0,
+            "READ finished"
+        );
+        
+        // Run write benchmark
+        tracing::info!(half = "write", concurrency = args.concurrency, "📊 Running WRITE benchmark");
+        // Empty key list for write mode: writes get fresh keys and never
+        // overwrite the dataset the read half used (KeyIsolation::Disjoint)
+        let write_state = Arc::new(BenchmarkState::new(client.clone(), Vec::new(), args.object_size_bytes, prefix.clone()).with_seed(seed.wrapping_add(1)).with_intervals().with_payload_pool(args.payload_pool).with_op_timeout(args.op_timeout_ms)
+            .with_series_interval(args.timeseries_interval_seconds)
+            .with_target_rate(args.target_rate)
+            .with_worker_stats(args.worker_stats));
+        
+        set_live_mode("write_small", args.concurrency);
+        let (write_ok, write_err, write_hist) = run_write_benchmark(write_state.clone(), duration, args.concurrency).await;
+        let mut write_result = BenchmarkResult::from_run(args, "write_small", args.concurrency, write_ok, write_err, &write_hist, &write_state);
+        write_result.key_isolation = Some(KeyIsolation::Disjoint);
+        
+        report!("{}", serde_json::to_string_pretty(&write_result)?);
+        tracing::info!(
+            half = "write",
+            qps = write_result.qps,
+            p50_ms = write_result.latency_us_p50 as f64 / 1000.0,
+            p95_ms = write_result.latency_us_p95 as f64 / 1000.0,
+            p99_ms = write_result.latency_us_p99 as f64 / 1000.0,
+            "WRITE finished"
+        );
+        
+        // Print combined summary
+        report!();
+        report!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+        report!("📊 Combined Results Summary");
+        report!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+        report!("READ Operations:");
+        report!("  QPS:               {:.2}", read_result.qps);
+        report!("  Latency P50:       {:.2} ms", read_result.latency_us_p50 as f64 / 1000.0);
+        report!("  Latency P95:       {:.2} ms", read_result.latency_us_p95 as f64 / 1000.0);
+        report!("  Latency P99:       {:.2} ms", read_result.latency_us_p99 as f64 / 1000.0);
+        report!("  Successful Ops:    {}", read_result.ok_ops);
+        report!("WRITE Operations:");
+        report!("  QPS:               {:.2}", write_result.qps);
+        report!("  Latency P50:       {:.2} ms", write_result.latency_us_p50 as f64 / 1000.0);
+        report!("  Latency P95:       {:.2} ms", write_result.latency_us_p95 as f64 / 1000.0);
+        report!("  Latency P99:       {:.2} ms", write_result.latency_us_p99 as f64 / 1000.0);
+        report!("  Successful Ops:    {}", write_result.ok_ops);
+        report!("Key isolation:       {}", KeyIsolation::Disjoint.describe());
+        
+        let results = serde_json::json!({ "read": read_result, "write": write_result });
+        if let Some(path) = &args.export_bundle {
+            let manifest = DatasetManifest {
+                prefix: prefix.clone(),
+                object_size_bytes,
+                keys: read_state.keys.to_vec(),
+                incomplete: false,
+                seeded: dataset.is_none_or(|d| d.seeded),
+            };
+            export_bundle(path, args, seed, &manifest, &results)?;
+        }
+        
+        // Cleanup if requested
+        if args.cleanup && !keep_dataset && !read_state.keys.is_empty() {
+            cleanup_objects(client.as_ref(), &prefix, object_size_bytes, &read_state.keys, &args.cleanup_options).await?;
+        }
+        
+        return Ok(results);
+    }
+    
+    if !args.phases.is_empty() {
+        let keys = if mode_needs_dataset(&args.phases[0].mode) {
+            dataset_keys(client.as_ref(), dataset, &prefix, args, seed).await?
+        } else {
+            dataset.map(|d| shuffle_keys(d.keys.clone(), args, seed)).unwrap_or_default()
+        };
+        let start = DatasetManifest {
+            prefix: prefix.clone(),
+            object_size_bytes,
+            keys,
+            incomplete: false,
+            seeded: dataset.is_none_or(|d| d.seeded),
+        };
+        let (phased, keys) = run_phases(args, client.clone(), start, key_popularity, seed, &unsupported).await?;
+        let results = serde_json::to_value(&phased)?;
+        
+        if let Some(path) = &args.export_bundle {
+            let manifest = DatasetManifest {
+                prefix: prefix.clone(),
+                object_size_bytes,
+                keys: keys.clone(),
+                incomplete: false,
+                seeded: dataset.is_none_or(|d| d.seeded),
+            };
+            export_bundle(path, args, seed, &manifest, &results)?;
+        }
+        
+        // Objects written by the phases are part of the dataset, so they go too
+        if args.cleanup && !keep_dataset && !keys.is_empty() {
+            cleanup_objects(client.as_ref(), &prefix, object_size_bytes, &keys, &args.cleanup_options).await?;
+        }
+        
+        return Ok(results);
+    }
+    
+    // Pre-create dataset for modes that need it; a stat storm only needs its few keys
+    let keys = if args.mode == "stat_storm" && dataset.is_none() {
+        let storm_args = Args { objects: args.objects.min(args.storm_keys), ..args.clone() };
+        dataset_keys(client.as_ref(), dataset, &prefix, &storm_args, seed).await?
+    } else if mode_needs_dataset(&args.mode) {
+        dataset_keys(client.as_ref(), dataset, &prefix, args, seed).await?
+    } else {
+        Vec::new()
+    };
+    if args.mode == "delete" && keep_dataset {
+        tracing::warn!("⚠️  delete mode removes dataset objects; recreate the dataset before reusing it");
+    }
+    if args.mode == "read_overwrite" && keep_dataset {
+        tracing::warn!("⚠️  read_overwrite replaces the content of {} dataset objects; --verify-reads will fail on them later", args.overwrite_keys);
+    }
+    
+    let state = Arc::new(BenchmarkState::new(client.clone(), keys, args.object_size_bytes, prefix.clone())
+        .with_seed(seed)
+        .with_payload_pool(args.payload_pool)
+        .with_op_timeout(args.op_timeout_ms)
+        .with_target_rate(args.target_rate)
+        .with_worker_stats(args.worker_stats)
+        .with_key_popularity(key_popularity)
+        .with_intervals_for(args)
+        .with_dual_write(DualWriteTarget::for_args(args)?)
+        .with_read_compare(ReadCompareTarget::for_args(args)?)
+        .with_read_check(ReadCheck::for_args(args)?)
+        .with_overwrite_race(&args.mode, args.overwrite_keys)
+        .with_churn(&args.mode, args.churn_pct_per_minute, seed, client, dataset.is_none_or(|d| d.seeded)));
+    
+    let results = if args.sweep_concurrency.is_empty() {
+        let (ok_ops, err_ops, histogram) = run_mode(args, state.clone(), args.concurrency, duration, not_found_retry).await?;
+        let result = BenchmarkResult::from_run(args, &args.mode, args.concurrency, ok_ops, err_ops, &histogram, &state);
+        print_result(&result)?;
+        serde_json::to_value(&result)?
+    } else {
+        let sweep = run_concurrency_sweep(args, state.clone(), duration, not_found_retry).await?;
+        serde_json::to_value(&sweep)?
+    };
+    
+    if let Some(path) = &args.export_bundle {
+        let manifest = DatasetManifest {
+            prefix: prefix.clone(),
+            object_size_bytes,
+            keys: state.keys.to_vec(),
+            incomplete: false,
+            seeded: dataset.is_none_or(|d| d.seeded),
+        };
+        export_bundle(path, args, seed, &manifest, &results)?;
+    }
+    
+    // Cleanup if requested
+    if args.cleanup && !keep_dataset && !state.keys.is_empty() {
+        cleanup_objects(state.client.as_ref(), &prefix, object_size_bytes, &state.keys, &args.cleanup_options).await?;
+    }
+    // dual_write leaves its objects on both backends otherwise
+    if let Some(target) = state.dual_write.as_ref().filter(|_| args.cleanup) {
+        let written = std::mem::take(&mut *state.written.lock().unwrap());
+        if !written.is_empty() {
+            cleanup_objects(state.client.as_ref(), &prefix, object_size_bytes, &written, &args.cleanup_options).await?;
+        }
+        target.cleanup_mirror(&prefix, object_size_bytes, &args.cleanup_options).await?;
+    }
+    
+    Ok(results)
+}
+
+/// A benchmark to run from code with [`run`]: the command line's run
+/// settings, starting from their declared defaults. Nothing is read from the
+/// environment, so credentials come from [`Scenario::with_credentials`].
+#[derive(Debug, Clone)]
+pub struct Scenario {
+    args: Args,
+}
+
+impl Scenario {
+    /// A run of `mode` (any of the command line's `--mode` values).
+    pub fn new(mode: impl Into<String>) -> Result<Self> {
+        let mut args = default_args()?;
+        args.mode = mode.into();
+        Ok(Scenario { args })
+    }
+
+    pub fn with_endpoint(mut self, endpoint: impl Into<String>) -> Self {
+        self.args.backend.endpoint = endpoint.into();
+        self
+    }
+
+    pub fn with_bucket(mut self, bucket: impl Into<String>) -> Self {
+        self.args.backend.bucket = bucket.into();
+        self
+    }
+
+    pub fn with_region(mut self, region: impl Into<String>) -> Self {
+        self.args.backend.region = region.into();
+        self
+    }
+
+    pub fn with_credentials(mut self, access_key: impl Into<String>, secret_key: impl Into<String>) -> Self {
+        self.args.backend.access_key = access_key.into();
+        self.args.backend.secret_key = secret_key.into();
+        self
+    }
+
+    /// Which client issues the requests: opendal (the default), raw or aws-sdk.
+    pub fn with_client(mut self, client: impl Into<String>) -> Self {
+        self.args.backend.client = client.into();
+        self
+    }
+
+    /// Prefix the run's objects are created under.
+    pub fn with_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.args.prefix = prefix.into();
+        self
+    }
+
+    pub fn with_concurrency(mut self, concurrency: usize) -> Self {
+        self.args.concurrency = concurrency;
+        self
+    }
+
+    /// How long the benchmark runs, in whole seconds (at least one).
+    pub fn with_duration(mut self, duration: Duration) -> Self {
+        self.args.duration_seconds = duration.as_secs().max(1);
+        self
+    }
+
+    /// Objects in the dataset of the modes that read existing objects.
+    pub fn with_objects(mut self, objects: usize) -> Self {
+        self.args.objects = objects;
+        self
+    }
+
+    pub fn with_object_size(mut self, bytes: usize) -> Self {
+        self.args.object_size_bytes = bytes;
+        self
+    }
+
+    /// Offers ops at this rate instead of as fast as the workers go.
+    pub fn with_target_rate(mut self, ops_per_second: f64) -> Self {
+        self.args.target_rate = Some(ops_per_second);
+        self
+    }
+
+    /// Seeds the keys, content and key order, so the same seed replays the
+    /// same workload.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.args.seed = Some(seed);
+        self
+    }
+
+    /// Whether the run deletes the objects it created (the default).
+    pub fn with_cleanup(mut self, cleanup: bool) -> Self {
+        self.args.cleanup = cleanup;
+        self
+    }
+}
+
+/// The typed outcome of a [`run`].
+#[derive(Debug, Clone)]
+pub struct BenchReport {
+    /// Shared by every result of the run
+    pub run_id: String,
+    /// One per mode run: a single result, or one per phase, sweep step or
+    /// read_write half
+    pub results: Vec<ModeReport>,
+    /// Stopped early (a signal or --max-run-time), so the results are partial
+    pub interrupted: bool,
+    /// The full results document, as the command line writes it
+    pub document: serde_json::Value,
+}
+
+/// The headline numbers of one mode's run.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ModeReport {
+    /// `mode`, `<phase>:<mode>`, `<mode>@<concurrency>`, `read` or `write`
+    pub label: String,
+    pub mode: String,
+    pub concurrency: usize,
+    pub ok_ops: u64,
+    pub err_ops: u64,
+    pub qps: f64,
+    pub latency_p50: Duration,
+    pub latency_p95: Duration,
+    pub latency_p99: Duration,
+    pub latency_mean: Duration,
+    pub latency_max: Duration,
+}
+
+impl ModeReport {
+    fn new(label: String, result: &BenchmarkResult) -> Self {
+        ModeReport {
+            label,
+            mode: result.mode.clone(),
+            concurrency: result.concurrency,
+            ok_ops: result.ok_ops,
+            err_ops: result.err_ops,
+            qps: result.qps,
+            latency_p50: Duration::from_micros(result.latency_us_p50),
+            latency_p95: Duration::from_micros(result.latency_us_p95),
+            latency_p99: Duration::from_micros(result.latency_us_p99),
+            latency_mean: Duration::from_micros(result.latency_us_mean),
+            latency_max: Duration::from_micros(result.latency_us_max),
+        }
+    }
+}
+
+/// Runs `scenario` and returns its results without printing anything:
+/// progress and warnings are `tracing` events for the caller's subscriber,
+/// and no result sinks, signal handling or exit codes are involved. Each
+/// call is a run of its own, with its own run id and stop state, so calls
+/// may follow one another or overlap in one process.
+pub async fn run(scenario: Scenario) -> Result<BenchReport> {
+    let args = scenario.args;
+    RUN.scope(Arc::new(RunContext::new(false)), async move {
+        check_workload_settings(&args)?;
+        let document = run_benchmark(&args, None).await?;
+        let results = labelled_results(&document)?
+            .into_iter()
+            .map(|(label, result)| ModeReport::new(label, &result))
+            .collect();
+        Ok(BenchReport {
+            run_id: run_metadata().run_id,
+            results,
+            interrupted: stopped_early(),
+            document,
+        })
+    })
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn throttle_reason_reads_status_and_code() {
+        let response = |status, code: Option<&str>| ErrorResponse { status, code: code.map(str::to_string) };
+        let rate_limited = opendal::Error::new(opendal::ErrorKind::RateLimited, "throttled");
+        let unexpected = opendal::Error::new(opendal::ErrorKind::Unexpected, "failed");
+        assert_eq!(throttle_reason(&unexpected, Some(&response(503, Some("SlowDown")))), Some("slow_down"));
+        assert_eq!(throttle_reason(&rate_limited, Some(&response(503, None))), Some("slow_down"));
+        assert_eq!(throttle_reason(&unexpected, Some(&response(503, None))), Some("service_unavailable"));
+        assert_eq!(throttle_reason(&unexpected, Some(&response(429, None))), Some("too_many_requests"));
+        assert_eq!(throttle_reason(&rate_limited, None), Some("rate_limited"));
+        assert_eq!(throttle_reason(&unexpected, Some(&response(500, Some("InternalError")))), None);
+    }
+
+    #[test]
+    fn scenario_defaults_ignore_the_environment() {
+        std::env::set_var("QPS_BENCH_SESSION_TOKEN", "from-the-environment");
+        let scenario = Scenario::new("stat").unwrap();
+        std::env::remove_var("QPS_BENCH_SESSION_TOKEN");
+        assert_eq!(scenario.args.mode, "stat");
+        assert_eq!(scenario.args.backend.session_token, None);
+    }
+
+    /// Writes `text` to a config file named `name` in the temp dir.
+    #[cfg(any(feature = "toml-config", feature = "yaml-config"))]
+    fn config_file(name: &str, text: &str) -> std::path::PathBuf {
+        let path = std::env::temp_dir().join(format!("qps-bench-{}-{}", Uuid::new_v4(), name));
+        std::fs::write(&path, text).unwrap();
+        path
+    }
+
+    fn setting<'a>(settings: &'a [(String, serde_json::Value)], key: &str) -> Option<&'a serde_json::Value> {
+        settings.iter().find(|(name, _)| name == key).map(|(_, value)| value)
+    }
+
+    #[test]
+    #[cfg(feature = "toml-config")]
+    fn toml_config_tables_only_group_settings() {
+        let path = config_file("run.toml", "concurrency = 8\n\n[backend]\nbucket = \"bench\"\n\n[workload]\nmode = \"stat\"\n");
+        let settings = load_config_settings(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(settings.len(), 3);
+        assert_eq!(setting(&settings, "concurrency"), Some(&serde_json::json!(8)));
+        assert_eq!(setting(&settings, "bucket"), Some(&serde_json::json!("bench")));
+        assert_eq!(setting(&settings, "mode"), Some(&serde_json::json!("stat")));
+    }
+
+    #[test]
+    #[cfg(feature = "yaml-config")]
+    fn yaml_config_is_read_by_extension() {
+        let path = config_file("run.yaml", "workload:\n  mode: list\n  objects: 100\n");
+        let settings = load_config_settings(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(setting(&settings, "mode"), Some(&serde_json::json!("list")));
+        assert_eq!(setting(&settings, "objects"), Some(&serde_json::json!(100)));
+    }
+
+    #[test]
+    #[cfg(feature = "toml-config")]
+    fn config_must_be_a_table_of_settings() {
+        let path = config_file("broken.toml", "mode = \"stat\n");
+        let error = load_config_settings(&path).unwrap_err();
+        std::fs::remove_file(&path).unwrap();
+        assert!(error.to_string().starts_with("Invalid TOML in "), "{}", error);
+    }
+
+    #[test]
+    fn settings_become_flags_unless_given_on_the_command_line() {
+        let matches = Cli::command().try_get_matches_from(["qps-bench", "--concurrency", "4"]).unwrap();
+        let settings = vec![
+            ("concurrency".to_string(), serde_json::json!(8)),
+            ("mode".to_string(), serde_json::json!("stat")),
+            ("cleanup".to_string(), serde_json::json!(false)),
+            ("labels".to_string(), serde_json::json!(["team=storage", "ci=true"])),
+        ];
+        assert_eq!(
+            settings_to_args(settings, &matches).unwrap(),
+            ["--mode", "stat", "--cleanup=false", "--label", "team=storage", "--label", "ci=true"]
+        );
+        let unknown = settings_to_args(vec![("concurency".to_string(), serde_json::json!(8))], &matches).unwrap_err();
+        assert_eq!(unknown.to_string(), "Unknown setting 'concurency' in config file");
+    }
+
+    #[test]
+    fn presets_expand_into_settings() {
+        let settings = preset_settings("throughput").unwrap();
+        assert_eq!(setting(&settings, "mode"), Some(&serde_json::json!("read_small")));
+        assert_eq!(setting(&settings, "object_size_bytes"), Some(&serde_json::json!("16777216")));
+        let unknown = preset_settings("fast").unwrap_err().to_string();
+        assert!(unknown.starts_with("Unknown preset: fast. Supported presets: small-object-metadata, throughput"), "{}", unknown);
+    }
+
+    #[test]
+    fn edit_distance_counts_single_character_edits() {
+        assert_eq!(edit_distance("concurency", "concurrency"), 1);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(edit_distance("", "mode"), 4);
+        assert_eq!(edit_distance("mode", "mode"), 0);
+    }
+
+    #[test]
+    fn default_settings_have_no_conflicts() {
+        let args = default_args().unwrap();
+        assert_eq!(config_conflicts(&args), Vec::<String>::new());
+        assert!(check_workload_settings(&args).is_ok());
+    }
+
+    #[test]
+    fn config_conflicts_lists_every_problem() {
+        let mut args = default_args().unwrap();
+        args.mode = "stats".to_string();
+        args.target_rate = Some(0.0);
+        args.tui = true;
+        args.stream_events = true;
+        args.sinks = vec!["file".to_string()];
+        let problems = config_conflicts(&args);
+        assert_eq!(problems.len(), 4, "{:?}", problems);
+        assert!(problems[0].starts_with("unknown mode 'stats' (supported: stat, "), "{}", problems[0]);
+        assert_eq!(problems[1], "--sink file needs a target, as file=...");
+        assert_eq!(problems[2], "target_rate must be positive");
+        assert_eq!(problems[3], "tui and stream_events both need stdout");
+        let error = check_workload_settings(&args).unwrap_err();
+        assert_eq!(FailureCategory::of(&error), FailureCategory::Config);
+    }
+
+    #[test]
+    fn mirror_settings_need_a_mirrored_mode() {
+        let mut args = default_args().unwrap();
+        args.mode = "dual_write".to_string();
+        assert_eq!(config_conflicts(&args), ["dual_write and read_compare need mirror_endpoint"]);
+        args.mode = "stat".to_string();
+        args.mirror_bucket = Some("mirror".to_string());
+        assert_eq!(config_conflicts(&args), ["mirror_* settings are ignored outside the dual_write and read_compare modes"]);
+    }
+
+    #[test]
+    fn access_log_fields_keep_bracketed_and_quoted_fields_whole() {
+        let line = "79a5 bench [06/Feb/2019:00:00:38 +0000] 192.0.2.3 - 3E57 REST.GET.OBJECT data/1 \"GET /bench/data/1 HTTP/1.1\" 200";
+        let fields = access_log_fields(line);
+        assert_eq!(fields.len(), 10);
+        assert_eq!(fields[2], "06/Feb/2019:00:00:38 +0000");
+        assert_eq!(fields[6], "REST.GET.OBJECT");
+        assert_eq!(fields[8], "GET /bench/data/1 HTTP/1.1");
+        assert_eq!(fields[9], "200");
+    }
+}
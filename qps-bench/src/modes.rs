@@ -0,0 +1,2088 @@
+//! The benchmark modes and the loops that drive them, plus the probing,
+//! phasing and sweeps that pick which modes run and how hard.
+
+use super::*;
+
+/// Drives `op` in a closed loop for `duration` with `concurrency` workers, each
+/// running its ops one after another, and records the latency of every
+/// successful call.
+pub(crate) async fn run_closed_loop<F, Fut>(state: Arc<BenchmarkState>, duration: Duration, concurrency: usize, op: F) -> (u64, u64, Histogram<u64>)
+where
+    F: Fn(Arc<BenchmarkState>) -> Fut,
+    Fut: Future<Output = opendal::Result<()>> + Send + 'static,
+{
+    let start = Instant::now();
+    let end_time = start + duration;
+    {
+        let mut started_at = state.started_at.lock().unwrap();
+        if started_at.is_none() {
+            *started_at = Some(std::time::SystemTime::now());
+            reset_peak_rss();
+            *state.alloc_baseline.lock().unwrap() = allocation_counters();
+        }
+    }
+    if let Some(workers) = &state.workers {
+        workers.start(concurrency);
+    }
+    
+    let histogram = Arc::new(std::sync::Mutex::new(Histogram::<u64>::new(3).unwrap()));
+    let ok_count = Arc::new(std::sync::atomic::AtomicU64::new(0));
+    let err_count = Arc::new(std::sync::atomic::AtomicU64::new(0));
+    // Slots of the --target-rate schedule, claimed in order by whichever worker is free
+    let scheduled = std::sync::atomic::AtomicU64::new(0);
+    // Op events carry the mode this loop runs, even if published after the next one starts
+    let event_mode: Arc<str> = with_run(|run| run.live_metrics.get().map(|live| live.mode.lock().unwrap().as_str().into())).unwrap_or_else(|| "".into());
+    
+    let run_worker = |worker: usize| {
+        let (state, op, scheduled) = (&state, &op, &scheduled);
+        let (histogram, ok_count, err_count, event_mode) = (&histogram, &ok_count, &err_count, &event_mode);
+        async move {
+            while Instant::now() < end_time && !interrupted() {
+                // With --target-rate each op has an intended start on a fixed schedule;
+                // a late start (every worker busy) still counts from the intended one
+                let intended_start = match state.target_interval {
+                    Some(interval) => {
+                        let slot = scheduled.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+                        // u64 nanoseconds: `interval * u32` overflows after 2^32 ops
+                        let intended = start + Duration::from_nanos((interval.as_nanos() as u64).saturating_mul(slot));
+                        if intended >= end_time {
+                            break;
+                        }
+                        tokio::time::sleep_until(intended.into()).await;
+                        Some(intended)
+                    }
+                    None => None,
+                };
+                let dispatched = Instant::now();
+                let histogram_clone = histogram.clone();
+                let ok_count_clone = ok_count.clone();
+                let err_count_clone = err_count.clone();
+                let task_state = state.clone();
+                let task_mode = event_mode.clone();
+                let fut = op(state.clone());
+                // Root span for the op's OpenDAL spans; only sampled ops are exported
+                #[cfg(feature = "otlp-traces")]
+                let fut = tracing::Instrument::instrument(fut, tracing::info_span!("qps_bench.op"));
+                
+                // Each op runs as its own task so the runtime spreads them over its threads
+                let handle = spawn_in_run(OP_CONTEXT.scope(OpContext::default(), async move {
+                    let _in_flight = LiveInFlight::start();
+                    task_state.record_interval_start(start.elapsed());
+                    let op_start = Instant::now();
+                    {
+                        let mut queued = task_state.queue_latencies.lock().unwrap();
+                        if let Some(intended) = intended_start {
+                            queued.0.record(dispatched.saturating_duration_since(intended).as_micros() as u64).ok();
+                        }
+                        queued.1.record(op_start.saturating_duration_since(dispatched).as_micros() as u64).ok();
+                    }
+                    OP_CONTEXT.with(|context| context.started.set(Some(op_start)));
+                    // None if the op timed out
+                    let result = match task_state.op_timeout {
+                        Some(timeout) => tokio::time::timeout(timeout, fut).await.ok(),
+                        None => Some(fut.await),
+                    };
+                    // A timed-out op's last attempt was cut short, so only its retries count
+                    let attempts = if result.is_some() { OP_CONTEXT.with(OpContext::attempt_latencies) } else { Vec::new() };
+                    task_state.record_retries(OP_CONTEXT.with(|context| context.retries.get()), &attempts);
+                    match result {
+                        Some(Ok(())) => {
+                            let latency_us = op_start.elapsed().saturating_sub(op_throttled()).as_micros() as u64;
+                            record_live_op(Some(latency_us));
+                            publish_op_event(&task_mode, latency_us, "ok");
+                            histogram_clone.lock().unwrap().record(latency_us).ok();
+                            if let Some(intended) = intended_start {
+                                let corrected_us = intended.elapsed().saturating_sub(op_throttled()).as_micros() as u64;
+                                task_state.corrected_latencies.lock().unwrap().record(corrected_us).ok();
+                            }
+                            ok_count_clone.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+                            task_state.record_interval(start.elapsed(), Some(latency_us));
+                            task_state.record_worker(worker, Some(latency_us));
+                        }
+                        Some(Err(e)) => {
+                            record_live_op(None);
+                            publish_op_event(&task_mode, op_start.elapsed().as_micros() as u64, "error");
+                            err_count_clone.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+                            match throttle_reason(&e, take_error_response().as_ref()) {
+                                Some(reason) => task_state.record_interval_throttled(start.elapsed(), reason),
+                                None => task_state.record_interval(start.elapsed(), None),
+                            }
+                            task_state.record_worker(worker, None);
+                        }
+                        None => {
+                            record_live_op(None);
+                            publish_op_event(&task_mode, op_start.elapsed().as_micros() as u64, "timeout");
+                            task_state.timeouts.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+                            task_state.record_interval(start.elapsed(), None);
+                            task_state.record_worker(worker, None);
+                        }
+                    }
+                }));
+                let _ = handle.await;
+            }
+        }
+    };
+    futures::future::join_all((0..concurrency).map(run_worker)).await;
+    
+    if interrupted() {
+        *state.interrupted_after.lock().unwrap() = Some(start.elapsed());
+    }
+    // The ops counted include those drained after the deadline, so QPS is over
+    // the time until the last one finished
+    let elapsed = start.elapsed();
+    *state.elapsed.lock().unwrap().get_or_insert(Duration::ZERO) += elapsed;
+    
+    let hist = histogram.lock().unwrap().clone();
+    (ok_count.load(std::sync::atomic::Ordering::Relaxed), 
+     err_count.load(std::sync::atomic::Ordering::Relaxed),
+     hist)
+}
+
+/// Share of worker time that may go to producing payloads before the client,
+/// not the backend, is taken to be the bottleneck.
+pub(crate) const PAYLOAD_GENERATION_BOUND_SHARE: f64 = 0.2;
+
+/// Upper bound on the memory held by a payload pool; large objects get fewer buffers.
+pub(crate) const PAYLOAD_POOL_MAX_BYTES: usize = 256 * 1024 * 1024;
+
+/// Write payloads: random bytes, so compressing or deduplicating backends
+/// aren't flattered. Each worker produces the payload for its own next write,
+/// so time spent generating is time that worker isn't issuing ops; it is
+/// tracked to tell a generation-bound client from a slow backend.
+pub(crate) struct PayloadSource {
+    size: usize,
+    pool: Vec<Vec<u8>>,
+    next: std::sync::atomic::AtomicUsize,
+    generated: std::sync::atomic::AtomicU64,
+    generation_nanos: std::sync::atomic::AtomicU64,
+}
+
+impl PayloadSource {
+    fn new(size: usize, pool_buffers: usize) -> Self {
+        let pool_buffers = pool_buffers.min((PAYLOAD_POOL_MAX_BYTES / size.max(1)).max(1));
+        let pool = (0..pool_buffers).map(|_| Self::random(size)).collect();
+        PayloadSource {
+            size,
+            pool,
+            next: std::sync::atomic::AtomicUsize::new(0),
+            generated: std::sync::atomic::AtomicU64::new(0),
+            generation_nanos: std::sync::atomic::AtomicU64::new(0),
+        }
+    }
+
+    fn random(size: usize) -> Vec<u8> {
+        let mut data = vec![0u8; size];
+        rand::thread_rng().fill_bytes(&mut data);
+        data
+    }
+
+    /// Returns the next payload, timing how long it took to produce.
+    fn next(&self) -> Vec<u8> {
+        let start = Instant::now();
+        let data = if self.pool.is_empty() {
+            Self::random(self.size)
+        } else {
+            let index = self.next.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+            self.pool[index % self.pool.len()].clone()
+        };
+        self.generation_nanos.fetch_add(start.elapsed().as_nanos() as u64, std::sync::atomic::Ordering::Relaxed);
+        self.generated.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        data
+    }
+
+    /// Reports payload generation over a run of `workers` lasting `elapsed`,
+    /// warning when it dominated, and resets the counters. None if no payloads
+    /// were produced.
+    pub(crate) fn take_report(&self, elapsed: Duration, workers: usize) -> Option<PayloadReport> {
+        let payloads = self.generated.swap(0, std::sync::atomic::Ordering::Relaxed);
+        let generation_nanos = self.generation_nanos.swap(0, std::sync::atomic::Ordering::Relaxed);
+        if payloads == 0 {
+            return None;
+        }
+        let generation_share = generation_nanos as f64 / (elapsed.as_nanos() as f64 * workers as f64).max(1.0);
+        let generation_bound = generation_share > PAYLOAD_GENERATION_BOUND_SHARE;
+        if generation_bound {
+            tracing::warn!("⚠️  Payload generation took {:.0}% of worker time: QPS is limited by the client, not the backend{}",
+                           generation_share * 100.0,
+                           if self.pool.is_empty() { " (use --payload-pool to pre-generate payloads)" } else { "" });
+        }
+        Some(PayloadReport {
+            pool_buffers: self.pool.len(),
+            payloads,
+            generation_ms: generation_nanos as f64 / 1_000_000.0,
+            generation_share,
+            generation_bound,
+        })
+    }
+}
+
+impl BenchmarkState {
+    pub(crate) fn new(client: Arc<dyn BenchClient>, keys: Vec<String>, object_size: usize, prefix: String) -> Self {
+        let verbs = VerbStatsMap::default();
+        BenchmarkState {
+            client: Arc::new(MeteredClient { inner: client, verbs: verbs.clone() }),
+            verbs,
+            rng: std::sync::Mutex::new(StdRng::from_entropy()),
+            written: std::sync::Mutex::new(Vec::new()),
+            deleted: std::sync::Mutex::new(std::collections::HashSet::new()),
+            keys: Arc::new(keys),
+            object_size,
+            prefix,
+            next_key_index: Arc::new(std::sync::atomic::AtomicUsize::new(0)),
+            phases: std::sync::Mutex::new(BTreeMap::new()),
+            intervals: None,
+            series_interval: SERIES_INTERVAL,
+            in_flight: std::sync::atomic::AtomicU64::new(0),
+            second_counts: std::sync::Mutex::new(Vec::new()),
+            throttle_reasons: std::sync::Mutex::new(BTreeMap::new()),
+            interrupted_after: std::sync::Mutex::new(None),
+            elapsed: std::sync::Mutex::new(None),
+            payloads: PayloadSource::new(object_size, 0),
+            key_popularity: None,
+            key_latencies: std::sync::Mutex::new(BTreeMap::new()),
+            truncated_lists: std::sync::atomic::AtomicU64::new(0),
+            started_at: std::sync::Mutex::new(None),
+            alloc_baseline: std::sync::Mutex::new(None),
+            dual_write: None,
+            read_compare: None,
+            read_check: None,
+            op_timeout: None,
+            timeouts: std::sync::atomic::AtomicU64::new(0),
+            retried_ops: std::sync::atomic::AtomicU64::new(0),
+            retries: std::sync::atomic::AtomicU64::new(0),
+            attempt_latencies: std::sync::Mutex::new((Histogram::<u64>::new(3).unwrap(), Histogram::<u64>::new(3).unwrap())),
+            target_interval: None,
+            corrected_latencies: std::sync::Mutex::new(Histogram::<u64>::new(3).unwrap()),
+            queue_latencies: std::sync::Mutex::new((Histogram::<u64>::new(3).unwrap(), Histogram::<u64>::new(3).unwrap())),
+            workers: None,
+            overwrite: None,
+            churn: None,
+        }
+    }
+
+    /// Records the retries one op needed and the latency of each attempt.
+    fn record_retries(&self, retries: u32, attempts: &[Duration]) {
+        if retries > 0 {
+            self.retried_ops.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+            self.retries.fetch_add(retries as u64, std::sync::atomic::Ordering::Relaxed);
+        }
+        if let Some(first) = attempts.first() {
+            let mut histograms = self.attempt_latencies.lock().unwrap();
+            histograms.0.record(first.as_micros() as u64).ok();
+            for attempt in attempts {
+                histograms.1.record(attempt.as_micros() as u64).ok();
+            }
+        }
+    }
+
+    /// Returns the retry counts and attempt latencies so far and resets them.
+    pub(crate) fn take_retry_stats(&self) -> RetryStats {
+        let (first_attempt, attempts) = std::mem::replace(
+            &mut *self.attempt_latencies.lock().unwrap(),
+            (Histogram::<u64>::new(3).unwrap(), Histogram::<u64>::new(3).unwrap()),
+        );
+        RetryStats {
+            retried_ops: self.retried_ops.swap(0, std::sync::atomic::Ordering::Relaxed),
+            retries: self.retries.swap(0, std::sync::atomic::Ordering::Relaxed),
+            first_attempt: (!first_attempt.is_empty()).then(|| PhaseLatency::from_histogram(&first_attempt)),
+            attempts: (!attempts.is_empty()).then(|| PhaseLatency::from_histogram(&attempts)),
+        }
+    }
+
+    /// Returns the queueing delays so far and resets them, or None if no op ran.
+    pub(crate) fn take_queueing_delay(&self) -> Option<QueueingDelay> {
+        let (schedule_lag, scheduler) = std::mem::replace(
+            &mut *self.queue_latencies.lock().unwrap(),
+            (Histogram::<u64>::new(3).unwrap(), Histogram::<u64>::new(3).unwrap()),
+        );
+        (!scheduler.is_empty()).then(|| QueueingDelay {
+            schedule_lag: (!schedule_lag.is_empty()).then(|| PhaseLatency::from_histogram(&schedule_lag)),
+            scheduler: PhaseLatency::from_histogram(&scheduler),
+        })
+    }
+
+    /// Writes every object of dual_write to `target` as well.
+    pub(crate) fn with_dual_write(mut self, target: Option<Arc<DualWriteTarget>>) -> Self {
+        self.dual_write = target;
+        self
+    }
+
+    /// Reads every key of read_compare from `target` as well.
+    pub(crate) fn with_read_compare(mut self, target: Option<Arc<ReadCompareTarget>>) -> Self {
+        self.read_compare = target;
+        self
+    }
+
+    /// Makes read_small read ranges and/or check content as `check` says.
+    pub(crate) fn with_read_check(mut self, check: Option<Arc<ReadCheck>>) -> Self {
+        self.read_check = check;
+        self
+    }
+
+    /// Abandons ops running longer than `timeout_ms`, counting them as timeouts.
+    pub(crate) fn with_op_timeout(mut self, timeout_ms: Option<u64>) -> Self {
+        self.op_timeout = timeout_ms.map(Duration::from_millis);
+        self
+    }
+
+    /// Starts ops `rate` times a second instead of as fast as slots free up.
+    pub(crate) fn with_target_rate(mut self, rate: Option<f64>) -> Self {
+        self.target_interval = rate.map(|rate| Duration::from_secs_f64(1.0 / rate));
+        self
+    }
+
+    /// Tracks ops and latency per worker.
+    pub(crate) fn with_worker_stats(mut self, enabled: bool) -> Self {
+        if enabled {
+            self.workers = Some(WorkerStats { workers: std::sync::Mutex::new(Vec::new()) });
+        }
+        self
+    }
+
+    /// Races reads of the first `keys` dataset keys against overwrites when
+    /// `mode` is read_overwrite.
+    pub(crate) fn with_overwrite_race(mut self, mode: &str, keys: usize) -> Self {
+        if mode == "read_overwrite" {
+            let keys = self.keys[..keys.min(self.keys.len())].to_vec();
+            self.overwrite = Some(Arc::new(OverwriteRace::new(keys, self.object_size)));
+        }
+        self
+    }
+
+    /// Deletes and recreates `pct_per_minute` percent of the keys per minute
+    /// during stat and read_small runs, starting from a key picked by `seed`.
+    /// The replacements go through `client`, unmetered so they stay out of the
+    /// run's verb stats, with the content the dataset was created with.
+    pub(crate) fn with_churn(mut self, mode: &str, pct_per_minute: Option<f64>, seed: u64, client: Arc<dyn BenchClient>, seeded: bool) -> Self {
+        if let Some(pct_per_minute) = pct_per_minute.filter(|_| is_churn_mode(mode)) {
+            self.churn = Some(Arc::new(Churn::new(pct_per_minute, seed, client, seeded)));
+        }
+        self
+    }
+
+    /// Records an op `worker` ran, with --worker-stats.
+    fn record_worker(&self, worker: usize, latency_us: Option<u64>) {
+        if let Some(workers) = &self.workers {
+            workers.record(worker, latency_us);
+        }
+    }
+
+    /// Returns the per-worker breakdown so far and resets it, with --worker-stats.
+    pub(crate) fn take_worker_report(&self) -> Option<WorkerReport> {
+        self.workers.as_ref().map(WorkerStats::take_report)
+    }
+
+    /// Returns the latencies from intended start so far and resets them, if --target-rate is set.
+    pub(crate) fn take_corrected_latency(&self) -> Option<CorrectedLatency> {
+        let interval = self.target_interval?;
+        let histogram = std::mem::replace(&mut *self.corrected_latencies.lock().unwrap(), Histogram::<u64>::new(3).unwrap());
+        Some(CorrectedLatency {
+            target_rate: 1.0 / interval.as_secs_f64(),
+            latency: PhaseLatency::from_histogram(&histogram),
+        })
+    }
+
+    /// Returns the number of timed-out ops so far and resets it, if timeouts are enabled.
+    pub(crate) fn take_timeouts(&self) -> Option<u64> {
+        self.op_timeout.map(|_| self.timeouts.swap(0, std::sync::atomic::Ordering::Relaxed))
+    }
+
+    /// Draws new keys and --key-popularity ranks from `seed`.
+    pub(crate) fn with_seed(mut self, seed: u64) -> Self {
+        self.rng = std::sync::Mutex::new(StdRng::seed_from_u64(seed));
+        self
+    }
+
+    /// A new key under the run's prefix for the `index`th write.
+    fn generate_key(&self, index: usize) -> String {
+        generate_key(&self.prefix, index, &mut self.rng.lock().unwrap())
+    }
+
+    /// Pre-generates `buffers` write payloads instead of one per write.
+    pub(crate) fn with_payload_pool(mut self, buffers: usize) -> Self {
+        self.payloads = PayloadSource::new(self.object_size, buffers);
+        self
+    }
+
+    /// Returns how long the last run lasted if it was interrupted, and resets it.
+    pub(crate) fn take_interrupted_after(&self) -> Option<Duration> {
+        self.interrupted_after.lock().unwrap().take()
+    }
+
+    /// Returns the wall time of the runs so far and resets it.
+    pub(crate) fn take_elapsed(&self) -> Option<Duration> {
+        self.elapsed.lock().unwrap().take()
+    }
+
+    /// Also records a per-interval series of QPS and latency.
+    pub(crate) fn with_intervals(mut self) -> Self {
+        self.intervals = Some(std::sync::Mutex::new(Vec::new()));
+        self
+    }
+
+    /// `with_intervals` when a sink or --timeseries-* needs the series.
+    pub(crate) fn with_intervals_for(self, args: &Args) -> Self {
+        let needed = args.influx_url.is_some() || args.influx_file.is_some() || args.html_report.is_some()
+            || args.timeseries_interval_seconds.is_some() || args.timeseries_csv.is_some();
+        if needed {
+            self.with_intervals().with_series_interval(args.timeseries_interval_seconds)
+        } else {
+            self
+        }
+    }
+
+    /// Makes the per-interval series `seconds` wide instead of SERIES_INTERVAL.
+    pub(crate) fn with_series_interval(mut self, seconds: Option<u64>) -> Self {
+        if let Some(seconds) = seconds {
+            self.series_interval = Duration::from_secs(seconds.max(1));
+        }
+        self
+    }
+
+    /// Runs `update` on the bucket of the interval `elapsed` falls in, if the series is on.
+    fn update_interval(&self, elapsed: Duration, update: impl FnOnce(&mut IntervalBucket)) {
+        let Some(intervals) = &self.intervals else {
+            return;
+        };
+        let index = (elapsed.as_millis() / self.series_interval.as_millis()) as usize;
+        let mut intervals = intervals.lock().unwrap();
+        if intervals.len() <= index {
+            // Intervals where nothing started or finished still had the ops in flight
+            let in_flight = self.in_flight.load(std::sync::atomic::Ordering::Relaxed);
+            // Two significant digits keep hour-long series small
+            intervals.resize_with(index + 1, || IntervalBucket {
+                histogram: Histogram::<u64>::new(2).unwrap(),
+                err_ops: 0,
+                throttled_ops: 0,
+                max_in_flight: in_flight,
+            });
+        }
+        update(&mut intervals[index]);
+    }
+
+    /// Records an op starting `elapsed` into the run.
+    fn record_interval_start(&self, elapsed: Duration) {
+        if self.intervals.is_none() {
+            return;
+        }
+        let in_flight = self.in_flight.fetch_add(1, std::sync::atomic::Ordering::Relaxed) + 1;
+        self.update_interval(elapsed, |bucket| bucket.max_in_flight = bucket.max_in_flight.max(in_flight));
+    }
+
+    /// Records an op completing `elapsed` into the run, with its latency if it succeeded.
+    fn record_interval(&self, elapsed: Duration, latency_us: Option<u64>) {
+        self.record_interval_outcome(elapsed, latency_us, false);
+    }
+
+    /// `record_interval` for an op that failed with a throttling error.
+    fn record_interval_throttled(&self, elapsed: Duration, reason: &'static str) {
+        *self.throttle_reasons.lock().unwrap().entry(reason).or_default() += 1;
+        self.record_interval_outcome(elapsed, None, true);
+    }
+
+    /// Returns the throttled ops by reason so far and resets them.
+    pub(crate) fn take_throttle_reasons(&self) -> BTreeMap<String, u64> {
+        std::mem::take(&mut *self.throttle_reasons.lock().unwrap())
+            .into_iter()
+            .map(|(reason, count)| (reason.to_string(), count))
+            .collect()
+    }
+
+    fn record_interval_outcome(&self, elapsed: Duration, latency_us: Option<u64>, throttled: bool) {
+        {
+            let second = elapsed.as_secs() as usize;
+            let mut seconds = self.second_counts.lock().unwrap();
+            if seconds.len() <= second {
+                seconds.resize(second + 1, SecondCounts::default());
+            }
+            match latency_us {
+                Some(_) => seconds[second].ok_ops += 1,
+                None => seconds[second].err_ops += 1,
+            }
+            seconds[second].throttled_ops += throttled as u64;
+        }
+        if self.intervals.is_none() {
+            return;
+        }
+        self.in_flight.fetch_sub(1, std::sync::atomic::Ordering::Relaxed);
+        self.update_interval(elapsed, |bucket| match latency_us {
+            Some(latency_us) => {
+                bucket.histogram.record(latency_us).ok();
+            }
+            None => {
+                bucket.err_ops += 1;
+                bucket.throttled_ops += throttled as u64;
+            }
+        });
+    }
+
+    /// Returns the per-second op counts recorded so far and resets them.
+    pub(crate) fn take_second_counts(&self) -> Vec<SecondCounts> {
+        std::mem::take(&mut *self.second_counts.lock().unwrap())
+    }
+
+    /// Returns the per-interval series recorded so far and resets it.
+    pub(crate) fn take_intervals(&self) -> Vec<IntervalStat> {
+        let Some(intervals) = &self.intervals else {
+            return Vec::new();
+        };
+        let interval_secs = self.series_interval.as_secs_f64();
+        let buckets = std::mem::take(&mut *intervals.lock().unwrap());
+        let window = ((PERCENTILE_WINDOW.as_millis() / self.series_interval.as_millis()) as usize).max(1);
+        let mut rolling = Histogram::<u64>::new(2).unwrap();
+        buckets
+            .iter()
+            .enumerate()
+            .map(|(index, bucket)| {
+                // Slide the window: add this interval, drop the one falling out of it
+                rolling.add(&bucket.histogram).ok();
+                if index >= window {
+                    rolling.subtract(&buckets[index - window].histogram).ok();
+                }
+                let ops = bucket.histogram.len() + bucket.err_ops;
+                IntervalStat {
+                    start_seconds: index as f64 * interval_secs,
+                    ok_ops: bucket.histogram.len(),
+                    err_ops: bucket.err_ops,
+                    qps: bucket.histogram.len() as f64 / interval_secs,
+                    error_pct: if ops == 0 { 0.0 } else { bucket.err_ops as f64 / ops as f64 * 100.0 },
+                    in_flight: bucket.max_in_flight,
+                    latency_us_p50: bucket.histogram.value_at_quantile(0.5),
+                    latency_us_p99: bucket.histogram.value_at_quantile(0.99),
+                    window_latency_us_p50: rolling.value_at_quantile(0.5),
+                    window_latency_us_p99: rolling.value_at_quantile(0.99),
+                    throttled_ops: bucket.throttled_ops,
+                    throttled: is_throttled(ops, bucket.throttled_ops),
+                }
+            })
+            .collect()
+    }
+
+    /// Records the latency of one successful phase of a multi-step op.
+    fn record_phase(&self, phase: &'static str, latency_us: u64) {
+        self.phases
+            .lock()
+            .unwrap()
+            .entry(phase)
+            .or_insert_with(|| Histogram::<u64>::new(3).unwrap())
+            .record(latency_us)
+            .ok();
+    }
+
+    /// Returns the phase latencies recorded so far and resets them.
+    pub(crate) fn take_phase_latencies(&self) -> BTreeMap<String, PhaseLatency> {
+        std::mem::take(&mut *self.phases.lock().unwrap())
+            .iter()
+            .map(|(phase, hist)| (phase.to_string(), PhaseLatency::from_histogram(hist)))
+            .collect()
+    }
+
+    /// Returns the per-HTTP-method metrics recorded so far and resets them.
+    pub(crate) fn take_verb_latencies(&self) -> BTreeMap<String, VerbLatency> {
+        std::mem::take(&mut *self.verbs.lock().unwrap())
+            .iter()
+            .map(|(verb, stats)| (verb.to_string(), stats.latency()))
+            .collect()
+    }
+
+    /// Records one op against `key` for the per-key breakdown.
+    fn record_key_latency(&self, key: &str, elapsed: Duration, ok: bool) {
+        let key = key.strip_prefix(self.prefix.as_str()).unwrap_or(key);
+        let mut keys = self.key_latencies.lock().unwrap();
+        let stats = keys.entry(key.to_string()).or_insert_with(VerbStats::new);
+        if ok {
+            stats.histogram.record(elapsed.as_micros() as u64).ok();
+        } else {
+            stats.err_ops += 1;
+        }
+    }
+
+    /// Returns when the run started, in unix milliseconds, and resets it.
+    pub(crate) fn take_started_at_unix_ms(&self) -> Option<u64> {
+        self.started_at
+            .lock()
+            .unwrap()
+            .take()
+            .and_then(|started| started.duration_since(std::time::UNIX_EPOCH).ok())
+            .map(|since_epoch| since_epoch.as_millis() as u64)
+    }
+
+    /// Returns the memory used since the run started, if any is measurable, and
+    /// resets the allocation baseline.
+    pub(crate) fn take_memory_report(&self, ops: u64) -> Option<MemoryReport> {
+        let per_op = |count: u64| count as f64 / ops.max(1) as f64;
+        let allocations = match (self.alloc_baseline.lock().unwrap().take(), allocation_counters()) {
+            (Some((allocations, bytes)), Some((allocations_now, bytes_now))) => {
+                Some((per_op(allocations_now - allocations), per_op(bytes_now - bytes)))
+            }
+            _ => None,
+        };
+        let peak_rss_bytes = peak_rss_bytes();
+        if peak_rss_bytes.is_none() && allocations.is_none() {
+            return None;
+        }
+        Some(MemoryReport {
+            peak_rss_bytes,
+            allocations_per_op: allocations.map(|(count, _)| count),
+            allocated_bytes_per_op: allocations.map(|(_, bytes)| bytes),
+        })
+    }
+
+    /// Returns the per-key latencies recorded so far and resets them.
+    pub(crate) fn take_key_latencies(&self) -> BTreeMap<String, VerbLatency> {
+        std::mem::take(&mut *self.key_latencies.lock().unwrap())
+            .into_iter()
+            .map(|(key, stats)| (key, stats.latency()))
+            .collect()
+    }
+
+    /// Returns the dataset as left by the run: the initial keys plus objects
+    /// written, minus objects deleted.
+    fn live_keys(&self) -> Vec<String> {
+        let written = self.written.lock().unwrap();
+        let deleted = self.deleted.lock().unwrap();
+        self.keys
+            .iter()
+            .chain(written.iter())
+            .filter(|key| !deleted.contains(*key))
+            .cloned()
+            .collect()
+    }
+
+    /// Returns the next dataset key in round-robin order.
+    fn next_key(&self) -> &str {
+        let index = self.next_key_index.fetch_add(1, std::sync::atomic::Ordering::Relaxed) % self.keys.len();
+        &self.keys[index]
+    }
+
+    /// Returns a key to stat or read: drawn from --key-popularity when set,
+    /// round-robin otherwise. Ranks beyond the dataset wrap around.
+    fn read_key(&self) -> &str {
+        let Some(popularity) = &self.key_popularity else {
+            return self.next_key();
+        };
+        let total = popularity.last().copied().unwrap_or_default();
+        let target = self.rng.lock().unwrap().gen_range(0.0..total);
+        let rank = popularity.partition_point(|cumulative| *cumulative <= target).min(popularity.len() - 1);
+        &self.keys[rank % self.keys.len()]
+    }
+
+    /// Draws stat/read keys from `popularity` (cumulative weights by rank).
+    pub(crate) fn with_key_popularity(mut self, popularity: Option<Arc<Vec<f64>>>) -> Self {
+        self.key_popularity = popularity;
+        self
+    }
+}
+
+/// Loads a `key,weight` CSV as cumulative weights in row order. A header row
+/// and `#` comments are skipped; keys may contain commas.
+pub(crate) fn load_key_popularity(path: &std::path::Path) -> Result<Vec<f64>> {
+    let text = std::fs::read_to_string(path)
+        .map_err(|e| anyhow::anyhow!("Failed to read {}: {}", path.display(), e))?;
+    let mut cumulative = Vec::new();
+    let mut total = 0.0;
+    for (index, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let weight = line.rsplit_once(',').map(|(_, weight)| weight.trim().parse::<f64>());
+        match weight {
+            Some(Ok(weight)) if weight.is_finite() && weight >= 0.0 => {
+                total += weight;
+                cumulative.push(total);
+            }
+            Some(Err(_)) if index == 0 => continue,
+            _ => anyhow::bail!("Invalid key popularity row {} in {}: {}", index + 1, path.display(), line),
+        }
+    }
+    if total <= 0.0 {
+        anyhow::bail!("{} has no keys with a positive weight", path.display());
+    }
+    Ok(cumulative)
+}
+
+/// Pause between attempts while waiting for a NotFound key to become visible.
+pub(crate) const NOT_FOUND_RETRY_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Retries `f` while it fails with NotFound, for up to `deadline` after the
+/// first miss. Ops that eventually succeed record the extra wait in the
+/// "visibility_wait" phase; the op latency itself includes the wait.
+pub(crate) async fn retry_not_found<T, F, Fut>(state: &BenchmarkState, deadline: Duration, mut f: F) -> opendal::Result<T>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = opendal::Result<T>>,
+{
+    let mut first_miss: Option<Instant> = None;
+    loop {
+        match f().await {
+            Ok(value) => {
+                if let Some(first_miss) = first_miss {
+                    state.record_phase("visibility_wait", first_miss.elapsed().as_micros() as u64);
+                }
+                return Ok(value);
+            }
+            Err(e) if e.kind() == opendal::ErrorKind::NotFound => {
+                let first_miss = *first_miss.get_or_insert_with(Instant::now);
+                if first_miss.elapsed() >= deadline {
+                    return Err(e);
+                }
+                tokio::time::sleep(NOT_FOUND_RETRY_INTERVAL).await;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+pub(crate) async fn run_stat_benchmark(state: Arc<BenchmarkState>, duration: Duration, concurrency: usize, not_found_retry: Duration) -> (u64, u64, Histogram<u64>) {
+    run_with_churn(&state, duration, run_closed_loop(state.clone(), duration, concurrency, |state| async move {
+        let key = state.read_key();
+        let start = Instant::now();
+        let result = retry_not_found(&state, not_found_retry, || state.client.stat(key)).await;
+        if let Some(churn) = &state.churn {
+            churn.record_read(key, start.elapsed(), &result);
+        }
+        result
+    })).await
+}
+
+/// Stats the first `keys` dataset objects round-robin as fast as possible, to
+/// probe metadata-path caching and the per-object QPS ceiling. Latency is also
+/// recorded per key.
+pub(crate) async fn run_stat_storm_benchmark(state: Arc<BenchmarkState>, duration: Duration, concurrency: usize, keys: usize) -> (u64, u64, Histogram<u64>) {
+    let keys = keys.min(state.keys.len());
+    let counter = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+    
+    run_closed_loop(state, duration, concurrency, |state| {
+        let index = counter.fetch_add(1, std::sync::atomic::Ordering::Relaxed) % keys;
+        async move {
+            let key = &state.keys[index];
+            let start = Instant::now();
+            let result = state.client.stat(key).await;
+            state.record_key_latency(key, start.elapsed(), result.is_ok());
+            result
+        }
+    }).await
+}
+
+pub(crate) async fn run_read_benchmark(state: Arc<BenchmarkState>, duration: Duration, concurrency: usize, not_found_retry: Duration) -> (u64, u64, Histogram<u64>) {
+    run_with_churn(&state, duration, run_closed_loop(state.clone(), duration, concurrency, |state| async move {
+        let key = state.read_key();
+        let start = Instant::now();
+        let result = match &state.read_check {
+            Some(check) => retry_not_found(&state, not_found_retry, || check.read(state.client.as_ref(), key, state.object_size)).await,
+            None => retry_not_found(&state, not_found_retry, || state.client.read(key)).await.map(|_| ()),
+        };
+        if let Some(churn) = &state.churn {
+            churn.record_read(key, start.elapsed(), &result);
+        }
+        result
+    })).await
+}
+
+/// FNV-1a: a hash that is stable across builds and platforms, unlike DefaultHasher.
+pub(crate) fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| (hash ^ *byte as u64).wrapping_mul(0x100_0000_01b3))
+}
+
+/// Content of dataset object `key`: seeded, or zeros for datasets created
+/// before seeded content.
+pub(crate) fn dataset_content(seeded: bool, key: &str, size: usize) -> Vec<u8> {
+    if seeded { seeded_content(key, 0, size) } else { vec![0u8; size] }
+}
+
+/// Content of dataset object `key` from byte `offset` on: little-endian
+/// splitmix64 words of the key's FNV-1a hash and the word index, so every
+/// range of every object differs and can be checked without the original.
+pub(crate) fn seeded_content(key: &str, offset: u64, length: usize) -> Vec<u8> {
+    let splitmix64 = |x: u64| {
+        let mut z = x.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    };
+    let seed = fnv1a(key.as_bytes());
+    let skip = (offset % 8) as usize;
+    let mut content = Vec::with_capacity(length + 16);
+    let mut word = offset / 8;
+    while content.len() < skip + length {
+        content.extend_from_slice(&splitmix64(seed ^ word).to_le_bytes());
+        word += 1;
+    }
+    content.drain(..skip);
+    content.truncate(length);
+    content
+}
+
+/// Most mismatches listed in a --verify-reads report.
+pub(crate) const READ_VERIFY_MAX_MISMATCHES: usize = 100;
+
+/// How read_small reads: a random range of each object (--range-read-bytes),
+/// and whether the content is checked against the seeded dataset content
+/// (--verify-reads).
+pub(crate) struct ReadCheck {
+    range_bytes: Option<usize>,
+    verify: bool,
+    stats: std::sync::Mutex<ReadVerifyStats>,
+}
+
+#[derive(Default)]
+pub(crate) struct ReadVerifyStats {
+    verified: u64,
+    mismatched: u64,
+    mismatches: Vec<RangeMismatch>,
+}
+
+impl ReadCheck {
+    pub(crate) fn for_args(args: &Args) -> Result<Option<Arc<Self>>> {
+        if args.range_read_bytes.is_none() && !args.verify_reads {
+            return Ok(None);
+        }
+        if args.range_read_bytes == Some(0) {
+            return Err(RunFailure::new(FailureCategory::Config, "--range-read-bytes must be at least 1").into());
+        }
+        if args.backend.client != "opendal" {
+            return Err(RunFailure::new(FailureCategory::Config, "--range-read-bytes and --verify-reads need --client opendal").into());
+        }
+        Ok(Some(Arc::new(ReadCheck {
+            range_bytes: args.range_read_bytes,
+            verify: args.verify_reads,
+            stats: std::sync::Mutex::new(ReadVerifyStats::default()),
+        })))
+    }
+
+    /// Reads `key` (a random range of it with --range-read-bytes) and, with
+    /// --verify-reads, fails the op when the content isn't the expected slice.
+    async fn read(&self, client: &dyn BenchClient, key: &str, object_size: usize) -> opendal::Result<()> {
+        let length = self.range_bytes.map_or(object_size, |bytes| bytes.min(object_size));
+        let offset = if length < object_size { rand::thread_rng().gen_range(0..=object_size - length) as u64 } else { 0 };
+        if !self.verify {
+            return match self.range_bytes {
+                Some(_) => client.read_range(key, offset, length as u64).await.map(|_| ()),
+                None => client.read(key).await.map(|_| ()),
+            };
+        }
+        
+        let content = client.read_range(key, offset, length as u64).await?;
+        let expected = seeded_content(key, offset, length);
+        if content == expected {
+            self.stats.lock().unwrap().verified += 1;
+            return Ok(());
+        }
+        let (reason, first_bad_offset) = if content.len() == object_size && length < object_size && content == seeded_content(key, 0, object_size) {
+            ("range_ignored", None)
+        } else if content.len() != length {
+            ("length", None)
+        } else {
+            let position = content.iter().zip(&expected).position(|(got, want)| got != want).unwrap_or_default();
+            ("content", Some(offset + position as u64))
+        };
+        let mut stats = self.stats.lock().unwrap();
+        stats.verified += 1;
+        stats.mismatched += 1;
+        if stats.mismatches.len() < READ_VERIFY_MAX_MISMATCHES {
+            stats.mismatches.push(RangeMismatch {
+                key: key.to_string(),
+                offset,
+                length: length as u64,
+                returned_bytes: content.len() as u64,
+                reason: reason.to_string(),
+                first_bad_offset,
+            });
+        }
+        Err(opendal::Error::new(opendal::ErrorKind::Unexpected, format!("{}: bytes {}..{} failed verification ({})", key, offset, offset + length as u64, reason)))
+    }
+
+    /// Returns the verification report so far and resets it; None without --verify-reads.
+    pub(crate) fn take_report(&self) -> Option<ReadVerifyReport> {
+        if !self.verify {
+            return None;
+        }
+        let stats = std::mem::take(&mut *self.stats.lock().unwrap());
+        Some(ReadVerifyReport {
+            range_bytes: self.range_bytes,
+            verified: stats.verified,
+            mismatched: stats.mismatched,
+            mismatches: stats.mismatches,
+        })
+    }
+}
+
+pub(crate) async fn run_write_benchmark(state: Arc<BenchmarkState>, duration: Duration, concurrency: usize) -> (u64, u64, Histogram<u64>) {
+    let key_counter = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+    
+    run_closed_loop(state, duration, concurrency, |state| {
+        let counter = key_counter.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        let data = state.payloads.next();
+        async move {
+            let key = state.generate_key(counter);
+            state.client.write(&key, data).await?;
+            state.written.lock().unwrap().push(key);
+            Ok(())
+        }
+    }).await
+}
+
+/// The second backend of dual_write, with what the run saw on each side.
+pub(crate) struct DualWriteTarget {
+    mirror: Arc<dyn BenchClient>,
+    /// Plain operators for reading objects back from the primary and the
+    /// mirror, with --dual-write-verify
+    verify: Option<(Operator, Operator)>,
+    stats: std::sync::Mutex<DualWriteStats>,
+    /// Keys written to the mirror, for cleanup; those written to the primary
+    /// join the run's dataset like any other write
+    mirror_written: std::sync::Mutex<Vec<String>>,
+}
+
+pub(crate) struct DualWriteStats {
+    primary: VerbStats,
+    mirror: VerbStats,
+    diverged: u64,
+    verified: u64,
+    mismatches: u64,
+}
+
+impl DualWriteStats {
+    fn new() -> Self {
+        DualWriteStats { primary: VerbStats::new(), mirror: VerbStats::new(), diverged: 0, verified: 0, mismatches: 0 }
+    }
+}
+
+/// The --mirror-* backend: the primary's settings with the --mirror-* overrides.
+pub(crate) fn mirror_backend(args: &Args) -> Result<BackendArgs> {
+    let Some(endpoint) = &args.mirror_endpoint else {
+        return Err(RunFailure::new(FailureCategory::Config, format!("{} needs --mirror-endpoint", args.mode)).into());
+    };
+    let mut mirror = args.backend.clone();
+    mirror.endpoint = endpoint.clone();
+    if let Some(bucket) = &args.mirror_bucket {
+        mirror.bucket = bucket.clone();
+    }
+    if let Some(region) = &args.mirror_region {
+        mirror.region = region.clone();
+    }
+    if let Some(service) = &args.mirror_service {
+        mirror.service = service.clone();
+    }
+    if let Some(access_key) = &args.mirror_access_key {
+        mirror.access_key = access_key.clone();
+    }
+    if let Some(secret_key) = &args.mirror_secret_key {
+        mirror.secret_key = secret_key.clone();
+    }
+    mirror.validate()?;
+    Ok(mirror)
+}
+
+impl DualWriteTarget {
+    /// Connects to the --mirror-* backend when `args.mode` is dual_write.
+    pub(crate) fn for_args(args: &Args) -> Result<Option<Arc<Self>>> {
+        if args.mode != "dual_write" {
+            return Ok(None);
+        }
+        let mirror = mirror_backend(args)?;
+        let verify = if args.dual_write_verify {
+            Some((create_operator(&args.backend)?, create_operator(&mirror)?))
+        } else {
+            None
+        };
+        Ok(Some(Arc::new(DualWriteTarget {
+            mirror: create_client(&mirror)?,
+            verify,
+            stats: std::sync::Mutex::new(DualWriteStats::new()),
+            mirror_written: std::sync::Mutex::new(Vec::new()),
+        })))
+    }
+
+    fn record(&self, primary: (Duration, bool), mirror: (Duration, bool)) {
+        let mut guard = self.stats.lock().unwrap();
+        let stats = &mut *guard;
+        for ((elapsed, ok), side) in [(primary, &mut stats.primary), (mirror, &mut stats.mirror)] {
+            if ok {
+                side.histogram.record(elapsed.as_micros() as u64).ok();
+            } else {
+                side.err_ops += 1;
+            }
+        }
+        if primary.1 != mirror.1 {
+            stats.diverged += 1;
+        }
+    }
+
+    /// Reads `key` back from both backends and checks both hold `expected`.
+    async fn verify(&self, key: &str, expected: &[u8]) {
+        let Some((primary, mirror)) = &self.verify else {
+            return;
+        };
+        let (primary, mirror) = tokio::join!(primary.read(key), mirror.read(key));
+        let matches = [primary, mirror]
+            .into_iter()
+            .all(|read| read.is_ok_and(|buffer| buffer.to_vec() == expected));
+        let mut stats = self.stats.lock().unwrap();
+        stats.verified += 1;
+        if !matches {
+            stats.mismatches += 1;
+        }
+    }
+
+    /// Deletes the objects the run wrote to the mirror.
+    pub(crate) async fn cleanup_mirror(&self, prefix: &str, object_size_bytes: usize, options: &CleanupOptions) -> Result<()> {
+        let written = std::mem::take(&mut *self.mirror_written.lock().unwrap());
+        if written.is_empty() {
+            return Ok(());
+        }
+        cleanup_objects(self.mirror.as_ref(), prefix, object_size_bytes, &written, options).await
+    }
+
+    /// Returns the report so far and resets it.
+    pub(crate) fn take_report(&self) -> DualWriteReport {
+        let mut stats = self.stats.lock().unwrap();
+        let report = DualWriteReport {
+            primary: stats.primary.latency(),
+            mirror: stats.mirror.latency(),
+            diverged: stats.diverged,
+            verified: self.verify.as_ref().map(|_| stats.verified),
+            readback_mismatches: self.verify.as_ref().map(|_| stats.mismatches),
+        };
+        *stats = DualWriteStats::new();
+        report
+    }
+}
+
+/// Writes each object to the primary backend and the --mirror-* backend at the
+/// same time. The op completes when both writes have, and fails if either did;
+/// each side's latency is also recorded, as are writes that landed on only one.
+pub(crate) async fn run_dual_write_benchmark(state: Arc<BenchmarkState>, duration: Duration, concurrency: usize, target: Arc<DualWriteTarget>) -> (u64, u64, Histogram<u64>) {
+    let key_counter = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+    
+    run_closed_loop(state, duration, concurrency, |state| {
+        let counter = key_counter.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        let data = state.payloads.next();
+        let target = target.clone();
+        async move {
+            let key = state.generate_key(counter);
+            let expected = target.verify.as_ref().map(|_| data.clone());
+            let primary = async {
+                let start = Instant::now();
+                let result = state.client.write(&key, data.clone()).await;
+                (result, start.elapsed())
+            };
+            let mirror = async {
+                let start = Instant::now();
+                let result = target.mirror.write(&key, data.clone()).await;
+                (result, start.elapsed())
+            };
+            let ((primary, primary_elapsed), (mirror, mirror_elapsed)) = tokio::join!(primary, mirror);
+            target.record((primary_elapsed, primary.is_ok()), (mirror_elapsed, mirror.is_ok()));
+            if primary.is_ok() {
+                state.written.lock().unwrap().push(key.clone());
+            }
+            if mirror.is_ok() {
+                target.mirror_written.lock().unwrap().push(key.clone());
+            }
+            primary.and(mirror)?;
+            if let Some(expected) = expected {
+                target.verify(&key, &expected).await;
+            }
+            Ok(())
+        }
+    }).await
+}
+
+/// Most divergent keys listed in a read_compare report.
+pub(crate) const READ_COMPARE_MAX_DIVERGENCES: usize = 100;
+
+/// The two backends of read_compare, with what the run saw on each side.
+/// Each side has its own client, built from its backend's settings, so
+/// neither shares the other's rate limiter or stat cache.
+pub(crate) struct ReadCompareTarget {
+    primary: Arc<dyn BenchClient>,
+    mirror: Arc<dyn BenchClient>,
+    stats: std::sync::Mutex<ReadCompareStats>,
+}
+
+pub(crate) struct ReadCompareStats {
+    primary: VerbStats,
+    mirror: VerbStats,
+    compared: u64,
+    matched: u64,
+    divergences: BTreeMap<String, Divergence>,
+}
+
+impl ReadCompareStats {
+    fn new() -> Self {
+        ReadCompareStats { primary: VerbStats::new(), mirror: VerbStats::new(), compared: 0, matched: 0, divergences: BTreeMap::new() }
+    }
+}
+
+impl ReadCompareTarget {
+    /// Connects to both backends when `args.mode` is read_compare.
+    pub(crate) fn for_args(args: &Args) -> Result<Option<Arc<Self>>> {
+        if args.mode != "read_compare" {
+            return Ok(None);
+        }
+        Ok(Some(Arc::new(ReadCompareTarget {
+            primary: create_client(&args.backend)?,
+            mirror: create_client(&mirror_backend(args)?)?,
+            stats: std::sync::Mutex::new(ReadCompareStats::new()),
+        })))
+    }
+
+    /// Reads `key` from both backends at the same time and compares the
+    /// contents. A key missing on either side is a divergence, not a failure;
+    /// other read errors fail the op.
+    async fn compare(&self, key: &str) -> opendal::Result<()> {
+        let timed = |client: &Arc<dyn BenchClient>| {
+            let client = client.clone();
+            let key = key.to_string();
+            async move {
+                let start = Instant::now();
+                let result = client.read_content(&key).await;
+                (result, start.elapsed())
+            }
+        };
+        let ((primary, primary_elapsed), (mirror, mirror_elapsed)) = tokio::join!(timed(&self.primary), timed(&self.mirror));
+        let found = |result: &opendal::Result<Vec<u8>>| match result {
+            Ok(content) => Ok(Some(content.len() as u64)),
+            Err(e) if e.kind() == opendal::ErrorKind::NotFound => Ok(None),
+            Err(_) => Err(()),
+        };
+        
+        let mut guard = self.stats.lock().unwrap();
+        let stats = &mut *guard;
+        for (result, elapsed, side) in [(&primary, primary_elapsed, &mut stats.primary), (&mirror, mirror_elapsed, &mut stats.mirror)] {
+            match found(result) {
+                Ok(Some(_)) => {
+                    side.histogram.record(elapsed.as_micros() as u64).ok();
+                }
+                Ok(None) => {}
+                Err(()) => side.err_ops += 1,
+            }
+        }
+        let (Ok(primary_bytes), Ok(mirror_bytes)) = (found(&primary), found(&mirror)) else {
+            drop(guard);
+            primary?;
+            mirror?;
+            return Ok(());
+        };
+        stats.compared += 1;
+        let reason = match (&primary, &mirror) {
+            (Ok(a), Ok(b)) if a == b => {
+                stats.matched += 1;
+                return Ok(());
+            }
+            (Ok(a), Ok(b)) if a.len() != b.len() => "size",
+            (Ok(_), Ok(_)) => "content",
+            (Ok(_), Err(_)) => "missing_on_mirror",
+            (Err(_), Ok(_)) => "missing_on_primary",
+            (Err(_), Err(_)) => "missing_on_both",
+        };
+        stats.divergences.entry(key.to_string()).or_insert_with(|| Divergence {
+            key: key.to_string(),
+            reason: reason.to_string(),
+            primary_bytes,
+            mirror_bytes,
+        });
+        Ok(())
+    }
+
+    /// Returns the report so far and resets it.
+    pub(crate) fn take_report(&self) -> ReadCompareReport {
+        let stats = std::mem::replace(&mut *self.stats.lock().unwrap(), ReadCompareStats::new());
+        ReadCompareReport {
+            primary: stats.primary.latency(),
+            mirror: stats.mirror.latency(),
+            compared: stats.compared,
+            matched: stats.matched,
+            divergent_keys: stats.divergences.len() as u64,
+            divergences: stats.divergences.into_values().take(READ_COMPARE_MAX_DIVERGENCES).collect(),
+        }
+    }
+}
+
+/// Reads dataset keys from the primary backend and the --mirror-* backend at
+/// the same time and compares what came back. The op completes when both reads
+/// have; each side's latency is also recorded.
+pub(crate) async fn run_read_compare_benchmark(state: Arc<BenchmarkState>, duration: Duration, concurrency: usize, target: Arc<ReadCompareTarget>) -> (u64, u64, Histogram<u64>) {
+    run_closed_loop(state, duration, concurrency, |state| {
+        let target = target.clone();
+        async move { target.compare(state.read_key()).await }
+    }).await
+}
+
+/// Start of every object read_overwrite writes, ahead of the version and key index.
+pub(crate) const OVERWRITE_MAGIC: &[u8; 8] = b"QPSOVW1\0";
+
+/// Magic, version and key index, each 8 bytes; objects must be at least this big.
+pub(crate) const OVERWRITE_HEADER_LEN: usize = 24;
+
+/// The keys of read_overwrite and the versions written to each. Version 0 is
+/// the dataset's own content; each overwrite writes the next version, with
+/// the version and key index in the first bytes so a read can tell which
+/// write it saw.
+pub(crate) struct OverwriteRace {
+    keys: Vec<String>,
+    object_size: usize,
+    /// Per key: the newest version started and the newest acknowledged
+    versions: Vec<std::sync::Mutex<(u64, u64)>>,
+    next_read: std::sync::atomic::AtomicUsize,
+    stats: std::sync::Mutex<OverwriteStats>,
+}
+
+pub(crate) struct OverwriteStats {
+    overwrites: VerbStats,
+    current: u64,
+    new: u64,
+    stale: u64,
+    torn: u64,
+    read_errors: BTreeMap<String, u64>,
+    racing_reads: VerbStats,
+    quiet_reads: VerbStats,
+}
+
+impl OverwriteStats {
+    fn new() -> Self {
+        OverwriteStats {
+            overwrites: VerbStats::new(),
+            current: 0,
+            new: 0,
+            stale: 0,
+            torn: 0,
+            read_errors: BTreeMap::new(),
+            racing_reads: VerbStats::new(),
+            quiet_reads: VerbStats::new(),
+        }
+    }
+}
+
+impl OverwriteRace {
+    fn new(keys: Vec<String>, object_size: usize) -> Self {
+        OverwriteRace {
+            versions: keys.iter().map(|_| std::sync::Mutex::new((0, 0))).collect(),
+            keys,
+            object_size,
+            next_read: std::sync::atomic::AtomicUsize::new(0),
+            stats: std::sync::Mutex::new(OverwriteStats::new()),
+        }
+    }
+
+    /// Writes the next version of key `index` and returns once it's acknowledged.
+    async fn overwrite(&self, client: &dyn BenchClient, index: usize) {
+        let version = {
+            let mut versions = self.versions[index].lock().unwrap();
+            versions.0 += 1;
+            versions.0
+        };
+        let mut content = Vec::with_capacity(self.object_size.max(OVERWRITE_HEADER_LEN));
+        content.extend_from_slice(OVERWRITE_MAGIC);
+        content.extend_from_slice(&version.to_le_bytes());
+        content.extend_from_slice(&(index as u64).to_le_bytes());
+        content.resize(self.object_size.max(OVERWRITE_HEADER_LEN), (version % 251) as u8);
+        let start = Instant::now();
+        let result = client.write(&self.keys[index], content).await;
+        let elapsed = start.elapsed();
+        if result.is_ok() {
+            let mut versions = self.versions[index].lock().unwrap();
+            versions.1 = versions.1.max(version);
+        }
+        let mut stats = self.stats.lock().unwrap();
+        match result {
+            Ok(()) => {
+                stats.overwrites.histogram.record(elapsed.as_micros() as u64).ok();
+            }
+            Err(_) => stats.overwrites.err_ops += 1,
+        }
+    }
+
+    /// Reads the next key in turn and classifies the version it returned
+    /// against the acknowledged versions before and after the read.
+    async fn read(&self, client: &dyn BenchClient) -> opendal::Result<()> {
+        let index = self.next_read.fetch_add(1, std::sync::atomic::Ordering::Relaxed) % self.keys.len();
+        let (_, acked_before) = *self.versions[index].lock().unwrap();
+        let start = Instant::now();
+        let result = client.read_range(&self.keys[index], 0, self.object_size as u64).await;
+        let elapsed = start.elapsed();
+        let (started_after, acked_after) = *self.versions[index].lock().unwrap();
+        
+        let mut guard = self.stats.lock().unwrap();
+        let stats = &mut *guard;
+        let content = match result {
+            Ok(content) => content,
+            Err(e) => {
+                *stats.read_errors.entry(format!("{:?}", e.kind())).or_default() += 1;
+                return Err(e);
+            }
+        };
+        // Some write to the key was unacknowledged at some point during the read
+        let racing = started_after > acked_before;
+        let latencies = if racing { &mut stats.racing_reads } else { &mut stats.quiet_reads };
+        latencies.histogram.record(elapsed.as_micros() as u64).ok();
+        // A version counts only if the whole body is what its writer sent
+        let size = self.object_size.max(OVERWRITE_HEADER_LEN);
+        let version = if content.starts_with(OVERWRITE_MAGIC) && content.len() == size {
+            let word = |at: usize| u64::from_le_bytes(content[at..at + 8].try_into().unwrap());
+            let version = word(8);
+            let filled = content[OVERWRITE_HEADER_LEN..].iter().all(|byte| *byte == (version % 251) as u8);
+            (word(16) == index as u64 && filled).then_some(version)
+        } else {
+            // Not one of ours: the dataset's original (seeded or zeroed) content
+            let original = content.len() == self.object_size
+                && (content == seeded_content(&self.keys[index], 0, self.object_size) || content.iter().all(|byte| *byte == 0));
+            original.then_some(0)
+        };
+        match version {
+            Some(version) if version > started_after => stats.torn += 1,
+            Some(version) if version < acked_before => stats.stale += 1,
+            Some(version) if version <= acked_after => stats.current += 1,
+            Some(_) => stats.new += 1,
+            None => stats.torn += 1,
+        }
+        Ok(())
+    }
+
+    /// Returns the report so far and resets it.
+    pub(crate) fn take_report(&self) -> OverwriteReport {
+        let stats = std::mem::replace(&mut *self.stats.lock().unwrap(), OverwriteStats::new());
+        OverwriteReport {
+            keys: self.keys.len() as u64,
+            overwrites: stats.overwrites.latency(),
+            current: stats.current,
+            new: stats.new,
+            stale: stats.stale,
+            torn: stats.torn,
+            read_errors: stats.read_errors,
+            racing_reads: stats.racing_reads.latency(),
+            quiet_reads: stats.quiet_reads.latency(),
+        }
+    }
+}
+
+/// Reads a small set of dataset keys while `overwriters` background tasks
+/// overwrite them as fast as they can, and classifies what each read returned.
+/// Each key has one writer, so its versions are acknowledged in order. Only
+/// the reads are the benchmarked ops; the overwrites are load.
+pub(crate) async fn run_read_overwrite_benchmark(state: Arc<BenchmarkState>, duration: Duration, concurrency: usize, race: Arc<OverwriteRace>, overwriters: usize) -> (u64, u64, Histogram<u64>) {
+    let deadline = Instant::now() + duration;
+    // More writers than keys would leave some without a key of their own
+    let overwriters = overwriters.min(race.keys.len()).max(1);
+    let writers: Vec<_> = (0..overwriters)
+        .map(|writer| {
+            let state = state.clone();
+            let race = race.clone();
+            spawn_in_run(async move {
+                // Writer `w` owns keys w, w + overwriters, w + 2 * overwriters, ...
+                let owned: Vec<usize> = (writer..race.keys.len()).step_by(overwriters).collect();
+                for index in owned.iter().cycle() {
+                    if Instant::now() >= deadline || interrupted() {
+                        break;
+                    }
+                    race.overwrite(state.client.as_ref(), *index).await;
+                }
+            })
+        })
+        .collect();
+    let outcome = run_closed_loop(state, duration, concurrency, |state| {
+        let race = race.clone();
+        async move { race.read(state.client.as_ref()).await }
+    }).await;
+    for writer in writers {
+        let _ = writer.await;
+    }
+    outcome
+}
+
+/// Churn replacements in flight at once; ticks beyond that are skipped (and
+/// counted) rather than queued, so a slow backend doesn't build a backlog.
+pub(crate) const CHURN_MAX_IN_FLIGHT: usize = 64;
+
+/// What --churn-pct-per-minute did and how the reads fared.
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct ChurnReport {
+    pub(crate) pct_per_minute: f64,
+    /// Delete-then-recreate of one key, end to end
+    pub(crate) replacements: VerbLatency,
+    /// Replacements skipped because CHURN_MAX_IN_FLIGHT were still running
+    #[serde(default)]
+    pub(crate) skipped: u64,
+    /// Distinct keys replaced at least once
+    pub(crate) churned_keys: u64,
+    /// Reads that still failed with NotFound after --not-found-retry-ms
+    pub(crate) not_found_reads: u64,
+    pub(crate) not_found_pct: f64,
+    /// Reads of keys replaced earlier in the run, and of keys not (yet) replaced
+    pub(crate) churned_reads: VerbLatency,
+    pub(crate) other_reads: VerbLatency,
+}
+
+/// Background deletes and recreates of dataset keys during stat/read_small.
+pub(crate) struct Churn {
+    pct_per_minute: f64,
+    /// Picks the first key replaced, so a seeded run churns the same keys
+    seed: u64,
+    /// Unmetered client the replacements go through
+    client: Arc<dyn BenchClient>,
+    /// Whether the dataset has seeded content rather than zeros
+    seeded: bool,
+    stats: std::sync::Mutex<ChurnStats>,
+}
+
+pub(crate) struct ChurnStats {
+    replacements: VerbStats,
+    skipped: u64,
+    churned: std::collections::HashSet<String>,
+    not_found_reads: u64,
+    churned_reads: VerbStats,
+    other_reads: VerbStats,
+}
+
+impl ChurnStats {
+    fn new() -> Self {
+        ChurnStats {
+            replacements: VerbStats::new(),
+            skipped: 0,
+            churned: std::collections::HashSet::new(),
+            not_found_reads: 0,
+            churned_reads: VerbStats::new(),
+            other_reads: VerbStats::new(),
+        }
+    }
+}
+
+impl Churn {
+    fn new(pct_per_minute: f64, seed: u64, client: Arc<dyn BenchClient>, seeded: bool) -> Self {
+        Churn { pct_per_minute, seed, client, seeded, stats: std::sync::Mutex::new(ChurnStats::new()) }
+    }
+
+    /// Deletes `key` and writes it again with the dataset's content for it.
+    async fn replace(&self, key: &str, object_size: usize) {
+        self.stats.lock().unwrap().churned.insert(key.to_string());
+        let start = Instant::now();
+        let result = match self.client.delete(key).await {
+            Ok(()) => self.client.write(key, dataset_content(self.seeded, key, object_size)).await,
+            Err(e) => Err(e),
+        };
+        let elapsed = start.elapsed();
+        let mut stats = self.stats.lock().unwrap();
+        match result {
+            Ok(()) => {
+                stats.replacements.histogram.record(elapsed.as_micros() as u64).ok();
+            }
+            Err(e) => {
+                stats.replacements.err_ops += 1;
+                tracing::debug!("Churn of {} failed: {}", key, e);
+            }
+        }
+    }
+
+    /// Records a stat/read of `key`, split by whether the key has been churned.
+    fn record_read<T>(&self, key: &str, latency: Duration, result: &opendal::Result<T>) {
+        let mut stats = self.stats.lock().unwrap();
+        if matches!(result, Err(e) if e.kind() == opendal::ErrorKind::NotFound) {
+            stats.not_found_reads += 1;
+        }
+        let reads = if stats.churned.contains(key) { &mut stats.churned_reads } else { &mut stats.other_reads };
+        match result {
+            Ok(_) => {
+                reads.histogram.record(latency.as_micros() as u64).ok();
+            }
+            Err(_) => reads.err_ops += 1,
+        }
+    }
+
+    pub(crate) fn take_report(&self) -> ChurnReport {
+        let stats = std::mem::replace(&mut *self.stats.lock().unwrap(), ChurnStats::new());
+        let reads = stats.churned_reads.histogram.len() + stats.churned_reads.err_ops + stats.other_reads.histogram.len() + stats.other_reads.err_ops;
+        ChurnReport {
+            pct_per_minute: self.pct_per_minute,
+            replacements: stats.replacements.latency(),
+            skipped: stats.skipped,
+            churned_keys: stats.churned.len() as u64,
+            not_found_reads: stats.not_found_reads,
+            not_found_pct: if reads == 0 { 0.0 } else { stats.not_found_reads as f64 / reads as f64 * 100.0 },
+            churned_reads: stats.churned_reads.latency(),
+            other_reads: stats.other_reads.latency(),
+        }
+    }
+}
+
+/// Whether --churn-pct-per-minute applies to `mode`.
+pub(crate) fn is_churn_mode(mode: &str) -> bool {
+    matches!(mode, "stat" | "read_small")
+}
+
+/// Drives `run` while, with --churn-pct-per-minute, a background task replaces
+/// dataset keys in turn (from a seeded start) at the configured rate until the
+/// run's deadline.
+pub(crate) async fn run_with_churn<T>(state: &Arc<BenchmarkState>, duration: Duration, run: impl Future<Output = T>) -> T {
+    let Some(churn) = state.churn.clone().filter(|_| !state.keys.is_empty()) else {
+        return run.await;
+    };
+    let deadline = Instant::now() + duration;
+    let state = state.clone();
+    let churner = spawn_in_run(async move {
+        let keys = state.keys.len();
+        let per_second = churn.pct_per_minute / 100.0 * keys as f64 / 60.0;
+        let mut ticker = tokio::time::interval(Duration::from_secs_f64(1.0 / per_second).max(Duration::from_millis(1)));
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        let mut next = StdRng::seed_from_u64(churn.seed).gen_range(0..keys);
+        let mut in_flight = futures::stream::FuturesUnordered::new();
+        loop {
+            tokio::select! {
+                _ = ticker.tick() => {
+                    if Instant::now() >= deadline || interrupted() {
+                        break;
+                    }
+                    if in_flight.len() >= CHURN_MAX_IN_FLIGHT {
+                        churn.stats.lock().unwrap().skipped += 1;
+                        continue;
+                    }
+                    let key = state.keys[next % keys].clone();
+                    next += 1;
+                    let (state, churn) = (state.clone(), churn.clone());
+                    in_flight.push(async move { churn.replace(&key, state.object_size).await });
+                }
+                Some(()) = in_flight.next(), if !in_flight.is_empty() => {}
+            }
+        }
+        // Leave every key in place for the next phase and cleanup
+        while in_flight.next().await.is_some() {}
+    });
+    let outcome = run.await;
+    let _ = churner.await;
+    outcome
+}
+
+/// Each logical operation writes `fanout` related objects (`fanout - 1` parts
+/// plus a manifest) in parallel and completes when the slowest one does, so the
+/// recorded latency is the time a job waiting on all of its files would see.
+pub(crate) async fn run_scatter_write_benchmark(state: Arc<BenchmarkState>, duration: Duration, concurrency: usize, fanout: usize) -> (u64, u64, Histogram<u64>) {
+    let key_counter = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+    
+    run_closed_loop(state, duration, concurrency, |state| {
+        let counter = key_counter.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        let data = state.payloads.next();
+        async move {
+            let base = state.generate_key(counter);
+            let writes = (0..fanout).map(|i| {
+                let key = if i + 1 == fanout {
+                    format!("{}/manifest", base)
+                } else {
+                    format!("{}/part-{:04}", base, i)
+                };
+                let state = state.clone();
+                let data = data.clone();
+                async move { state.client.write(&key, data).await }
+            });
+            futures::future::try_join_all(writes).await.map(|_| ())
+        }
+    }).await
+}
+
+/// Models a table-format commit (Iceberg/Delta): `parts` data files are written
+/// in parallel, then a manifest listing them is written only once all parts
+/// succeeded. The op latency is end-to-end; each phase is also recorded.
+pub(crate) async fn run_commit_benchmark(state: Arc<BenchmarkState>, duration: Duration, concurrency: usize, parts: usize) -> (u64, u64, Histogram<u64>) {
+    let key_counter = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+    
+    run_closed_loop(state, duration, concurrency, |state| {
+        let counter = key_counter.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        let data = state.payloads.next();
+        async move {
+            let base = state.generate_key(counter);
+            let part_keys: Vec<String> = (0..parts).map(|i| format!("{}/part-{:04}", base, i)).collect();
+            
+            let parts_start = Instant::now();
+            let writes = part_keys.iter().map(|key| state.client.write(key, data.clone()));
+            futures::future::try_join_all(writes).await?;
+            state.record_phase("parts", parts_start.elapsed().as_micros() as u64);
+            
+            let manifest_start = Instant::now();
+            let manifest = part_keys.join("\n");
+            state.client.write(&format!("{}/manifest", base), manifest.into_bytes()).await?;
+            state.record_phase("manifest", manifest_start.elapsed().as_micros() as u64);
+            
+            Ok::<_, opendal::Error>(())
+        }
+    }).await
+}
+
+pub(crate) async fn run_delete_benchmark(state: Arc<BenchmarkState>, duration: Duration, concurrency: usize) -> (u64, u64, Histogram<u64>) {
+    run_closed_loop(state, duration, concurrency, |state| async move {
+        let key = state.next_key();
+        state.client.delete(key).await?;
+        state.deleted.lock().unwrap().insert(key.to_string());
+        Ok(())
+    }).await
+}
+
+pub(crate) async fn run_list_benchmark(state: Arc<BenchmarkState>, duration: Duration, concurrency: usize, max_entries: usize) -> (u64, u64, Histogram<u64>) {
+    run_closed_loop(state, duration, concurrency, |state| async move {
+        let outcome = state.client.list(&state.prefix, max_entries).await?;
+        if outcome.truncated {
+            state.truncated_lists.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        }
+        Ok(())
+    }).await
+}
+
+/// Seconds the bytes in flight may take to cross the client's NIC before the
+/// run measures the link rather than the backend.
+pub(crate) const GUARDRAIL_MAX_TRANSFER_SECS: u64 = 10;
+
+/// Whole objects a single op of `mode` holds in memory and on the wire.
+pub(crate) fn objects_per_op(mode: &str, fanout: usize) -> usize {
+    match mode {
+        "scatter_write" | "commit" => fanout,
+        // Every op goes to both backends
+        "dual_write" | "read_compare" => 2,
+        "read_small" | "write_small" | "read_overwrite" | "read_write" => 1,
+        _ => 0,
+    }
+}
+
+/// Object bytes moved per second by `qps` ops of `mode`.
+pub(crate) fn throughput_bytes_per_second(args: &Args, mode: &str, qps: f64) -> f64 {
+    qps * (objects_per_op(mode, args.fanout) * args.object_size_bytes) as f64
+}
+
+/// Checks that object size × concurrency fits in half the available memory and
+/// crosses the NIC within [`GUARDRAIL_MAX_TRANSFER_SECS`]. Warns when it doesn't,
+/// and with --auto-cap-concurrency returns the args with the concurrency (sweep
+/// levels and phases included) lowered to fit.
+pub(crate) fn concurrency_guardrail(args: &Args) -> Option<Args> {
+    let modes: Vec<(&str, usize)> = if args.phases.is_empty() {
+        let levels = args.sweep_concurrency.iter().copied().chain([args.concurrency]);
+        vec![(args.mode.as_str(), levels.max().unwrap_or(args.concurrency))]
+    } else {
+        args.phases.iter().map(|phase| (phase.mode.as_str(), phase.concurrency.unwrap_or(args.concurrency))).collect()
+    };
+    let op_bytes = modes
+        .iter()
+        .map(|(mode, _)| (objects_per_op(mode, args.fanout) * args.object_size_bytes) as u64)
+        .max()
+        .unwrap_or(0);
+    let requested = modes.iter().map(|(_, concurrency)| *concurrency).max().unwrap_or(args.concurrency);
+    if op_bytes == 0 {
+        return None;
+    }
+    
+    let in_flight = op_bytes * requested as u64;
+    let gib = |bytes: u64| bytes as f64 / (1024.0 * 1024.0 * 1024.0);
+    let mut limits = Vec::new();
+    if let Some(available) = available_memory_bytes() {
+        let reason = format!("{:.1} GiB in flight exceeds half the available memory ({:.1} GiB)", gib(in_flight), gib(available));
+        limits.push((available / 2 / op_bytes, reason));
+    }
+    if let Some(nic) = nic_bytes_per_second(&args.backend.endpoint) {
+        let reason = format!(
+            "{:.1} GiB in flight takes {:.0}s to cross the {:.1} Gbit/s NIC, so latency would measure the client link",
+            gib(in_flight), in_flight as f64 / nic as f64, (nic * 8) as f64 / 1e9,
+        );
+        limits.push((nic * GUARDRAIL_MAX_TRANSFER_SECS / op_bytes, reason));
+    }
+    let (limit, reason) = limits.into_iter().min_by_key(|(limit, _)| *limit)?;
+    let limit = (limit as usize).max(1);
+    if requested <= limit {
+        return None;
+    }
+    if !args.auto_cap_concurrency {
+        tracing::warn!("⚠️  Concurrency {}: {} (at most {} fits; --auto-cap-concurrency lowers it)", requested, reason, limit);
+        return None;
+    }
+    tracing::warn!("⚠️  Capping concurrency {} to {}: {}", requested, limit, reason);
+    let mut capped = args.clone();
+    capped.concurrency = capped.concurrency.min(limit);
+    for level in &mut capped.sweep_concurrency {
+        *level = (*level).min(limit);
+    }
+    capped.sweep_concurrency.dedup();
+    for phase in &mut capped.phases {
+        phase.concurrency = phase.concurrency.map(|concurrency| concurrency.min(limit));
+    }
+    capped.concurrency_cap = Some(ConcurrencyCap { requested, capped_to: limit, reason });
+    Some(capped)
+}
+
+/// Every --mode, including the combined read_write.
+pub(crate) const MODES: &[&str] = &[
+    "stat", "stat_storm", "read_small", "write_small", "scatter_write", "commit", "delete", "list", "dual_write", "read_compare",
+    "read_overwrite", "read_write",
+];
+
+/// A backend operation a mode or cleanup depends on. Besides the basics, some
+/// S3-compatible endpoints lack range reads, listing by delimiter or
+/// multi-object deletes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum BackendOperation {
+    Stat,
+    Read,
+    RangeRead,
+    Write,
+    Delete,
+    BatchDelete,
+    ListDelimiter,
+}
+
+impl BackendOperation {
+    fn name(self) -> &'static str {
+        match self {
+            BackendOperation::Stat => "stat",
+            BackendOperation::Read => "read",
+            BackendOperation::RangeRead => "range_read",
+            BackendOperation::Write => "write",
+            BackendOperation::Delete => "delete",
+            BackendOperation::BatchDelete => "batch_delete",
+            BackendOperation::ListDelimiter => "list_delimiter",
+        }
+    }
+
+    /// Whether OpenDAL's capability flags for the service allow it.
+    pub(crate) fn supported_by(self, capability: &opendal::Capability) -> bool {
+        match self {
+            BackendOperation::Stat => capability.stat,
+            BackendOperation::Read | BackendOperation::RangeRead => capability.read,
+            BackendOperation::Write => capability.write,
+            BackendOperation::Delete => capability.delete,
+            BackendOperation::BatchDelete => capability.delete_max_size.is_some_and(|size| size > 1),
+            BackendOperation::ListDelimiter => capability.list,
+        }
+    }
+}
+
+/// The backend operations `mode` issues with these settings, including the
+/// writes that create its dataset.
+pub(crate) fn mode_operations(args: &Args, mode: &str) -> Vec<BackendOperation> {
+    use BackendOperation::*;
+    // --range-read-bytes and --verify-reads read ranges rather than whole objects
+    let reads = if args.range_read_bytes.is_some() || args.verify_reads { RangeRead } else { Read };
+    match mode {
+        "stat" | "stat_storm" => vec![Stat, Write],
+        "read_small" | "read_write" => vec![reads, Write],
+        "read_compare" => vec![Read, Write],
+        "read_overwrite" => vec![RangeRead, Write],
+        "write_small" | "scatter_write" | "commit" | "dual_write" => vec![Write],
+        "delete" => vec![Delete, Write],
+        "list" => vec![ListDelimiter, Write],
+        _ => Vec::new(),
+    }
+}
+
+/// The operations of `mode` among those the backend turned down.
+pub(crate) fn missing_operations(args: &Args, mode: &str, unsupported: &[BackendOperation]) -> Vec<&'static str> {
+    mode_operations(args, mode).into_iter().filter(|operation| unsupported.contains(operation)).map(BackendOperation::name).collect()
+}
+
+/// Whether a failed probe means the endpoint doesn't implement the operation,
+/// rather than failing for a reason the run itself will report.
+pub(crate) fn is_unsupported(error: &opendal::Error, response: Option<&ErrorResponse>) -> bool {
+    error.kind() == opendal::ErrorKind::Unsupported
+        || response.is_some_and(|response| {
+            matches!(response.status, 405 | 501) || matches!(response.code.as_deref(), Some("NotImplemented" | "MethodNotAllowed"))
+        })
+}
+
+/// Sends one probe request in its own op context, so the response it failed
+/// with can be told apart from the others'. True if the backend turned it
+/// down as unsupported.
+pub(crate) async fn probe_unsupported<T>(request: impl Future<Output = opendal::Result<T>>) -> bool {
+    OP_CONTEXT
+        .scope(OpContext::default(), async {
+            match request.await {
+                Ok(_) => false,
+                Err(e) => is_unsupported(&e, take_error_response().as_ref()),
+            }
+        })
+        .await
+}
+
+/// Tries each of `operations` once against a one-byte object under `prefix`
+/// and returns those the backend turns down. OpenDAL's flags describe the
+/// service rather than what a given S3-compatible endpoint implements, and the
+/// raw and aws-sdk clients have none, so only a request tells.
+pub(crate) async fn probe_operations(client: &dyn BenchClient, prefix: &str, operations: &[BackendOperation]) -> Vec<BackendOperation> {
+    let dir = format!("{}/capability-probe-{}/", prefix, Uuid::new_v4());
+    let key = format!("{}probe", dir);
+    let missing = [format!("{}missing-1", dir), format!("{}missing-2", dir)];
+    let mut unsupported = Vec::new();
+    // Written first so the reads find an object
+    if probe_unsupported(client.write(&key, vec![0])).await {
+        unsupported.push(BackendOperation::Write);
+    }
+    for operation in operations {
+        let rejected = match operation {
+            BackendOperation::Write => continue,
+            BackendOperation::Stat => probe_unsupported(client.stat(&key)).await,
+            BackendOperation::Read => probe_unsupported(client.read(&key)).await,
+            BackendOperation::RangeRead => probe_unsupported(client.read_range(&key, 0, 1)).await,
+            BackendOperation::ListDelimiter => probe_unsupported(client.list(&dir, 1)).await,
+            // S3 acknowledges deletes of missing keys
+            BackendOperation::BatchDelete => probe_unsupported(client.delete_batch(&missing)).await,
+            BackendOperation::Delete => probe_unsupported(client.delete(&missing[0])).await,
+        };
+        if rejected {
+            unsupported.push(*operation);
+        }
+    }
+    // Deleting a probe that was never written is harmless
+    let _ = client.delete(&key).await;
+    unsupported
+}
+
+/// Logs the backend's capabilities, including those no mode needs, so a new
+/// endpoint's gaps show up before the run rather than as errors.
+pub(crate) fn log_capabilities(capability: &opendal::Capability) {
+    let flags = [
+        ("stat", capability.stat),
+        ("read", capability.read),
+        ("write", capability.write),
+        ("delete", capability.delete),
+        ("list", capability.list),
+        ("list_recursive", capability.list_with_recursive),
+        ("copy", capability.copy),
+        ("rename", capability.rename),
+        ("append", capability.write_can_append),
+        ("presign", capability.presign),
+        ("batch_delete", capability.delete_max_size.is_some_and(|size| size > 1)),
+    ];
+    let supported: Vec<&str> = flags.iter().filter(|(_, supported)| *supported).map(|(name, _)| *name).collect();
+    let unsupported: Vec<&str> = flags.iter().filter(|(_, supported)| !*supported).map(|(name, _)| *name).collect();
+    tracing::info!("🔎 Backend supports: {}", supported.join(", "));
+    if !unsupported.is_empty() {
+        tracing::info!("   Not supported: {}", unsupported.join(", "));
+    }
+}
+
+/// Whether `mode` operates on existing objects rather than writing its own.
+pub(crate) fn mode_needs_dataset(mode: &str) -> bool {
+    matches!(mode, "stat" | "stat_storm" | "read_small" | "delete" | "list" | "read_compare" | "read_overwrite")
+}
+
+/// Runs `args.phases` back to back, starting from `dataset`. Each phase starts
+/// from the dataset left by the previous one, so a read phase sees the objects
+/// just written. Returns the per-phase results and the final dataset keys.
+pub(crate) async fn run_phases(args: &Args, client: Arc<dyn BenchClient>, dataset: DatasetManifest, key_popularity: Option<Arc<Vec<f64>>>, seed: u64, unsupported: &[BackendOperation]) -> Result<(PhasedResult, Vec<String>)> {
+    let DatasetManifest { prefix, mut keys, seeded, .. } = dataset;
+    let not_found_retry = Duration::from_millis(args.not_found_retry_ms);
+    let mut phase_results = Vec::with_capacity(args.phases.len());
+    let mut skipped_phases = Vec::new();
+    for (index, phase) in args.phases.iter().enumerate() {
+        if interrupted() {
+            break;
+        }
+        let missing = missing_operations(args, &phase.mode, unsupported);
+        if !missing.is_empty() {
+            tracing::warn!("⏭️  Skipping phase {} ({}): the backend doesn't support {}", index + 1, phase.mode, missing.join(", "));
+            skipped_phases.push(SkippedPhase {
+                phase: index + 1,
+                mode: phase.mode.clone(),
+                unsupported: missing.iter().map(|operation| operation.to_string()).collect(),
+            });
+            continue;
+        }
+        if mode_needs_dataset(&phase.mode) && keys.is_empty() {
+            anyhow::bail!("Phase {} ({}) needs objects, but no earlier phase left any", index + 1, phase.mode);
+        }
+        let phase_args = Args {
+            mode: phase.mode.clone(),
+            duration_seconds: phase.duration_seconds.unwrap_or(args.duration_seconds),
+            concurrency: phase.concurrency.unwrap_or(args.concurrency),
+            phases: Vec::new(),
+            ..args.clone()
+        };
+        
+        tracing::info!(
+            phase = index + 1,
+            phases = args.phases.len(),
+            mode = %phase_args.mode,
+            duration_s = phase_args.duration_seconds,
+            concurrency = phase_args.concurrency,
+            objects = keys.len(),
+            "📊 Starting phase"
+        );
+        let state = Arc::new(BenchmarkState::new(client.clone(), std::mem::take(&mut keys), args.object_size_bytes, prefix.clone())
+            .with_seed(seed.wrapping_add(index as u64))
+            .with_payload_pool(args.payload_pool)
+            .with_op_timeout(args.op_timeout_ms)
+            .with_target_rate(args.target_rate)
+            .with_worker_stats(args.worker_stats)
+            .with_key_popularity(key_popularity.clone())
+            .with_intervals_for(args)
+            .with_dual_write(DualWriteTarget::for_args(&phase_args)?)
+            .with_read_compare(ReadCompareTarget::for_args(&phase_args)?)
+            .with_read_check(ReadCheck::for_args(&phase_args)?)
+            .with_overwrite_race(&phase.mode, args.overwrite_keys)
+            .with_churn(&phase.mode, args.churn_pct_per_minute, seed.wrapping_add(index as u64), client.clone(), seeded));
+        let duration = Duration::from_secs(phase_args.duration_seconds);
+        let (ok_ops, err_ops, histogram) = run_mode(&phase_args, state.clone(), phase_args.concurrency, duration, not_found_retry).await?;
+        let result = BenchmarkResult::from_run(&phase_args, &phase_args.mode, phase_args.concurrency, ok_ops, err_ops, &histogram, &state);
+        print_result(&result)?;
+        // Later phases only see the primary, so the mirror's copies go now
+        if let Some(target) = state.dual_write.as_ref().filter(|_| args.cleanup) {
+            target.cleanup_mirror(&prefix, args.object_size_bytes, &args.cleanup_options).await?;
+        }
+        
+        keys = state.live_keys();
+        phase_results.push(result);
+    }
+    Ok((PhasedResult { phase_results, skipped_phases }, keys))
+}
+
+pub(crate) async fn run_mode(args: &Args, state: Arc<BenchmarkState>, concurrency: usize, duration: Duration, not_found_retry: Duration) -> Result<(u64, u64, Histogram<u64>)> {
+    set_live_mode(&args.mode, concurrency);
+    let outcome = match args.mode.as_str() {
+        "stat" => run_stat_benchmark(state, duration, concurrency, not_found_retry).await,
+        "stat_storm" => run_stat_storm_benchmark(state, duration, concurrency, args.storm_keys).await,
+        "read_small" => run_read_benchmark(state, duration, concurrency, not_found_retry).await,
+        "write_small" => run_write_benchmark(state, duration, concurrency).await,
+        "scatter_write" => run_scatter_write_benchmark(state, duration, concurrency, args.fanout).await,
+        "commit" => run_commit_benchmark(state, duration, concurrency, args.fanout).await,
+        "delete" => run_delete_benchmark(state, duration, concurrency).await,
+        "dual_write" => {
+            let Some(target) = state.dual_write.clone() else {
+                anyhow::bail!("dual_write needs --mirror-endpoint");
+            };
+            run_dual_write_benchmark(state, duration, concurrency, target).await
+        }
+        "read_compare" => {
+            let Some(target) = state.read_compare.clone() else {
+                anyhow::bail!("read_compare needs --mirror-endpoint");
+            };
+            run_read_compare_benchmark(state, duration, concurrency, target).await
+        }
+        "read_overwrite" => {
+            let Some(race) = state.overwrite.clone().filter(|race| !race.keys.is_empty()) else {
+                anyhow::bail!("read_overwrite needs dataset keys to overwrite");
+            };
+            run_read_overwrite_benchmark(state, duration, concurrency, race, args.overwriters).await
+        }
+        "list" => run_list_benchmark(state, duration, concurrency, args.list_max_entries).await,
+        _ => anyhow::bail!("Unknown mode: {}. Supported modes: {}", args.mode, MODES.join(", ")),
+    };
+    Ok(outcome)
+}
+
+/// Finds the highest concurrency whose latency at the goal percentile stays
+/// within `goal_ms`, interpolating linearly between the last passing and the
+/// first failing sweep point. `points` must be sorted by concurrency.
+pub(crate) fn find_latency_goal(points: &[SweepPoint], percentile: f64, goal_ms: f64) -> LatencyGoalReport {
+    let goal_us = goal_ms * 1000.0;
+    let mut report = LatencyGoalReport {
+        percentile,
+        goal_ms,
+        max_concurrency: None,
+        max_qps: None,
+        bounded_by_sweep: false,
+    };
+    
+    for (i, point) in points.iter().enumerate() {
+        if point.latency_us as f64 > goal_us {
+            if report.max_concurrency.is_some() {
+                let prev = &points[i - 1];
+                let frac = (goal_us - prev.latency_us as f64) / (point.latency_us as f64 - prev.latency_us as f64);
+                report.max_concurrency = Some(prev.concurrency as f64 + frac * (point.concurrency - prev.concurrency) as f64);
+                report.max_qps = Some(prev.qps + frac * (point.qps - prev.qps));
+            }
+            return report;
+        }
+        report.max_concurrency = Some(point.concurrency as f64);
+        report.max_qps = Some(point.qps);
+    }
+    
+    report.bounded_by_sweep = report.max_concurrency.is_some();
+    report
+}
+
+/// Runs the configured mode once per `--sweep-concurrency` level against the
+/// same dataset and prints a comparison table plus the latency-goal answer.
+pub(crate) async fn run_concurrency_sweep(args: &Args, state: Arc<BenchmarkState>, duration: Duration, not_found_retry: Duration) -> Result<SweepResult> {
+    let mut levels = args.sweep_concurrency.clone();
+    levels.sort_unstable();
+    levels.dedup();
+    
+    let quantile = args.latency_goal_percentile / 100.0;
+    let mut results = Vec::with_capacity(levels.len());
+    let mut points = Vec::with_capacity(levels.len());
+    
+    for &concurrency in &levels {
+        if interrupted() {
+            break;
+        }
+        tracing::info!(concurrency, "📊 Sweep step");
+        let (ok_ops, err_ops, histogram) = run_mode(args, state.clone(), concurrency, duration, not_found_retry).await?;
+        let result = BenchmarkResult::from_run(args, &args.mode, concurrency, ok_ops, err_ops, &histogram, &state);
+        tracing::info!(
+            concurrency,
+            qps = result.qps,
+            p50_ms = result.latency_us_p50 as f64 / 1000.0,
+            p99_ms = result.latency_us_p99 as f64 / 1000.0,
+            errors = result.err_ops,
+            "Sweep step finished"
+        );
+        
+        // A level without a single successful op cannot meet any goal
+        let latency_us = if histogram.is_empty() { u64::MAX } else { histogram.value_at_quantile(quantile) };
+        points.push(SweepPoint { concurrency, qps: result.qps, latency_us });
+        results.push(result);
+    }
+    
+    let sweep = SweepResult {
+        mode: args.mode.clone(),
+        results,
+        latency_goal: args
+            .latency_goal_ms
+            .map(|goal_ms| find_latency_goal(&points, args.latency_goal_percentile, goal_ms)),
+    };
+    
+    report!();
+    report!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+    report!("📊 Sweep Results (JSON)");
+    report!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+    report!("{}", serde_json::to_string_pretty(&sweep)?);
+    
+    report!();
+    report!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+    report!("📊 Sweep Results (Human-readable)");
+    report!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+    report!("{:>12} {:>12} {:>10} {:>10} {:>10} {:>10}", "Concurrency", "QPS", "P50 ms", "P95 ms", "P99 ms", "Errors");
+    for result in &sweep.results {
+        report!("{:>12} {:>12.2} {:>10.2} {:>10.2} {:>10.2} {:>10}",
+                result.concurrency, result.qps,
+                result.latency_us_p50 as f64 / 1000.0, result.latency_us_p95 as f64 / 1000.0,
+                result.latency_us_p99 as f64 / 1000.0, result.err_ops);
+    }
+    
+    if let Some(goal) = &sweep.latency_goal {
+        report!();
+        match (goal.max_concurrency, goal.max_qps) {
+            (Some(concurrency), Some(qps)) if goal.bounded_by_sweep => {
+                report!("🎯 P{} <= {:.2} ms met at every level; max concurrency >= {:.0} (>= {:.2} QPS), sweep higher to find the limit",
+                        goal.percentile, goal.goal_ms, concurrency, qps);
+            }
+            (Some(concurrency), Some(qps)) => {
+                report!("🎯 P{} <= {:.2} ms: max concurrency ≈ {:.1} (≈ {:.2} QPS)",
+                        goal.percentile, goal.goal_ms, concurrency, qps);
+            }
+            _ => {
+                report!("🎯 P{} <= {:.2} ms not met at any swept concurrency", goal.percentile, goal.goal_ms);
+            }
+        }
+    }
+    
+    Ok(sweep)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unsupported_covers_kind_status_and_code() {
+        let response = |status, code: Option<&str>| ErrorResponse { status, code: code.map(str::to_string) };
+        let unexpected = opendal::Error::new(opendal::ErrorKind::Unexpected, "failed");
+        assert!(is_unsupported(&opendal::Error::new(opendal::ErrorKind::Unsupported, "no"), None));
+        assert!(is_unsupported(&unexpected, Some(&response(501, None))));
+        assert!(is_unsupported(&unexpected, Some(&response(400, Some("NotImplemented")))));
+        assert!(!is_unsupported(&unexpected, Some(&response(500, None))));
+    }
+}
@@ -0,0 +1,1051 @@
+//! Rendering results: the printed summary, result files, comparisons,
+//! trends and the Markdown and HTML reports.
+
+use super::*;
+
+/// Kubernetes metadata captured into result labels: (label, downward-API env var).
+/// Map these with `fieldRef` in the pod spec; see benchmark-pod.yaml.template.
+pub(crate) const K8S_LABEL_ENV: &[(&str, &str)] = &[
+    ("k8s.pod", "POD_NAME"),
+    ("k8s.namespace", "POD_NAMESPACE"),
+    ("k8s.node", "NODE_NAME"),
+    ("k8s.pod_uid", "POD_UID"),
+    ("k8s.pod_ip", "POD_IP"),
+];
+
+/// Collects user `--label`s plus, when running in Kubernetes, pod and node
+/// metadata from the downward API environment. User labels win on conflict.
+pub(crate) fn result_labels(args: &Args) -> BTreeMap<String, String> {
+    let mut labels = BTreeMap::new();
+    if std::env::var_os("KUBERNETES_SERVICE_HOST").is_some() {
+        for (label, var) in K8S_LABEL_ENV {
+            if let Ok(value) = std::env::var(var) {
+                labels.insert(label.to_string(), value);
+            }
+        }
+        // Fall back to what every pod has even without downward-API wiring
+        if !labels.contains_key("k8s.pod") {
+            if let Some(host) = hostname() {
+                labels.insert("k8s.pod".to_string(), host);
+            }
+        }
+        if !labels.contains_key("k8s.namespace") {
+            if let Ok(ns) = std::fs::read_to_string("/var/run/secrets/kubernetes.io/serviceaccount/namespace") {
+                labels.insert("k8s.namespace".to_string(), ns.trim().to_string());
+            }
+        }
+    }
+    for label in &args.labels {
+        match label.split_once('=') {
+            Some((key, value)) => {
+                labels.insert(key.trim().to_string(), value.trim().to_string());
+            }
+            None => tracing::warn!(label = %label, "Ignoring label: expected key=value"),
+        }
+    }
+    labels
+}
+
+/// Names a run starting now, sortably, so the outputs of many benchmark Jobs
+/// sharing one volume or bucket can be aggregated:
+/// `<mode>/<timestamp>_[<namespace>_]<pod or host>[_<label values>]_<run id>`.
+/// The run ID prefix keeps two processes starting in the same second on the
+/// same host (or with the same labels) from overwriting each other.
+pub(crate) fn results_run_name(args: &Args) -> String {
+    let labels = result_labels(args);
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    let mut parts = vec![amz_dates(now).1];
+    if let Some(namespace) = labels.get("k8s.namespace") {
+        parts.push(namespace.clone());
+    }
+    parts.push(
+        labels
+            .get("k8s.pod")
+            .cloned()
+            .or_else(hostname)
+            .unwrap_or_else(|| "local".to_string()),
+    );
+    parts.extend(labels.iter().filter(|(key, _)| !key.starts_with("k8s.")).map(|(_, value)| value.clone()));
+    parts.push(run_metadata().run_id[..8].to_string());
+    let sanitize = |name: String| -> String {
+        name.chars()
+            .map(|c| if c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-') { c } else { '-' })
+            .collect()
+    };
+    let mode = if args.phases.is_empty() { args.mode.clone() } else { "phases".to_string() };
+    format!("{}/{}", sanitize(mode), sanitize(parts.join("_")))
+}
+
+/// A run's results, its settings and its interval series, by file name.
+pub(crate) fn results_files(args: &Args, results: &serde_json::Value) -> Result<Vec<(String, Vec<u8>)>> {
+    let mut files = vec![
+        ("results.json".to_string(), serde_json::to_vec_pretty(results)?),
+        // Credentials are never serialized
+        ("config.json".to_string(), serde_json::to_vec_pretty(args)?),
+    ];
+    
+    if let Some(csv) = render_timeseries_csv(results)? {
+        files.push(("intervals.csv".to_string(), csv.into_bytes()));
+    }
+    files.extend(hgrm_files(results)?.into_iter().map(|(name, hgrm)| (name, hgrm.into_bytes())));
+    Ok(files)
+}
+
+/// The per-interval series of every result as CSV, or None if there is none.
+pub(crate) fn render_timeseries_csv(results: &serde_json::Value) -> Result<Option<String>> {
+    let mut rows = String::new();
+    for (label, result) in labelled_results(results)? {
+        for interval in &result.intervals {
+            let _ = writeln!(
+                rows,
+                "{},{},{},{},{:.2},{},{},{:.3},{},{},{},{},{}",
+                csv_field(&label), interval.start_seconds, interval.ok_ops, interval.err_ops, interval.qps, interval.latency_us_p50,
+                interval.latency_us_p99, interval.error_pct, interval.in_flight, interval.window_latency_us_p50, interval.window_latency_us_p99,
+                interval.throttled_ops, interval.throttled,
+            );
+        }
+    }
+    if rows.is_empty() {
+        return Ok(None);
+    }
+    Ok(Some(format!("result,start_seconds,ok_ops,err_ops,qps,latency_us_p50,latency_us_p99,error_pct,in_flight,window_latency_us_p50,window_latency_us_p99,throttled_ops,throttled\n{}", rows)))
+}
+
+/// Quotes a CSV field when it holds a separator, quote or line break.
+pub(crate) fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// One flat row per result, so the rows of many runs can be concatenated and
+/// loaded into a spreadsheet or pandas.
+pub(crate) fn render_results_csv(args: &Args, results: &serde_json::Value, header: bool) -> Result<String> {
+    let mut csv = String::new();
+    if header {
+        csv.push_str("result,mode,concurrency,duration_seconds,object_size_bytes,ok_ops,err_ops,timeout_ops,qps,");
+        csv.push_str("latency_us_p50,latency_us_p95,latency_us_p99,latency_us_mean,bytes_per_second,labels\n");
+    }
+    for (label, result) in labelled_results(results)? {
+        let bytes_per_second = throughput_bytes_per_second(args, &result.mode, result.qps);
+        let labels: Vec<String> = result.labels.iter().map(|(key, value)| format!("{}={}", key, value)).collect();
+        let _ = writeln!(
+            csv,
+            "{},{},{},{},{},{},{},{},{:.2},{},{},{},{},{:.0},{}",
+            csv_field(&label), result.mode, result.concurrency, result.duration_seconds, args.object_size_bytes,
+            result.ok_ops, result.err_ops, result.timeout_ops.unwrap_or(0), result.qps,
+            result.latency_us_p50, result.latency_us_p95, result.latency_us_p99, result.latency_us_mean,
+            bytes_per_second, csv_field(&labels.join(";")),
+        );
+    }
+    Ok(csv)
+}
+
+/// Writes the results to --output in --format, replacing the file or, with
+/// --append, adding to it (a JSON line, or CSV rows with the header only
+/// when the file is new).
+pub(crate) fn write_output_file(path: &std::path::Path, args: &Args, results: &serde_json::Value) -> Result<()> {
+    let content = match (args.format.as_str(), args.append) {
+        ("csv", append) => {
+            let header = !append || std::fs::metadata(path).map_or(true, |metadata| metadata.len() == 0);
+            render_results_csv(args, results, header)?.into_bytes()
+        }
+        (_, true) => {
+            let mut line = serde_json::to_vec(results)?;
+            line.push(b'\n');
+            line
+        }
+        (_, false) => serde_json::to_vec_pretty(results)?,
+    };
+    let written = if args.append {
+        std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .and_then(|mut file| std::io::Write::write_all(&mut file, &content))
+    } else {
+        std::fs::write(path, content)
+    };
+    written.map_err(|e| anyhow::anyhow!("Failed to write results to {}: {}", path.display(), e))?;
+    tracing::info!("💾 {} results to {}", if args.append { "Appended" } else { "Wrote" }, path.display());
+    Ok(())
+}
+
+/// One result of a run recorded in --results-db.
+#[derive(Debug)]
+pub(crate) struct TrendRun {
+    label: String,
+    recorded_at_unix_ms: u64,
+    qps: f64,
+    latency_us_p99: u64,
+    err_ops: u64,
+}
+
+#[cfg(feature = "results-db")]
+pub(crate) fn open_results_db(path: &std::path::Path) -> Result<rusqlite::Connection> {
+    let db = rusqlite::Connection::open(path)
+        .map_err(|e| anyhow::anyhow!("Failed to open results database {}: {}", path.display(), e))?;
+    db.execute_batch(
+        "CREATE TABLE IF NOT EXISTS runs (
+            id INTEGER PRIMARY KEY,
+            scenario TEXT NOT NULL,
+            label TEXT NOT NULL,
+            recorded_at_unix_ms INTEGER NOT NULL,
+            qps REAL NOT NULL,
+            latency_us_p99 INTEGER NOT NULL,
+            err_ops INTEGER NOT NULL,
+            result TEXT NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS runs_by_scenario ON runs (scenario, recorded_at_unix_ms);",
+    )?;
+    Ok(db)
+}
+
+/// Adds a row per result to --results-db, keeping the result's JSON next to
+/// the columns `trend` charts.
+#[cfg(feature = "results-db")]
+pub(crate) fn record_results_db(path: &std::path::Path, scenario: &str, results: &serde_json::Value) -> Result<()> {
+    let mut db = open_results_db(path)?;
+    let now_ms = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_millis() as u64;
+    let transaction = db.transaction()?;
+    for (label, result) in labelled_results(results)? {
+        transaction.execute(
+            "INSERT INTO runs (scenario, label, recorded_at_unix_ms, qps, latency_us_p99, err_ops, result)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            rusqlite::params![
+                scenario,
+                label,
+                result.started_at_unix_ms.unwrap_or(now_ms) as i64,
+                result.qps,
+                result.latency_us_p99 as i64,
+                result.err_ops as i64,
+                serde_json::to_string(&result)?,
+            ],
+        )?;
+    }
+    transaction.commit()?;
+    tracing::info!("💾 Recorded results as scenario {} in {}", scenario, path.display());
+    Ok(())
+}
+
+#[cfg(not(feature = "results-db"))]
+pub(crate) fn record_results_db(_path: &std::path::Path, _scenario: &str, _results: &serde_json::Value) -> Result<()> {
+    Err(RunFailure::new(FailureCategory::Config, "--results-db requires building with the `results-db` feature").into())
+}
+
+/// The most recent `limit` results recorded for `scenario`, oldest first.
+#[cfg(feature = "results-db")]
+pub(crate) fn load_trend_runs(path: &std::path::Path, scenario: &str, limit: usize) -> Result<Vec<TrendRun>> {
+    if !path.exists() {
+        anyhow::bail!("No results database at {}", path.display());
+    }
+    let db = open_results_db(path)?;
+    let mut query = db.prepare(
+        "SELECT label, recorded_at_unix_ms, qps, latency_us_p99, err_ops FROM runs
+         WHERE scenario = ?1 ORDER BY recorded_at_unix_ms DESC, id DESC LIMIT ?2",
+    )?;
+    let rows = query.query_map(rusqlite::params![scenario, limit as i64], |row| {
+        Ok(TrendRun {
+            label: row.get(0)?,
+            recorded_at_unix_ms: row.get::<_, i64>(1)? as u64,
+            qps: row.get(2)?,
+            latency_us_p99: row.get::<_, i64>(3)? as u64,
+            err_ops: row.get::<_, i64>(4)? as u64,
+        })
+    })?;
+    let mut runs = rows.collect::<rusqlite::Result<Vec<_>>>()?;
+    runs.reverse();
+    Ok(runs)
+}
+
+#[cfg(not(feature = "results-db"))]
+pub(crate) fn load_trend_runs(_path: &std::path::Path, _scenario: &str, _limit: usize) -> Result<Vec<TrendRun>> {
+    Err(RunFailure::new(FailureCategory::Config, "`trend` requires building with the `results-db` feature").into())
+}
+
+/// The recorded buckets of `histogram` as (value, count) pairs.
+pub(crate) fn recorded_buckets(histogram: &Histogram<u64>) -> Vec<(u64, u64)> {
+    histogram.iter_recorded().map(|value| (value.value_iterated_to(), value.count_at_value())).collect()
+}
+
+/// Adds (value, count) pairs saved by `recorded_buckets` to `histogram`.
+pub(crate) fn add_buckets(histogram: &mut Histogram<u64>, buckets: &[(u64, u64)]) {
+    for (value, count) in buckets {
+        histogram.record_n(*value, *count).ok();
+    }
+}
+
+/// Renders `histogram` (in microseconds) like HdrHistogram's
+/// outputPercentileDistribution with values in milliseconds, which
+/// HistogramLogAnalyzer and the online plotter read.
+pub(crate) fn render_hgrm(histogram: &Histogram<u64>) -> String {
+    let ms = |us: f64| us / 1000.0;
+    let mut hgrm = String::from("       Value     Percentile TotalCount 1/(1-Percentile)\n\n");
+    for value in histogram.iter_quantiles(5) {
+        let quantile = value.quantile_iterated_to();
+        let line = if quantile < 1.0 {
+            format!("{:12.3} {:2.12} {:10} {:14.2}", ms(value.value_iterated_to() as f64), quantile,
+                    value.count_since_last_iteration(), 1.0 / (1.0 - quantile))
+        } else {
+            format!("{:12.3} {:2.12} {:10}", ms(value.value_iterated_to() as f64), quantile, value.count_since_last_iteration())
+        };
+        hgrm.push_str(&line);
+        hgrm.push('\n');
+    }
+    let _ = writeln!(hgrm, "#[Mean    = {:12.3}, StdDeviation   = {:12.3}]", ms(histogram.mean()), ms(histogram.stdev()));
+    let _ = writeln!(hgrm, "#[Max     = {:12.3}, Total count    = {:12}]", ms(histogram.max() as f64), histogram.len());
+    hgrm
+}
+
+/// The .hgrm of every result that kept its histogram, by file name (named
+/// after the result's label).
+pub(crate) fn hgrm_files(results: &serde_json::Value) -> Result<Vec<(String, String)>> {
+    let mut files = Vec::new();
+    for (label, result) in labelled_results(results)? {
+        if result.histogram.is_empty() {
+            continue;
+        }
+        let mut histogram = Histogram::<u64>::new(3).unwrap();
+        add_buckets(&mut histogram, &result.histogram);
+        let name: String = label.chars().map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' }).collect();
+        files.push((format!("{}.hgrm", name), render_hgrm(&histogram)));
+    }
+    Ok(files)
+}
+
+/// Writes the .hgrm of every result that kept its histogram to `dir`.
+pub(crate) fn write_hgrm_files(dir: &std::path::Path, results: &serde_json::Value) -> Result<()> {
+    std::fs::create_dir_all(dir).map_err(|e| anyhow::anyhow!("Failed to create {}: {}", dir.display(), e))?;
+    for (name, hgrm) in hgrm_files(results)? {
+        let path = dir.join(name);
+        std::fs::write(&path, hgrm).map_err(|e| anyhow::anyhow!("Failed to write {}: {}", path.display(), e))?;
+        tracing::info!("💾 Wrote latency histogram to {}", path.display());
+    }
+    Ok(())
+}
+
+pub(crate) fn write_results_files(run_dir: &std::path::Path, files: &[(String, Vec<u8>)]) -> Result<()> {
+    std::fs::create_dir_all(run_dir)
+        .map_err(|e| anyhow::anyhow!("Failed to create results directory {}: {}", run_dir.display(), e))?;
+    for (name, content) in files {
+        let path = run_dir.join(name);
+        std::fs::write(&path, content).map_err(|e| anyhow::anyhow!("Failed to write {}: {}", path.display(), e))?;
+    }
+    tracing::info!("💾 Wrote results to {}", run_dir.display());
+    Ok(())
+}
+
+/// Uploads the results files under `<--results-prefix>/<run_name>/` in
+/// --results-bucket.
+pub(crate) async fn upload_results_files(args: &Args, bucket: &str, run_name: &str, files: &[(String, Vec<u8>)]) -> Result<()> {
+    let mut backend = args.backend.clone();
+    backend.bucket = bucket.to_string();
+    let client = create_client(&backend)?;
+    let prefix = args.results_prefix.trim_matches('/');
+    let dir = if prefix.is_empty() { run_name.to_string() } else { format!("{}/{}", prefix, run_name) };
+    for (name, content) in files {
+        let key = format!("{}/{}", dir, name);
+        client.write(&key, content.clone()).await
+            .map_err(|e| anyhow::anyhow!("Failed to upload {} to {}: {}", key, bucket, e))?;
+    }
+    tracing::info!("📤 Uploaded results to {}/{}/", bucket, dir);
+    Ok(())
+}
+
+pub(crate) fn print_result(result: &BenchmarkResult) -> Result<()> {
+    // Printed once the dashboard closes instead
+    if tui_active() {
+        return Ok(());
+    }
+    // Print JSON output
+    report!();
+    report!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+    report!("📊 Results (JSON)");
+    report!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+    report!("{}", serde_json::to_string_pretty(result)?);
+    
+    // Print human-readable summary
+    report!();
+    report!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+    report!("📊 Results (Human-readable)");
+    report!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+    print_summary(result);
+    Ok(())
+}
+
+pub(crate) fn print_summary(result: &BenchmarkResult) {
+    report!("Mode:              {}", result.mode);
+    report!("Concurrency:       {}", result.concurrency);
+    if let Some(cap) = &result.concurrency_cap {
+        report!("⚠️  Capped from {}: {}", cap.requested, cap.reason);
+    }
+    match result.effective_duration_seconds {
+        Some(effective) => report!("Duration:          {}s (effective {:.2}s)", result.duration_seconds, effective),
+        None => report!("Duration:          {}s", result.duration_seconds),
+    }
+    report!("Successful Ops:     {}", result.ok_ops);
+    report!("Failed Ops:        {}", result.err_ops);
+    if let Some(timeout_ops) = result.timeout_ops {
+        report!("Timed-out Ops:      {}", timeout_ops);
+    }
+    if let Some(budget) = &result.timeout_budget {
+        let deciles: Vec<String> = budget.deciles.iter().map(|fraction| format!("{:.1}%", fraction * 100.0)).collect();
+        report!("Timeout budget:     P99 used {:.0}% of {} ms; {:.2}% of ops finished in the last 10%",
+                budget.p99_budget_used * 100.0, budget.timeout_ms, budget.last_decile_fraction * 100.0);
+        report!("  by tenth:         {}", deciles.join(" "));
+    }
+    if let Some(truncated) = result.truncated_lists {
+        report!("⚠️  Truncated Lists: {} (hit --list-max-entries)", truncated);
+    }
+    if let Some(throttling) = &result.throttling {
+        let reasons: Vec<String> = throttling.reasons.iter().map(|(reason, count)| format!("{} {}", reason, count)).collect();
+        report!("Throttled Ops:      {} ({:.2}% of ops: {})", throttling.throttled_ops, throttling.throttle_rate_pct, reasons.join(", "));
+        report!("Throttled Time:     {}s ({:.1}% of the run), first at {}s", throttling.throttled_seconds, throttling.throttled_pct,
+                throttling.first_throttled_seconds);
+    }
+    if let Some(retries) = &result.retries {
+        report!("Retried Ops:        {} ({} retries)", retries.retried_ops, retries.retries);
+        for (name, latency) in [("First attempt", &retries.first_attempt), ("Every attempt", &retries.attempts)] {
+            if let Some(latency) = latency {
+                report!("{:<19} P50 {:.2} ms, P95 {:.2} ms, P99 {:.2} ms ({} attempts)",
+                        name, latency.latency_us_p50 as f64 / 1000.0, latency.latency_us_p95 as f64 / 1000.0,
+                        latency.latency_us_p99 as f64 / 1000.0, latency.ok_ops);
+            }
+        }
+    }
+    report!("QPS:               {:.2}", result.qps);
+    if let Some(isolation) = result.key_isolation {
+        report!("Key isolation:     {}", isolation.describe());
+    }
+    if let Some(workers) = &result.workers {
+        report!("Workers:           {} (median P99 {:.2} ms)", workers.workers.len(), workers.median_p99_us as f64 / 1000.0);
+        for worker in workers.workers.iter().filter(|w| workers.stragglers.contains(&w.worker)) {
+            report!("⚠️  Straggler:      worker {} P99 {:.2} ms ({:.1}x the median, {} ops)", worker.worker, worker.latency_us_p99 as f64 / 1000.0,
+                    worker.latency_us_p99 as f64 / workers.median_p99_us as f64, worker.ok_ops);
+        }
+    }
+    if let Some(stability) = &result.qps_stability {
+        report!("QPS Stability:     CoV {:.3} (per-second mean {:.1}, stddev {:.1})", stability.cov, stability.mean_qps, stability.stddev_qps);
+    }
+    if let Some(startup) = &result.startup {
+        report!("Startup:           first success after {:.1} ms (client ready at {:.1} ms, first request {:.1} ms)",
+                startup.first_success_ms, startup.client_ready_ms, startup.first_request_ms);
+    }
+    if let Some(queueing) = &result.queueing {
+        for (name, latency) in [("Schedule lag", queueing.schedule_lag.as_ref()), ("Scheduler delay", Some(&queueing.scheduler))] {
+            let Some(latency) = latency else {
+                continue;
+            };
+            report!("{:<19} P50 {:.2} ms, P99 {:.2} ms (client-side, not in op latency)", format!("{}:", name),
+                    latency.latency_us_p50 as f64 / 1000.0, latency.latency_us_p99 as f64 / 1000.0);
+        }
+    }
+    if let Some(corrected) = &result.corrected_latency {
+        let latency = &corrected.latency;
+        report!("Target rate:       {:.2} ops/s{}", corrected.target_rate,
+                if result.qps < corrected.target_rate * 0.95 { " ⚠️  not sustained" } else { "" });
+        report!("Corrected latency:  P50 {:.2} ms, P95 {:.2} ms, P99 {:.2} ms (from intended start)",
+                latency.latency_us_p50 as f64 / 1000.0, latency.latency_us_p95 as f64 / 1000.0, latency.latency_us_p99 as f64 / 1000.0);
+    }
+    if let Some(nic) = &result.nic {
+        let gbit = |bytes: f64| bytes * 8.0 / 1e9;
+        let usage = format!("{:.2} of {:.1} Gbit/s ({:.0}%)", gbit(nic.throughput_bytes_per_second),
+                            gbit(nic.link_bytes_per_second as f64), nic.utilization * 100.0);
+        if nic.saturated {
+            report!("⚠️  NIC saturated:   {}; throughput is bounded by the client link", usage);
+        } else {
+            report!("NIC:               {}", usage);
+        }
+    }
+    report!("Latency P50:        {} μs ({:.2} ms)", result.latency_us_p50, result.latency_us_p50 as f64 / 1000.0);
+    report!("Latency P95:        {} μs ({:.2} ms)", result.latency_us_p95, result.latency_us_p95 as f64 / 1000.0);
+    report!("Latency P99:        {} μs ({:.2} ms)", result.latency_us_p99, result.latency_us_p99 as f64 / 1000.0);
+    report!("Latency Mean:       {} μs ({:.2} ms)", result.latency_us_mean, result.latency_us_mean as f64 / 1000.0);
+    if result.ok_ops > 0 {
+        report!("Latency Min/Max:    {:.2} ms / {:.2} ms (stddev {:.2} ms)",
+                result.latency_us_min as f64 / 1000.0, result.latency_us_max as f64 / 1000.0, result.latency_us_stddev / 1000.0);
+    }
+    if !result.latency_distribution.is_empty() {
+        report!("Latency distribution:");
+        report!("{}", render_latency_distribution(&result.latency_distribution).trim_end_matches('\n'));
+    }
+    if !result.percentiles.is_empty() {
+        let percentiles: Vec<String> = result
+            .percentiles
+            .iter()
+            .map(|p| format!("P{} {:.2} ms", p.percentile, p.latency_us as f64 / 1000.0))
+            .collect();
+        report!("Percentiles:        {}", percentiles.join(", "));
+    }
+    for (phase, latency) in &result.phases {
+        report!("Phase {:<12} P50 {:.2} ms, P95 {:.2} ms, P99 {:.2} ms ({} ops)",
+                phase, latency.latency_us_p50 as f64 / 1000.0, latency.latency_us_p95 as f64 / 1000.0,
+                latency.latency_us_p99 as f64 / 1000.0, latency.ok_ops);
+    }
+    if let Some(cache) = &result.stat_cache {
+        report!("Stat cache:         {:.1}% hits ({} hits, {} misses, TTL {} ms); uncached P50 {:.2} ms, P99 {:.2} ms",
+                cache.hit_rate * 100.0, cache.hits, cache.misses, cache.ttl_ms,
+                cache.miss_latency_us_p50 as f64 / 1000.0, cache.miss_latency_us_p99 as f64 / 1000.0);
+    }
+    if let Some(payload) = &result.payload {
+        report!("Payload generation: {:.1}% of worker time ({} payloads, {})",
+                payload.generation_share * 100.0, payload.payloads,
+                if payload.pool_buffers == 0 { "fresh per write".to_string() } else { format!("pool of {}", payload.pool_buffers) });
+    }
+    if let Some(memory) = &result.memory {
+        if let Some(peak) = memory.peak_rss_bytes {
+            report!("Peak RSS:           {:.1} MiB", peak as f64 / (1024.0 * 1024.0));
+        }
+        if let (Some(allocations), Some(bytes)) = (memory.allocations_per_op, memory.allocated_bytes_per_op) {
+            report!("Allocations/op:     {:.1} ({:.0} bytes)", allocations, bytes);
+        }
+    }
+    for (verb, latency) in &result.verbs {
+        report!("HTTP {:<13} P50 {:.2} ms, P95 {:.2} ms, P99 {:.2} ms ({} ok, {} err)",
+                verb, latency.latency_us_p50 as f64 / 1000.0, latency.latency_us_p95 as f64 / 1000.0,
+                latency.latency_us_p99 as f64 / 1000.0, latency.ok_ops, latency.err_ops);
+    }
+    if let Some(dual_write) = &result.dual_write {
+        for (side, latency) in [("primary", &dual_write.primary), ("mirror", &dual_write.mirror)] {
+            report!("Write to {:<10} P50 {:.2} ms, P95 {:.2} ms, P99 {:.2} ms ({} ok, {} err)",
+                    side, latency.latency_us_p50 as f64 / 1000.0, latency.latency_us_p95 as f64 / 1000.0,
+                    latency.latency_us_p99 as f64 / 1000.0, latency.ok_ops, latency.err_ops);
+        }
+        if dual_write.diverged > 0 {
+            report!("⚠️  Diverged writes: {} (succeeded on one backend only)", dual_write.diverged);
+        }
+        if let (Some(verified), Some(mismatches)) = (dual_write.verified, dual_write.readback_mismatches) {
+            report!("Read-back check:    {} objects, {} mismatches", verified, mismatches);
+        }
+    }
+    if let Some(compare) = &result.read_compare {
+        for (side, latency) in [("primary", &compare.primary), ("mirror", &compare.mirror)] {
+            report!("Read from {:<9} P50 {:.2} ms, P95 {:.2} ms, P99 {:.2} ms ({} ok, {} err)",
+                    side, latency.latency_us_p50 as f64 / 1000.0, latency.latency_us_p95 as f64 / 1000.0,
+                    latency.latency_us_p99 as f64 / 1000.0, latency.ok_ops, latency.err_ops);
+        }
+        report!("Compared reads:     {} ({} matched)", compare.compared, compare.matched);
+        if compare.divergent_keys > 0 {
+            report!("⚠️  Divergent keys: {}", compare.divergent_keys);
+            for divergence in &compare.divergences {
+                report!("  {} ({})", divergence.key, divergence.reason);
+            }
+        }
+    }
+    if let Some(overwrite) = &result.overwrite {
+        let reads = overwrite.current + overwrite.new + overwrite.stale + overwrite.torn;
+        let share = |count: u64| if reads == 0 { 0.0 } else { count as f64 / reads as f64 * 100.0 };
+        report!("Overwrites:         {} of {} keys (P99 {:.2} ms, {} err)", overwrite.overwrites.ok_ops, overwrite.keys,
+                overwrite.overwrites.latency_us_p99 as f64 / 1000.0, overwrite.overwrites.err_ops);
+        report!("Read versions:      {:.1}% current, {:.1}% new, {:.1}% stale, {:.1}% torn", share(overwrite.current), share(overwrite.new),
+                share(overwrite.stale), share(overwrite.torn));
+        for (side, latency) in [("racing", &overwrite.racing_reads), ("quiet", &overwrite.quiet_reads)] {
+            report!("Reads {:<12} P50 {:.2} ms, P99 {:.2} ms ({} reads)", format!("({}):", side), latency.latency_us_p50 as f64 / 1000.0,
+                    latency.latency_us_p99 as f64 / 1000.0, latency.ok_ops);
+        }
+        for (kind, count) in &overwrite.read_errors {
+            report!("⚠️  Read errors:    {} {}", count, kind);
+        }
+    }
+    if let Some(churn) = &result.churn {
+        report!("Churn:              {}%/min, {} replacements of {} keys (P99 {:.2} ms, {} err{})", churn.pct_per_minute,
+                churn.replacements.ok_ops, churn.churned_keys, churn.replacements.latency_us_p99 as f64 / 1000.0, churn.replacements.err_ops,
+                if churn.skipped > 0 { format!(", {} skipped", churn.skipped) } else { String::new() });
+        report!("NotFound reads:     {} ({:.3}%)", churn.not_found_reads, churn.not_found_pct);
+        for (side, latency) in [("churned", &churn.churned_reads), ("other", &churn.other_reads)] {
+            report!("Reads {:<12} P50 {:.2} ms, P99 {:.2} ms ({} reads, {} err)", format!("({}):", side), latency.latency_us_p50 as f64 / 1000.0,
+                    latency.latency_us_p99 as f64 / 1000.0, latency.ok_ops, latency.err_ops);
+        }
+    }
+    if let Some(verify) = &result.read_verify {
+        report!("Verified reads:     {} ({} mismatched)", verify.verified, verify.mismatched);
+        for mismatch in &verify.mismatches {
+            let at = mismatch.first_bad_offset.map(|offset| format!(" at byte {}", offset)).unwrap_or_default();
+            report!("  ⚠️  {} bytes {}..{}: {}{} ({} bytes returned)", mismatch.key, mismatch.offset, mismatch.offset + mismatch.length,
+                    mismatch.reason, at, mismatch.returned_bytes);
+        }
+    }
+    for (key, latency) in &result.keys {
+        report!("Key {:<40} {:.2} QPS, P50 {:.2} ms, P99 {:.2} ms ({} err)",
+                key, latency.ok_ops as f64 / result.duration_seconds.max(1) as f64, latency.latency_us_p50 as f64 / 1000.0,
+                latency.latency_us_p99 as f64 / 1000.0, latency.err_ops);
+    }
+    report!("Backend:            {}://{}/{}", result.backend.service, result.backend.endpoint, result.backend.bucket);
+}
+
+#[derive(Debug, Serialize)]
+pub(crate) struct ResultComparison {
+    pub(crate) label: String,
+    pub(crate) baseline_endpoint: String,
+    pub(crate) current_endpoint: String,
+    pub(crate) metrics: Vec<MetricDelta>,
+}
+
+#[derive(Debug, Serialize)]
+pub(crate) struct MetricDelta {
+    pub(crate) metric: &'static str,
+    pub(crate) baseline: f64,
+    pub(crate) current: f64,
+    pub(crate) change_pct: Option<f64>,
+}
+
+/// Flattens a results document (single run, sweep, phases, or read_write pair) into
+/// labelled results that can be matched up across runs.
+pub(crate) fn labelled_results(value: &serde_json::Value) -> Result<Vec<(String, BenchmarkResult)>> {
+    if let Some(results) = value.get("phase_results") {
+        let results: Vec<BenchmarkResult> = serde_json::from_value(results.clone())?;
+        return Ok(results
+            .into_iter()
+            .enumerate()
+            .map(|(index, r)| (format!("{}:{}", index + 1, r.mode), r))
+            .collect());
+    }
+    if let Some(results) = value.get("results") {
+        let results: Vec<BenchmarkResult> = serde_json::from_value(results.clone())?;
+        return Ok(results
+            .into_iter()
+            .map(|r| (format!("{}@{}", r.mode, r.concurrency), r))
+            .collect());
+    }
+    if value.get("read").is_some() {
+        let mut labelled = Vec::new();
+        for label in ["read", "write"] {
+            labelled.push((label.to_string(), serde_json::from_value(value[label].clone())?));
+        }
+        return Ok(labelled);
+    }
+    let result: BenchmarkResult = serde_json::from_value(value.clone())?;
+    Ok(vec![(result.mode.clone(), result)])
+}
+
+pub(crate) fn compare_results(baseline: &serde_json::Value, current: &serde_json::Value) -> Result<Vec<ResultComparison>> {
+    let current = labelled_results(current)?;
+    let mut comparisons = Vec::new();
+    for (label, base) in labelled_results(baseline)? {
+        let Some((_, cur)) = current.iter().find(|(l, _)| *l == label) else {
+            continue;
+        };
+        let metrics = [
+            ("qps", base.qps, cur.qps),
+            ("latency_us_p50", base.latency_us_p50 as f64, cur.latency_us_p50 as f64),
+            ("latency_us_p95", base.latency_us_p95 as f64, cur.latency_us_p95 as f64),
+            ("latency_us_p99", base.latency_us_p99 as f64, cur.latency_us_p99 as f64),
+            ("latency_us_mean", base.latency_us_mean as f64, cur.latency_us_mean as f64),
+            ("err_ops", base.err_ops as f64, cur.err_ops as f64),
+        ]
+        .into_iter()
+        .map(|(metric, baseline, current)| MetricDelta {
+            metric,
+            baseline,
+            current,
+            change_pct: if baseline != 0.0 { Some((current - baseline) / baseline * 100.0) } else { None },
+        })
+        .collect();
+        comparisons.push(ResultComparison {
+            label,
+            baseline_endpoint: base.backend.endpoint,
+            current_endpoint: cur.backend.endpoint.clone(),
+            metrics,
+        });
+    }
+    Ok(comparisons)
+}
+
+pub(crate) fn report_results(report: ReportArgs) -> Result<()> {
+    if !matches!(report.format.as_str(), "text" | "md" | "html" | "hgrm") {
+        let message = format!("Unknown --format: {} (expected text, md, html or hgrm)", report.format);
+        return Err(RunFailure::new(FailureCategory::Config, message).into());
+    }
+    // One page for all the files, so runs can be compared on the same charts
+    let mut html_results = Vec::new();
+    let mut merged: BTreeMap<String, Histogram<u64>> = BTreeMap::new();
+    for path in &report.files {
+        let path = if path.is_dir() { path.join("results.json") } else { path.clone() };
+        let text = std::fs::read_to_string(&path)
+            .map_err(|e| anyhow::anyhow!("Failed to read {}: {}", path.display(), e))?;
+        let value: serde_json::Value = serde_json::from_str(&text)
+            .map_err(|e| anyhow::anyhow!("Invalid results JSON in {}: {}", path.display(), e))?;
+        if report.format == "md" {
+            report!("{}", markdown_report(&path, &value)?.trim_end_matches('\n'));
+            continue;
+        }
+        if report.format == "html" {
+            for (label, result) in labelled_results(&value)? {
+                let label = if report.files.len() > 1 { format!("{} ({})", label, path.display()) } else { label };
+                html_results.push((label, result));
+            }
+            continue;
+        }
+        if report.format == "hgrm" {
+            for (label, result) in labelled_results(&value)? {
+                if result.histogram.is_empty() {
+                    tracing::warn!("⚠️  {} in {} has no histogram (run with --hgrm-dir to keep it)", label, path.display());
+                    continue;
+                }
+                let histogram = merged.entry(label).or_insert_with(|| Histogram::<u64>::new(3).unwrap());
+                add_buckets(histogram, &result.histogram);
+            }
+            continue;
+        }
+        for (label, result) in labelled_results(&value)? {
+            report!();
+            report!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+            report!("📊 {} ({})", label, path.display());
+            report!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+            print_summary(&result);
+        }
+    }
+    if report.format == "html" {
+        let title = report.files.iter().map(|path| path.display().to_string()).collect::<Vec<_>>().join(", ");
+        report!("{}", html_report(&title, &html_results).trim_end_matches('\n'));
+    }
+    for (label, histogram) in &merged {
+        report!("# {}", label);
+        report!("{}", render_hgrm(histogram).trim_end_matches('\n'));
+        report!();
+    }
+    Ok(())
+}
+
+/// Why a run is a regression against the average of the `window` runs before
+/// it, if it is one.
+pub(crate) fn trend_regression(previous: &[&TrendRun], run: &TrendRun, threshold_pct: f64) -> Option<String> {
+    if previous.is_empty() {
+        return None;
+    }
+    let qps_average = previous.iter().map(|run| run.qps).sum::<f64>() / previous.len() as f64;
+    let p99_average = previous.iter().map(|run| run.latency_us_p99 as f64).sum::<f64>() / previous.len() as f64;
+    let mut reasons = Vec::new();
+    if qps_average > 0.0 && run.qps < qps_average * (1.0 - threshold_pct / 100.0) {
+        reasons.push(format!("QPS {:.1}% below", (qps_average - run.qps) / qps_average * 100.0));
+    }
+    if p99_average > 0.0 && run.latency_us_p99 as f64 > p99_average * (1.0 + threshold_pct / 100.0) {
+        reasons.push(format!("P99 {:.1}% above", (run.latency_us_p99 as f64 - p99_average) / p99_average * 100.0));
+    }
+    if reasons.is_empty() {
+        None
+    } else {
+        Some(format!("{} the {}-run average", reasons.join(", "), previous.len()))
+    }
+}
+
+/// Prints QPS and P99 per recorded run of a scenario (one chart per result
+/// label), flagging runs that breach the moving average of the runs before them.
+pub(crate) fn print_trend(trend: TrendArgs) -> Result<()> {
+    if trend.window == 0 {
+        return Err(RunFailure::new(FailureCategory::Config, "--window must be at least 1").into());
+    }
+    let runs = load_trend_runs(&trend.results_db, &trend.scenario, trend.limit)?;
+    if runs.is_empty() {
+        anyhow::bail!("No runs recorded for scenario {} in {}", trend.scenario, trend.results_db.display());
+    }
+    let mut by_label: BTreeMap<&str, Vec<&TrendRun>> = BTreeMap::new();
+    for run in &runs {
+        by_label.entry(run.label.as_str()).or_default().push(run);
+    }
+    
+    const BAR_WIDTH: usize = 30;
+    let mut latest_regressed = Vec::new();
+    for (label, runs) in &by_label {
+        report!();
+        report!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+        report!("📈 {} / {} ({} runs)", trend.scenario, label, runs.len());
+        report!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+        report!("{:<17} {:>12} {:>12} {:>8}  QPS", "Run (UTC)", "QPS", "P99 (µs)", "Errors");
+        let peak = runs.iter().map(|run| run.qps).fold(0.0, f64::max);
+        for (index, run) in runs.iter().enumerate() {
+            let previous = &runs[index.saturating_sub(trend.window)..index];
+            let regression = if previous.len() == trend.window { trend_regression(previous, run, trend.threshold_pct) } else { None };
+            let bar = if peak > 0.0 { (run.qps / peak * BAR_WIDTH as f64).round() as usize } else { 0 };
+            report!(
+                "{:<17} {:>12.2} {:>12} {:>8}  {}{}",
+                amz_dates(run.recorded_at_unix_ms / 1000).1,
+                run.qps,
+                run.latency_us_p99,
+                run.err_ops,
+                "█".repeat(bar),
+                regression.as_ref().map(|reason| format!("  ⚠️  {}", reason)).unwrap_or_default(),
+            );
+            if index + 1 == runs.len() && regression.is_some() {
+                latest_regressed.push(label.to_string());
+            }
+        }
+    }
+    
+    if !latest_regressed.is_empty() && trend.fail_on_regression {
+        let message = format!("Latest run of {} regressed: {}", trend.scenario, latest_regressed.join(", "));
+        return Err(RunFailure::new(FailureCategory::SlaBreach, message).into());
+    }
+    Ok(())
+}
+
+/// Renders one results file as Markdown: the run's settings (from the
+/// config.json beside it, when written by --results-dir), a table of QPS and
+/// percentiles per result, and the failed requests by HTTP method.
+pub(crate) fn markdown_report(path: &std::path::Path, value: &serde_json::Value) -> Result<String> {
+    let results = labelled_results(value)?;
+    let mut md = String::new();
+    let _ = writeln!(md, "## {}\n", path.display());
+    
+    let config: Option<serde_json::Value> = path
+        .parent()
+        .map(|dir| dir.join("config.json"))
+        .and_then(|config| std::fs::read_to_string(config).ok())
+        .and_then(|text| serde_json::from_str(&text).ok());
+    let mut settings = Vec::new();
+    if let Some((_, first)) = results.first() {
+        let backend = &first.backend;
+        settings.push(("Backend".to_string(), format!("{} {} (bucket {}, {})", backend.service, backend.endpoint, backend.bucket, backend.region)));
+        settings.push(("Client".to_string(), backend.client.clone()));
+        if let Some(engine) = &backend.transfer_engine {
+            settings.push(("Transfer engine".to_string(), engine.clone()));
+        }
+        for (key, value) in &first.labels {
+            settings.push((format!("Label `{}`", key), value.clone()));
+        }
+    }
+    if let Some(serde_json::Value::Object(config)) = &config {
+        for key in ["preset", "objects", "object_size_bytes", "fanout", "payload_pool", "op_timeout_ms", "retries", "operator_rate_limit"] {
+            match config.get(key) {
+                Some(serde_json::Value::Null) | None => {}
+                Some(serde_json::Value::String(value)) => settings.push((format!("`{}`", key), value.clone())),
+                Some(value) => settings.push((format!("`{}`", key), value.to_string())),
+            }
+        }
+    }
+    if !settings.is_empty() {
+        md.push_str("| Setting | Value |\n|---|---|\n");
+        for (key, value) in &settings {
+            let _ = writeln!(md, "| {} | {} |", key, value.replace('|', "\\|"));
+        }
+        md.push('\n');
+    }
+    
+    md.push_str("| Result | Mode | Concurrency | Duration | QPS | P50 (ms) | P95 (ms) | P99 (ms) | Mean (ms) | OK | Errors | Timeouts |\n");
+    md.push_str("|---|---|---:|---:|---:|---:|---:|---:|---:|---:|---:|---:|\n");
+    let ms = |us: u64| us as f64 / 1000.0;
+    for (label, result) in &results {
+        let _ = writeln!(
+            md,
+            "| {}{} | {} | {} | {}s | {:.2} | {:.2} | {:.2} | {:.2} | {:.2} | {} | {} | {} |",
+            label, if result.interrupted { " (interrupted)" } else { "" }, result.mode, result.concurrency, result.duration_seconds,
+            result.qps, ms(result.latency_us_p50), ms(result.latency_us_p95), ms(result.latency_us_p99), ms(result.latency_us_mean),
+            result.ok_ops, result.err_ops, result.timeout_ops.unwrap_or(0),
+        );
+    }
+    
+    let mut errors = String::new();
+    for (label, result) in &results {
+        for (verb, latency) in result.verbs.iter().filter(|(_, latency)| latency.err_ops > 0) {
+            let total = latency.ok_ops + latency.err_ops;
+            let _ = writeln!(errors, "| {} | {} | {} | {} | {:.2}% |", label, verb, latency.err_ops, total, latency.err_ops as f64 / total as f64 * 100.0);
+        }
+    }
+    if !errors.is_empty() {
+        md.push_str("\n**Failed requests**\n\n| Result | Method | Failed | Requests | Error rate |\n|---|---|---:|---:|---:|\n");
+        md.push_str(&errors);
+    }
+    for (label, result) in &results {
+        if let Some(retries) = result.retries.as_ref().filter(|retries| retries.retried_ops > 0) {
+            let _ = writeln!(md, "\n{}: {} ops retried ({} retries).", label, retries.retried_ops, retries.retries);
+        }
+    }
+    md.push('\n');
+    Ok(md)
+}
+
+/// Escapes text for HTML element content and attribute values.
+pub(crate) fn html_escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+/// Series colours of the HTML report's charts, reused in turn.
+pub(crate) const HTML_PALETTE: [&str; 6] = ["#2563eb", "#dc2626", "#16a34a", "#d97706", "#7c3aed", "#0891b2"];
+
+/// Renders labelled results as one HTML page with inline SVG charts and no
+/// external scripts or styles, so it can be attached to a ticket or mailed.
+/// QPS and errors over time need the per-second series (`intervals`), which
+/// runs record with --html-report.
+pub(crate) fn html_report(title: &str, results: &[(String, BenchmarkResult)]) -> String {
+    let mut html = String::new();
+    let _ = writeln!(html, "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\"><title>qps-bench: {}</title>", html_escape(title));
+    html.push_str("<style>body{font-family:sans-serif;margin:2em;color:#111}table{border-collapse:collapse}");
+    html.push_str("td,th{border:1px solid #ccc;padding:4px 8px;text-align:right}td:first-child,th:first-child{text-align:left}");
+    html.push_str("svg{background:#fafafa;border:1px solid #ddd;margin:0.5em 0}</style></head><body>\n");
+    let _ = writeln!(html, "<h1>qps-bench: {}</h1>", html_escape(title));
+    
+    html.push_str("<h2>Results</h2>\n<table><tr><th>Result</th><th>Mode</th><th>Concurrency</th><th>Duration</th><th>QPS</th>");
+    html.push_str("<th>P50 (ms)</th><th>P95 (ms)</th><th>P99 (ms)</th><th>Mean (ms)</th><th>OK</th><th>Errors</th><th>Timeouts</th></tr>\n");
+    let ms = |us: u64| us as f64 / 1000.0;
+    for (label, result) in results {
+        let _ = writeln!(
+            html,
+            "<tr><td>{}{}</td><td>{}</td><td>{}</td><td>{}s</td><td>{:.2}</td><td>{:.2}</td><td>{:.2}</td><td>{:.2}</td><td>{:.2}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+            html_escape(label), if result.interrupted { " (interrupted)" } else { "" }, html_escape(&result.mode), result.concurrency,
+            result.duration_seconds, result.qps, ms(result.latency_us_p50), ms(result.latency_us_p95), ms(result.latency_us_p99),
+            ms(result.latency_us_mean), result.ok_ops, result.err_ops, result.timeout_ops.unwrap_or(0),
+        );
+    }
+    html.push_str("</table>\n");
+    
+    html.push_str("<h2>Latency distribution</h2>\n");
+    let percentiles: Vec<(String, Vec<(&str, f64)>)> = results
+        .iter()
+        .map(|(label, result)| {
+            let bars = vec![("P50", ms(result.latency_us_p50)), ("P95", ms(result.latency_us_p95)), ("P99", ms(result.latency_us_p99))];
+            (label.clone(), bars)
+        })
+        .collect();
+    html.push_str(&svg_bar_chart(&percentiles, "ms"));
+    
+    let qps: Vec<(String, Vec<(f64, f64)>)> = results
+        .iter()
+        .filter(|(_, result)| !result.intervals.is_empty())
+        .map(|(label, result)| (label.clone(), result.intervals.iter().map(|i| (i.start_seconds, i.qps)).collect()))
+        .collect();
+    if qps.is_empty() {
+        html.push_str("<p>No per-second series in these results: run with --html-report to record QPS and errors over time.</p>\n");
+    } else {
+        let errors: Vec<(String, Vec<(f64, f64)>)> = results
+            .iter()
+            .filter(|(_, result)| !result.intervals.is_empty())
+            .map(|(label, result)| (label.clone(), result.intervals.iter().map(|i| (i.start_seconds, i.err_ops as f64)).collect()))
+            .collect();
+        html.push_str("<h2>QPS over time</h2>\n");
+        html.push_str(&svg_line_chart(&qps, "QPS"));
+        let rolling_p99: Vec<(String, Vec<(f64, f64)>)> = results
+            .iter()
+            .filter(|(_, result)| !result.intervals.is_empty())
+            .map(|(label, result)| (label.clone(), result.intervals.iter().map(|i| (i.start_seconds, ms(i.window_latency_us_p99))).collect()))
+            .collect();
+        html.push_str(&format!("<h2>P99 over rolling {}s windows</h2>\n", PERCENTILE_WINDOW.as_secs()));
+        html.push_str(&svg_line_chart(&rolling_p99, "ms"));
+        html.push_str("<h2>Errors over time</h2>\n");
+        html.push_str(&svg_line_chart(&errors, "errors/s"));
+    }
+    
+    let mut errors = String::new();
+    for (label, result) in results {
+        for (verb, latency) in result.verbs.iter().filter(|(_, latency)| latency.err_ops > 0) {
+            let total = latency.ok_ops + latency.err_ops;
+            let _ = writeln!(errors, "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{:.2}%</td></tr>",
+                             html_escape(label), verb, latency.err_ops, total, latency.err_ops as f64 / total as f64 * 100.0);
+        }
+    }
+    if !errors.is_empty() {
+        html.push_str("<h2>Failed requests</h2>\n<table><tr><th>Result</th><th>Method</th><th>Failed</th><th>Requests</th><th>Error rate</th></tr>\n");
+        html.push_str(&errors);
+        html.push_str("</table>\n");
+    }
+    html.push_str("</body></html>\n");
+    html
+}
+
+/// Chart area of the HTML report's SVGs, inside margins for the axis labels.
+pub(crate) const CHART_WIDTH: f64 = 720.0;
+pub(crate) const CHART_HEIGHT: f64 = 240.0;
+pub(crate) const CHART_MARGIN: f64 = 50.0;
+
+/// Legend under a chart, one swatch per series.
+pub(crate) fn svg_legend(labels: &[&str], y: f64) -> String {
+    let mut svg = String::new();
+    for (index, label) in labels.iter().enumerate() {
+        let x = CHART_MARGIN + (index % 4) as f64 * 180.0;
+        let y = y + (index / 4) as f64 * 16.0;
+        let colour = HTML_PALETTE[index % HTML_PALETTE.len()];
+        let _ = write!(svg, "<rect x=\"{}\" y=\"{}\" width=\"10\" height=\"10\" fill=\"{}\"/>", x, y - 9.0, colour);
+        let _ = write!(svg, "<text x=\"{}\" y=\"{}\" font-size=\"11\">{}</text>", x + 14.0, y, html_escape(label));
+    }
+    svg
+}
+
+/// Grouped bars: one group per entry, one bar per (name, value) in it.
+pub(crate) fn svg_bar_chart(groups: &[(String, Vec<(&str, f64)>)], unit: &str) -> String {
+    let max = groups.iter().flat_map(|(_, bars)| bars.iter().map(|(_, value)| *value)).fold(0.0_f64, f64::max).max(f64::MIN_POSITIVE);
+    let names: Vec<&str> = groups.first().map(|(_, bars)| bars.iter().map(|(name, _)| *name).collect()).unwrap_or_default();
+    let height = CHART_HEIGHT + 2.0 * CHART_MARGIN + 16.0 * names.len().div_ceil(4) as f64;
+    let mut svg = format!("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\">",
+                          CHART_WIDTH + 2.0 * CHART_MARGIN, height);
+    let _ = write!(svg, "<text x=\"4\" y=\"{}\" font-size=\"11\">{:.2} {}</text>", CHART_MARGIN, max, unit);
+    let group_width = CHART_WIDTH / groups.len().max(1) as f64;
+    for (index, (label, bars)) in groups.iter().enumerate() {
+        let bar_width = group_width * 0.8 / bars.len().max(1) as f64;
+        for (bar, (_, value)) in bars.iter().enumerate() {
+            let bar_height = value / max * CHART_HEIGHT;
+            let x = CHART_MARGIN + index as f64 * group_width + group_width * 0.1 + bar as f64 * bar_width;
+            let _ = write!(svg, "<rect x=\"{:.1}\" y=\"{:.1}\" width=\"{:.1}\" height=\"{:.1}\" fill=\"{}\"><title>{:.2} {}</title></rect>",
+                           x, CHART_MARGIN + CHART_HEIGHT - bar_height, bar_width, bar_height, HTML_PALETTE[bar % HTML_PALETTE.len()], value, unit);
+        }
+        let _ = write!(svg, "<text x=\"{:.1}\" y=\"{}\" font-size=\"11\" text-anchor=\"middle\">{}</text>",
+                       CHART_MARGIN + (index as f64 + 0.5) * group_width, CHART_MARGIN + CHART_HEIGHT + 14.0, html_escape(label));
+    }
+    svg.push_str(&svg_legend(&names, CHART_MARGIN + CHART_HEIGHT + 40.0));
+    svg.push_str("</svg>\n");
+    svg
+}
+
+/// One line per series of (seconds, value) points, on shared axes.
+pub(crate) fn svg_line_chart(series: &[(String, Vec<(f64, f64)>)], unit: &str) -> String {
+    let points = || series.iter().flat_map(|(_, points)| points.iter());
+    let max_x = points().map(|(x, _)| *x).fold(0.0_f64, f64::max).max(1.0);
+    let max_y = points().map(|(_, y)| *y).fold(0.0_f64, f64::max).max(1.0);
+    let height = CHART_HEIGHT + 2.0 * CHART_MARGIN + 16.0 * series.len().div_ceil(4) as f64;
+    let mut svg = format!("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\">",
+                          CHART_WIDTH + 2.0 * CHART_MARGIN, height);
+    let _ = write!(svg, "<text x=\"4\" y=\"{}\" font-size=\"11\">{:.0} {}</text>", CHART_MARGIN, max_y, unit);
+    let _ = write!(svg, "<text x=\"{}\" y=\"{}\" font-size=\"11\" text-anchor=\"end\">{:.0}s</text>",
+                   CHART_MARGIN + CHART_WIDTH, CHART_MARGIN + CHART_HEIGHT + 14.0, max_x);
+    let _ = write!(svg, "<line x1=\"{m}\" y1=\"{b}\" x2=\"{r}\" y2=\"{b}\" stroke=\"#999\"/>",
+                   m = CHART_MARGIN, b = CHART_MARGIN + CHART_HEIGHT, r = CHART_MARGIN + CHART_WIDTH);
+    for (index, (_, points)) in series.iter().enumerate() {
+        let path: Vec<String> = points
+            .iter()
+            .map(|(x, y)| format!("{:.1},{:.1}", CHART_MARGIN + x / max_x * CHART_WIDTH, CHART_MARGIN + CHART_HEIGHT - y / max_y * CHART_HEIGHT))
+            .collect();
+        let _ = write!(svg, "<polyline fill=\"none\" stroke-width=\"1.5\" stroke=\"{}\" points=\"{}\"/>",
+                       HTML_PALETTE[index % HTML_PALETTE.len()], path.join(" "));
+    }
+    let labels: Vec<&str> = series.iter().map(|(label, _)| label.as_str()).collect();
+    svg.push_str(&svg_legend(&labels, CHART_MARGIN + CHART_HEIGHT + 40.0));
+    svg.push_str("</svg>\n");
+    svg
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn trend_run(qps: f64, latency_us_p99: u64) -> TrendRun {
+        TrendRun { label: "stat".to_string(), recorded_at_unix_ms: 0, qps, latency_us_p99, err_ops: 0 }
+    }
+
+    #[test]
+    fn trend_regression_flags_qps_and_p99_beyond_the_threshold() {
+        let previous = [trend_run(1000.0, 10_000), trend_run(1200.0, 14_000)];
+        let previous: Vec<&TrendRun> = previous.iter().collect();
+        // Average 1100 QPS and 12 ms P99; 10% either way is still fine
+        assert_eq!(trend_regression(&previous, &trend_run(990.0, 13_200), 10.0), None);
+        assert_eq!(
+            trend_regression(&previous, &trend_run(880.0, 12_000), 10.0).as_deref(),
+            Some("QPS 20.0% below the 2-run average")
+        );
+        assert_eq!(
+            trend_regression(&previous, &trend_run(1100.0, 18_000), 10.0).as_deref(),
+            Some("P99 50.0% above the 2-run average")
+        );
+        assert_eq!(
+            trend_regression(&previous, &trend_run(550.0, 24_000), 10.0).as_deref(),
+            Some("QPS 50.0% below, P99 100.0% above the 2-run average")
+        );
+    }
+
+    #[test]
+    fn trend_regression_needs_earlier_runs() {
+        assert_eq!(trend_regression(&[], &trend_run(1.0, 1_000_000), 10.0), None);
+    }
+}
@@ -0,0 +1,1181 @@
+//! Where results and live measurements go besides the console: the result
+//! sinks, SLA checks, the op log, event sinks and metrics exporters.
+
+use super::*;
+
+/// Lines queued for the --op-log writer thread.
+pub(crate) struct OpLog {
+    /// `None` asks the writer to flush and stop
+    pub(crate) sender: std::sync::mpsc::SyncSender<Option<String>>,
+    pub(crate) dropped: std::sync::atomic::AtomicU64,
+}
+
+/// Lines buffered for --op-log before new ones are dropped (and counted)
+/// rather than slowing ops down or growing memory without bound.
+pub(crate) const OP_LOG_BUFFER: usize = 65_536;
+
+/// One --op-log line.
+#[derive(Serialize)]
+pub(crate) struct OpLogRecord<'a> {
+    unix_us: u64,
+    op: &'static str,
+    key: &'a str,
+    latency_us: u64,
+    bytes: u64,
+    result: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+/// A request on its way to --op-log. One dropped before it finished (cut
+/// off by --op-timeout-ms) is logged as a timeout.
+pub(crate) struct OpLogEntry<'a> {
+    verb: &'static str,
+    key: &'a str,
+    unix_us: u64,
+    start: Instant,
+    finished: bool,
+}
+
+impl<'a> OpLogEntry<'a> {
+    /// None unless --op-log is set and this request belongs to a benchmark op
+    /// (not dataset preparation, churn or cleanup).
+    pub(crate) fn start(verb: &'static str, key: &'a str) -> Option<Self> {
+        if !with_run(|run| run.op_log.get().is_some()) {
+            return None;
+        }
+        OP_CONTEXT.try_with(|_| ()).ok()?;
+        let unix_us = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap_or_default().as_micros() as u64;
+        Some(OpLogEntry { verb, key, unix_us, start: Instant::now(), finished: false })
+    }
+
+    /// Drops the entry without logging it, for a request that never went out.
+    pub(crate) fn discard(mut self) {
+        self.finished = true;
+    }
+
+    pub(crate) fn finish(mut self, latency_us: u64, bytes: u64, error: Option<&opendal::Error>) {
+        self.finished = true;
+        let (result, error) = match error {
+            None => ("ok", None),
+            Some(e) => ("error", Some(e.kind().to_string())),
+        };
+        self.write(latency_us, bytes, result, error);
+    }
+
+    fn write(&self, latency_us: u64, bytes: u64, result: &'static str, error: Option<String>) {
+        let record = OpLogRecord { unix_us: self.unix_us, op: self.verb, key: self.key, latency_us, bytes, result, error };
+        let Ok(line) = serde_json::to_string(&record) else {
+            return;
+        };
+        with_run(|run| {
+            if let Some(log) = run.op_log.get() {
+                if log.sender.try_send(Some(line)).is_err() {
+                    log.dropped.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+                }
+            }
+        });
+    }
+}
+
+impl Drop for OpLogEntry<'_> {
+    fn drop(&mut self) {
+        if !self.finished {
+            self.write(self.start.elapsed().as_micros() as u64, 0, "timeout", None);
+        }
+    }
+}
+
+/// Opens --op-log and starts the thread that writes it, off the runtime's
+/// worker threads. Send `None` and join the thread to flush the log.
+pub(crate) fn start_op_log(path: &std::path::Path) -> Result<std::thread::JoinHandle<std::io::Result<()>>> {
+    let file = std::fs::File::create(path).map_err(|e| anyhow::anyhow!("Failed to create op log {}: {}", path.display(), e))?;
+    let (sender, receiver) = std::sync::mpsc::sync_channel::<Option<String>>(OP_LOG_BUFFER);
+    if current_run()?.op_log.set(OpLog { sender, dropped: Default::default() }).is_err() {
+        anyhow::bail!("The op log is already open");
+    }
+    Ok(std::thread::spawn(move || {
+        use std::io::Write;
+        let mut writer = std::io::BufWriter::with_capacity(1 << 20, file);
+        while let Ok(Some(line)) = receiver.recv() {
+            writer.write_all(line.as_bytes())?;
+            writer.write_all(b"\n")?;
+        }
+        writer.flush()
+    }))
+}
+
+/// A completed op, queued for --event-sink and published as one JSON record.
+#[derive(Serialize)]
+pub(crate) struct OpEvent {
+    #[serde(rename = "ts")]
+    unix_ms: u64,
+    /// The mode the op ran under, captured when the run started it
+    mode: Arc<str>,
+    /// Verb of the op's first request (GET, PUT, HEAD, ...)
+    op: &'static str,
+    /// Object key of that request; Kafka records are partitioned by it
+    key: String,
+    latency_us: u64,
+    status: &'static str,
+}
+
+/// Queue feeding the --event-sink publisher.
+pub(crate) struct OpEventQueue {
+    sender: tokio::sync::mpsc::Sender<OpEvent>,
+    pub(crate) dropped: std::sync::atomic::AtomicU64,
+}
+
+/// Events published per NATS write or Kafka produce request.
+pub(crate) const EVENT_BATCH: usize = 1000;
+
+/// Queues an event for the completed op, if --event-sink is set. Never waits:
+/// when the publisher falls behind, events are dropped and counted instead of
+/// slowing the benchmark down.
+pub(crate) fn publish_op_event(mode: &Arc<str>, latency_us: u64, status: &'static str) {
+    with_run(|run| {
+        let Some(queue) = run.op_events.get() else {
+            return;
+        };
+        let unix_ms = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap_or_default().as_millis() as u64;
+        let (op, key) = OP_CONTEXT.try_with(|context| context.request.take()).ok().flatten().unwrap_or(("", String::new()));
+        if queue.sender.try_send(OpEvent { unix_ms, mode: mode.clone(), op, key, latency_us, status }).is_err() {
+            queue.dropped.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        }
+    });
+}
+
+/// Where --event-sink publishes.
+pub(crate) enum EventTransport {
+    /// Core NATS: one PUB per event. The writer is shared with the task that
+    /// answers the server's PINGs.
+    Nats {
+        writer: Arc<tokio::sync::Mutex<tokio::net::tcp::OwnedWriteHalf>>,
+        subject: String,
+    },
+    /// Every partition of the topic; each record goes to the one its key hashes to
+    #[cfg(feature = "kafka")]
+    Kafka(Vec<rskafka::client::partition::PartitionClient>),
+}
+
+impl EventTransport {
+    async fn connect(url: &str) -> Result<Self> {
+        let invalid = |reason: &str| RunFailure::new(FailureCategory::Config, format!("Invalid --event-sink {}: {}", url, reason));
+        let (scheme, rest) = url.split_once("://").ok_or_else(|| invalid("expected nats://HOST:PORT/SUBJECT or kafka://BROKER:PORT/TOPIC"))?;
+        let (addr, name) = rest.split_once('/').unwrap_or((rest, ""));
+        match scheme {
+            "nats" => {
+                use tokio::io::{AsyncBufReadExt, AsyncWriteExt};
+                
+                let stream = tokio::net::TcpStream::connect(addr)
+                    .await
+                    .map_err(|e| RunFailure::new(FailureCategory::Connectivity, format!("Failed to connect to NATS at {}: {}", addr, e)))?;
+                let (reader, mut writer) = stream.into_split();
+                let mut reader = tokio::io::BufReader::new(reader);
+                let mut info = String::new();
+                reader.read_line(&mut info).await?;
+                if !info.starts_with("INFO") {
+                    anyhow::bail!("Unexpected NATS greeting from {}: {}", addr, info.trim());
+                }
+                writer.write_all(b"CONNECT {\"verbose\":false,\"pedantic\":false,\"name\":\"qps-bench\"}\r\n").await?;
+                let writer = Arc::new(tokio::sync::Mutex::new(writer));
+                let ponger = writer.clone();
+                spawn_in_run(async move {
+                    let mut line = String::new();
+                    while reader.read_line(&mut line).await.is_ok_and(|len| len > 0) {
+                        if line.starts_with("PING") {
+                            let _ = ponger.lock().await.write_all(b"PONG\r\n").await;
+                        } else if line.starts_with("-ERR") {
+                            tracing::warn!(error = line.trim(), "NATS server error");
+                        }
+                        line.clear();
+                    }
+                });
+                let subject = if name.is_empty() { "qps-bench.ops" } else { name };
+                tracing::info!("📡 Publishing op events to NATS subject {} at {}", subject, addr);
+                Ok(EventTransport::Nats { writer, subject: subject.to_string() })
+            }
+            #[cfg(feature = "kafka")]
+            "kafka" => {
+                let topic = if name.is_empty() { "qps-bench-ops" } else { name };
+                let client = rskafka::client::ClientBuilder::new(vec![addr.to_string()])
+                    .build()
+                    .await
+                    .map_err(|e| RunFailure::new(FailureCategory::Connectivity, format!("Failed to connect to Kafka at {}: {}", addr, e)))?;
+                // A topic the broker doesn't know yet is auto-created with one partition
+                let partitions = client
+                    .list_topics()
+                    .await?
+                    .into_iter()
+                    .find(|t| t.name == topic)
+                    .map_or_else(|| vec![0], |t| t.partitions.into_iter().collect());
+                let mut clients = Vec::with_capacity(partitions.len());
+                for partition in partitions {
+                    clients.push(client.partition_client(topic.to_string(), partition, rskafka::client::partition::UnknownTopicHandling::Retry).await?);
+                }
+                tracing::info!("📡 Publishing op events to Kafka topic {} ({} partitions) at {}", topic, clients.len(), addr);
+                Ok(EventTransport::Kafka(clients))
+            }
+            #[cfg(not(feature = "kafka"))]
+            "kafka" => Err(RunFailure::new(FailureCategory::Config, "kafka:// event sinks require building with the `kafka` cargo feature").into()),
+            _ => Err(invalid("expected a nats:// or kafka:// URL").into()),
+        }
+    }
+
+    async fn publish(&self, events: &[OpEvent]) -> Result<()> {
+        match self {
+            EventTransport::Nats { writer, subject } => {
+                use tokio::io::AsyncWriteExt;
+                
+                let mut buffer = String::new();
+                for event in events {
+                    let payload = serde_json::to_string(event)?;
+                    let _ = write!(buffer, "PUB {} {}\r\n{}\r\n", subject, payload.len(), payload);
+                }
+                writer.lock().await.write_all(buffer.as_bytes()).await?;
+            }
+            #[cfg(feature = "kafka")]
+            EventTransport::Kafka(partitions) => {
+                let mut batches: Vec<Vec<rskafka::record::Record>> = partitions.iter().map(|_| Vec::new()).collect();
+                for event in events {
+                    let partition = (fnv1a(event.key.as_bytes()) % partitions.len() as u64) as usize;
+                    batches[partition].push(rskafka::record::Record {
+                        key: Some(event.key.clone().into_bytes()),
+                        value: Some(serde_json::to_vec(event)?),
+                        headers: BTreeMap::new(),
+                        timestamp: chrono::DateTime::from_timestamp_millis(event.unix_ms as i64).unwrap_or_default(),
+                    });
+                }
+                for (partition, records) in partitions.iter().zip(batches) {
+                    if !records.is_empty() {
+                        partition.produce(records, rskafka::client::partition::Compression::NoCompression).await?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Connects to --event-sink and starts publishing queued op events in
+/// batches. Send on the returned channel to publish what is still queued and
+/// stop.
+pub(crate) async fn start_event_sink(url: &str, buffer: usize) -> Result<(tokio::sync::oneshot::Sender<()>, tokio::task::JoinHandle<()>)> {
+    let transport = EventTransport::connect(url).await?;
+    let (sender, mut events) = tokio::sync::mpsc::channel(buffer.max(1));
+    if current_run()?.op_events.set(OpEventQueue { sender, dropped: Default::default() }).is_err() {
+        anyhow::bail!("--event-sink is already running");
+    }
+    // Tracks the running mode, which each op event is stamped with
+    live_metrics();
+    let (stop, mut stopped) = tokio::sync::oneshot::channel();
+    let publisher = spawn_in_run(async move {
+        let mut batch = Vec::with_capacity(EVENT_BATCH);
+        loop {
+            let stopping = tokio::select! {
+                received = events.recv_many(&mut batch, EVENT_BATCH) => received == 0,
+                _ = &mut stopped => true,
+            };
+            if stopping {
+                while let Ok(event) = events.try_recv() {
+                    batch.push(event);
+                }
+            }
+            for chunk in batch.chunks(EVENT_BATCH) {
+                if let Err(e) = transport.publish(chunk).await {
+                    tracing::warn!(events = chunk.len(), error = %e, "Failed to publish op events");
+                }
+            }
+            batch.clear();
+            if stopping {
+                break;
+            }
+        }
+    });
+    Ok((stop, publisher))
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct SlaCheck {
+    pub(crate) label: String,
+    pub(crate) metric: &'static str,
+    pub(crate) threshold: f64,
+    pub(crate) actual: f64,
+    #[serde(skip)]
+    pub(crate) passed: bool,
+}
+
+/// Evaluates the `--sla-*` thresholds and `[[sla]]` targets against every
+/// result in the document, passing or not.
+pub(crate) fn check_sla(args: &Args, results: &serde_json::Value) -> Result<Vec<SlaCheck>> {
+    let mut checks = Vec::new();
+    let mut check = |label: &str, metric: &'static str, threshold: Option<f64>, actual: f64| {
+        if let Some(threshold) = threshold {
+            checks.push(SlaCheck { label: label.to_string(), metric, threshold, actual, passed: actual <= threshold });
+        }
+    };
+    let error_pct = |failed: u64, ok: u64| if failed + ok == 0 { 0.0 } else { failed as f64 / (failed + ok) as f64 * 100.0 };
+    let ms = |us: u64| us as f64 / 1000.0;
+    let mut matched = vec![false; args.sla.len()];
+    for (label, result) in labelled_results(results)? {
+        let failed = result.err_ops + result.timeout_ops.unwrap_or(0);
+        check(&label, "latency_p99_ms", args.sla_p99_ms, ms(result.latency_us_p99));
+        check(&label, "error_pct", args.sla_max_error_pct, error_pct(failed, result.ok_ops));
+        for (target, matched) in args.sla.iter().zip(matched.iter_mut()) {
+            let (label, p50, p95, p99, errors) = if target.op == result.mode {
+                (label.clone(), result.latency_us_p50, result.latency_us_p95, result.latency_us_p99, error_pct(failed, result.ok_ops))
+            } else if let Some(verb) = result.verbs.get(&target.op.to_ascii_uppercase()) {
+                (format!("{} {}", label, target.op.to_ascii_uppercase()), verb.latency_us_p50, verb.latency_us_p95, verb.latency_us_p99,
+                 error_pct(verb.err_ops, verb.ok_ops))
+            } else {
+                continue;
+            };
+            *matched = true;
+            check(&label, "latency_p50_ms", target.p50_ms, ms(p50));
+            check(&label, "latency_p95_ms", target.p95_ms, ms(p95));
+            check(&label, "latency_p99_ms", target.p99_ms, ms(p99));
+            check(&label, "error_pct", target.max_error_pct, errors);
+        }
+    }
+    for (target, _) in args.sla.iter().zip(&matched).filter(|(_, matched)| !**matched) {
+        tracing::warn!("⚠️  SLA target for '{}' matched no result; it was not checked", target.op);
+    }
+    Ok(checks)
+}
+
+/// Escapes a GitHub Actions workflow command message (or, with `property`, a
+/// property value such as a title).
+pub(crate) fn gha_escape(text: &str, property: bool) -> String {
+    let escaped = text.replace('%', "%25").replace('\r', "%0D").replace('\n', "%0A");
+    if property {
+        escaped.replace(':', "%3A").replace(',', "%2C")
+    } else {
+        escaped
+    }
+}
+
+/// Prints `::notice`/`::error` annotations for the results and SLA breaches,
+/// and appends a markdown table of the results to the job summary.
+pub(crate) fn report_github_actions(results: &serde_json::Value, breaches: &[SlaCheck]) -> Result<()> {
+    let labelled = labelled_results(results)?;
+    let mut summary = String::from("### qps-bench results\n\n");
+    summary.push_str("| Result | Mode | Concurrency | QPS | P50 (ms) | P99 (ms) | Errors |\n");
+    summary.push_str("|---|---|---:|---:|---:|---:|---:|\n");
+    for (label, result) in &labelled {
+        let p50 = result.latency_us_p50 as f64 / 1000.0;
+        let p99 = result.latency_us_p99 as f64 / 1000.0;
+        println!(
+            "::notice title={}::{:.2} QPS, P50 {:.2} ms, P99 {:.2} ms, {} ok / {} errors",
+            gha_escape(&format!("qps-bench {}", label), true), result.qps, p50, p99, result.ok_ops, result.err_ops,
+        );
+        let _ = writeln!(
+            summary,
+            "| {} | {} | {} | {:.2} | {:.2} | {:.2} | {} |",
+            label, result.mode, result.concurrency, result.qps, p50, p99, result.err_ops,
+        );
+    }
+    for breach in breaches {
+        let message = format!("{} {} = {:.2} (limit {:.2})", breach.label, breach.metric, breach.actual, breach.threshold);
+        println!("::error title=SLA breach::{}", gha_escape(&message, false));
+        let _ = writeln!(summary, "\n❌ SLA breach: {}", message);
+    }
+    if stopped_early() {
+        println!("::warning title=qps-bench::Run interrupted; results are partial");
+    }
+    
+    // Only set inside a workflow step
+    let Some(path) = std::env::var_os("GITHUB_STEP_SUMMARY") else {
+        return Ok(());
+    };
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .map_err(|e| anyhow::anyhow!("Failed to open job summary {}: {}", path.to_string_lossy(), e))?;
+    std::io::Write::write_all(&mut file, summary.as_bytes())?;
+    Ok(())
+}
+
+/// The headline numbers of every result, for compact notifications.
+pub(crate) fn webhook_summary(results: &serde_json::Value) -> Result<Vec<serde_json::Value>> {
+    Ok(labelled_results(results)?
+        .into_iter()
+        .map(|(label, result)| serde_json::json!({
+            "result": label,
+            "mode": result.mode,
+            "concurrency": result.concurrency,
+            "qps": result.qps,
+            "latency_ms_p99": result.latency_us_p99 as f64 / 1000.0,
+            "ok_ops": result.ok_ops,
+            "err_ops": result.err_ops,
+        }))
+        .collect())
+}
+
+/// Posts `payload` to the webhook. Failures are only warned about so a
+/// flaky receiver never fails a finished benchmark.
+#[cfg(feature = "http-sinks")]
+pub(crate) async fn notify_webhook(url: &str, payload: &serde_json::Value) {
+    let response = reqwest::Client::new()
+        .post(url)
+        .timeout(Duration::from_secs(10))
+        .json(payload)
+        .send()
+        .await;
+    match response {
+        Ok(response) if response.status().is_success() => {
+            tracing::info!("📨 Posted {} notification to webhook", payload["event"].as_str().unwrap_or("run"));
+        }
+        Ok(response) => tracing::warn!(status = %response.status(), "Webhook rejected the notification"),
+        Err(e) => tracing::warn!(error = %e, "Failed to post webhook notification"),
+    }
+}
+
+/// Pushgateway URL of the group for this run's job and instance labels.
+pub(crate) fn pushgateway_group_url(args: &Args, base: &str) -> String {
+    let instance = args.pushgateway_instance.clone().or_else(hostname).unwrap_or_else(|| "unknown".to_string());
+    format!("{}/metrics/job/{}/instance/{}", base.trim_end_matches('/'), args.pushgateway_job, instance)
+}
+
+/// Without the `http-sinks` feature there is no webhook to post to:
+/// `config_conflicts` rejects --webhook-url.
+#[cfg(not(feature = "http-sinks"))]
+pub(crate) async fn notify_webhook(_url: &str, _payload: &serde_json::Value) {}
+
+/// Replaces the run's Pushgateway group with `body`, returning whether it
+/// worked. Failures are only warned about so a monitoring outage doesn't fail
+/// the benchmark.
+#[cfg(feature = "http-sinks")]
+pub(crate) async fn push_metrics(url: &str, body: String) -> bool {
+    let response = reqwest::Client::new()
+        .put(url)
+        .timeout(Duration::from_secs(10))
+        .header("Content-Type", "text/plain; version=0.0.4")
+        .body(body)
+        .send()
+        .await;
+    match response {
+        Ok(response) if response.status().is_success() => return true,
+        Ok(response) => tracing::warn!(status = %response.status(), "Pushgateway rejected the metrics"),
+        Err(e) => tracing::warn!(error = %e, "Failed to push metrics"),
+    }
+    false
+}
+
+/// Without the `http-sinks` feature there is no Pushgateway to push to:
+/// `config_conflicts` rejects --pushgateway-url.
+#[cfg(not(feature = "http-sinks"))]
+pub(crate) async fn push_metrics(_url: &str, _body: String) -> bool {
+    false
+}
+
+/// Escapes a Prometheus label value.
+pub(crate) fn prometheus_label(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+/// Builds a Grafana dashboard over the exported metric names: live QPS, error
+/// rate, latency percentiles and in-flight ops from the /metrics endpoint (or
+/// an OpenTelemetry collector's Prometheus exporter, which maps the OTLP names
+/// to the same ones), and the final results pushed to a Pushgateway. Queries
+/// are filtered by `job` and `instance` template variables.
+pub(crate) fn grafana_dashboard(title: &str) -> serde_json::Value {
+    let selector = |extra: &str| format!("{{job=~\"$job\",instance=~\"$instance\"{}}}", extra);
+    let rate = |metric: &str, extra: &str| format!("sum(rate({}{}[$__rate_interval]))", metric, selector(extra));
+    let latency = |quantile: f64| {
+        format!("histogram_quantile({}, sum by (le) (rate(qps_bench_op_latency_seconds_bucket{}[$__rate_interval])))", quantile, selector(""))
+    };
+    let target = |expr: String, legend: &str| serde_json::json!({
+        "datasource": { "type": "prometheus", "uid": "${datasource}" },
+        "expr": expr,
+        "legendFormat": legend,
+    });
+    let panels = [
+        ("QPS", "timeseries", "ops", vec![
+            target(rate("qps_bench_ops_total", ",result=\"ok\""), "ok"),
+            target(rate("qps_bench_ops_total", ",result=\"error\""), "error"),
+        ]),
+        ("Error rate", "timeseries", "percent", vec![
+            target(format!("100 * {} / {}", rate("qps_bench_ops_total", ",result=\"error\""), rate("qps_bench_ops_total", "")), "errors"),
+        ]),
+        ("Latency", "timeseries", "s", vec![
+            target(latency(0.5), "p50"),
+            target(latency(0.95), "p95"),
+            target(latency(0.99), "p99"),
+        ]),
+        ("In-flight ops", "timeseries", "short", vec![
+            target(format!("sum(qps_bench_in_flight_ops{})", selector("")), "in flight"),
+        ]),
+        ("Final QPS (Pushgateway)", "stat", "ops", vec![
+            target(format!("qps_bench_result_qps{}", selector("")), "{{result}} {{mode}}"),
+        ]),
+        ("Final P99 (Pushgateway)", "stat", "s", vec![
+            target(format!("qps_bench_result_latency_p99_seconds{}", selector("")), "{{result}} {{mode}}"),
+        ]),
+    ];
+    let panels: Vec<serde_json::Value> = panels
+        .into_iter()
+        .enumerate()
+        .map(|(index, (panel_title, kind, unit, targets))| serde_json::json!({
+            "id": index + 1,
+            "title": panel_title,
+            "type": kind,
+            "datasource": { "type": "prometheus", "uid": "${datasource}" },
+            "fieldConfig": { "defaults": { "unit": unit }, "overrides": [] },
+            "gridPos": { "h": 8, "w": 12, "x": (index % 2) * 12, "y": (index / 2) * 8 },
+            "targets": targets,
+        }))
+        .collect();
+    let variable = |name: &str| serde_json::json!({
+        "name": name,
+        "type": "query",
+        "datasource": { "type": "prometheus", "uid": "${datasource}" },
+        "query": format!("label_values(qps_bench_ops_total, {})", name),
+        "refresh": 2,
+        "includeAll": true,
+        "multi": true,
+        "current": { "text": "All", "value": "$__all" },
+    });
+    serde_json::json!({
+        "title": title,
+        "uid": null,
+        "tags": ["qps-bench"],
+        "timezone": "browser",
+        "schemaVersion": 39,
+        "time": { "from": "now-1h", "to": "now" },
+        "refresh": "10s",
+        "templating": {
+            "list": [
+                { "name": "datasource", "type": "datasource", "query": "prometheus" },
+                variable("job"),
+                variable("instance"),
+            ],
+        },
+        "panels": panels,
+    })
+}
+
+pub(crate) fn write_grafana_dashboard(args: GrafanaDashboardArgs) -> Result<()> {
+    let text = serde_json::to_string_pretty(&grafana_dashboard(&args.title))?;
+    match &args.output {
+        Some(path) => {
+            std::fs::write(path, text).map_err(|e| anyhow::anyhow!("Failed to write {}: {}", path.display(), e))?;
+            tracing::info!("📝 Wrote Grafana dashboard to {}", path.display());
+        }
+        None => println!("{}", text),
+    }
+    Ok(())
+}
+
+/// A pushed result gauge: (metric name, help text, value read from the result).
+pub(crate) type ResultMetric = (&'static str, &'static str, fn(&BenchmarkResult) -> f64);
+
+/// Renders every result in `results` as Prometheus gauges labelled by result and mode.
+pub(crate) fn render_result_metrics(results: &serde_json::Value) -> Result<String> {
+    let metrics: [ResultMetric; 6] = [
+        ("qps_bench_result_qps", "Successful operations per second.", |r| r.qps),
+        ("qps_bench_result_ok_ops", "Successful operations.", |r| r.ok_ops as f64),
+        ("qps_bench_result_err_ops", "Failed operations.", |r| r.err_ops as f64),
+        ("qps_bench_result_latency_p50_seconds", "Median latency.", |r| r.latency_us_p50 as f64 / 1_000_000.0),
+        ("qps_bench_result_latency_p95_seconds", "95th percentile latency.", |r| r.latency_us_p95 as f64 / 1_000_000.0),
+        ("qps_bench_result_latency_p99_seconds", "99th percentile latency.", |r| r.latency_us_p99 as f64 / 1_000_000.0),
+    ];
+    let results = labelled_results(results)?;
+    let mut text = String::new();
+    for (name, help, value) in metrics {
+        text.push_str(&format!("# HELP {} {}\n# TYPE {} gauge\n", name, help, name));
+        for (label, result) in &results {
+            text.push_str(&format!("{}{{result=\"{}\",mode=\"{}\"}} {}\n",
+                                   name, prometheus_label(label), prometheus_label(&result.mode), value(result)));
+        }
+    }
+    Ok(text)
+}
+
+#[cfg(feature = "otlp-metrics")]
+pub(crate) fn unix_nanos(time: std::time::SystemTime) -> String {
+    time.duration_since(std::time::UNIX_EPOCH).unwrap_or_default().as_nanos().to_string()
+}
+
+#[cfg(feature = "otlp-metrics")]
+pub(crate) fn otlp_attribute(key: &str, value: &str) -> serde_json::Value {
+    serde_json::json!({ "key": key, "value": { "stringValue": value } })
+}
+
+/// Renders the live metrics as an OTLP/HTTP JSON export request: cumulative op
+/// counts and latency histogram, plus the in-flight gauge. The run's target
+/// and workload are resource attributes; the current mode is a point attribute.
+#[cfg(feature = "otlp-metrics")]
+pub(crate) fn render_otlp_metrics(args: &Args, live: &LiveMetrics) -> serde_json::Value {
+    let load = |counter: &std::sync::atomic::AtomicU64| counter.load(std::sync::atomic::Ordering::Relaxed);
+    let start = unix_nanos(live.started);
+    let now = unix_nanos(std::time::SystemTime::now());
+    let mode = otlp_attribute("mode", &live.mode.lock().unwrap());
+    let bucket_counts: Vec<String> = live.buckets.iter().map(|count| load(count).to_string()).collect();
+    let count: u64 = live.buckets.iter().map(load).sum();
+    
+    let mut resource = vec![
+        otlp_attribute("service.name", "qps-bench"),
+        otlp_attribute("service.version", env!("CARGO_PKG_VERSION")),
+        otlp_attribute("qps_bench.endpoint", &args.backend.endpoint),
+        otlp_attribute("qps_bench.bucket", &args.backend.bucket),
+        otlp_attribute("qps_bench.mode", &args.mode),
+        otlp_attribute("qps_bench.concurrency", &args.concurrency.to_string()),
+    ];
+    if let Some(host) = hostname() {
+        resource.push(otlp_attribute("host.name", &host));
+    }
+    
+    serde_json::json!({
+        "resourceMetrics": [{
+            "resource": { "attributes": resource },
+            "scopeMetrics": [{
+                "scope": { "name": "qps-bench", "version": env!("CARGO_PKG_VERSION") },
+                "metrics": [
+                    {
+                        "name": "qps_bench.ops",
+                        "description": "Completed benchmark operations",
+                        "unit": "{operation}",
+                        "sum": {
+                            "aggregationTemporality": 2,
+                            "isMonotonic": true,
+                            "dataPoints": [
+                                { "attributes": [mode.clone(), otlp_attribute("result", "ok")], "startTimeUnixNano": start, "timeUnixNano": now, "asInt": load(&live.ok_ops).to_string() },
+                                { "attributes": [mode.clone(), otlp_attribute("result", "error")], "startTimeUnixNano": start, "timeUnixNano": now, "asInt": load(&live.err_ops).to_string() },
+                            ],
+                        },
+                    },
+                    {
+                        "name": "qps_bench.in_flight_ops",
+                        "description": "Operations currently in flight",
+                        "unit": "{operation}",
+                        "gauge": {
+                            "dataPoints": [
+                                { "attributes": [mode.clone()], "timeUnixNano": now, "asInt": live.in_flight.load(std::sync::atomic::Ordering::Relaxed).to_string() },
+                            ],
+                        },
+                    },
+                    {
+                        "name": "qps_bench.op.latency",
+                        "description": "Latency of successful operations",
+                        "unit": "s",
+                        "histogram": {
+                            "aggregationTemporality": 2,
+                            "dataPoints": [{
+                                "attributes": [mode.clone()],
+                                "startTimeUnixNano": start,
+                                "timeUnixNano": now,
+                                "count": count.to_string(),
+                                "sum": load(&live.latency_sum_us) as f64 / 1_000_000.0,
+                                "bucketCounts": bucket_counts,
+                                "explicitBounds": LIVE_LATENCY_BUCKETS,
+                            }],
+                        },
+                    },
+                ],
+            }],
+        }],
+    })
+}
+
+/// Sends one OTLP export. Failures are only warned about.
+#[cfg(feature = "otlp-metrics")]
+pub(crate) async fn export_otlp(endpoint: &str, body: &serde_json::Value) {
+    let url = format!("{}/v1/metrics", endpoint.trim_end_matches('/'));
+    let response = reqwest::Client::new()
+        .post(&url)
+        .timeout(Duration::from_secs(10))
+        .json(body)
+        .send()
+        .await;
+    match response {
+        Ok(response) if response.status().is_success() => {}
+        Ok(response) => tracing::warn!(status = %response.status(), "OTLP collector rejected the metrics"),
+        Err(e) => tracing::warn!(error = %e, "Failed to export OTLP metrics"),
+    }
+}
+
+/// Escapes an InfluxDB line protocol tag value.
+pub(crate) fn influx_tag(value: &str) -> String {
+    value.replace('\\', "\\\\").replace(',', "\\,").replace('=', "\\=").replace(' ', "\\ ")
+}
+
+/// Renders every result as line protocol: a `qps_bench_interval` point per
+/// interval and a `qps_bench_result` point at the end of the run, tagged with
+/// the result label, mode and target. Timestamps are in milliseconds.
+pub(crate) fn render_influx_lines(args: &Args, results: &serde_json::Value) -> Result<String> {
+    let mut lines = String::new();
+    for (label, result) in labelled_results(results)? {
+        let tags = format!(
+            "result={},mode={},concurrency={},endpoint={},bucket={}",
+            influx_tag(&label), influx_tag(&result.mode), result.concurrency,
+            influx_tag(&args.backend.endpoint), influx_tag(&args.backend.bucket),
+        );
+        let started_ms = result.started_at_unix_ms.unwrap_or_else(|| {
+            // Results without a start time are placed as if they had just finished
+            let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap_or_default();
+            (now.as_millis() as u64).saturating_sub(result.duration_seconds * 1000)
+        });
+        for interval in &result.intervals {
+            let _ = writeln!(
+                lines,
+                "qps_bench_interval,{} qps={},ok_ops={}i,err_ops={}i,latency_us_p50={}i,latency_us_p99={}i,window_latency_us_p50={}i,window_latency_us_p99={}i,throttled_ops={}i,throttled={} {}",
+                tags, interval.qps, interval.ok_ops, interval.err_ops, interval.latency_us_p50, interval.latency_us_p99,
+                interval.window_latency_us_p50, interval.window_latency_us_p99, interval.throttled_ops, interval.throttled,
+                started_ms + (interval.start_seconds * 1000.0) as u64,
+            );
+        }
+        let _ = writeln!(
+            lines,
+            "qps_bench_result,{} qps={},ok_ops={}i,err_ops={}i,latency_us_p50={}i,latency_us_p95={}i,latency_us_p99={}i,latency_us_mean={}i {}",
+            tags, result.qps, result.ok_ops, result.err_ops, result.latency_us_p50, result.latency_us_p95,
+            result.latency_us_p99, result.latency_us_mean, started_ms + result.duration_seconds * 1000,
+        );
+    }
+    Ok(lines)
+}
+
+/// Appends `lines` to --influx-file and writes them to --influx-url. Write
+/// failures to the server are only warned about, like the other sinks.
+pub(crate) async fn write_influx(args: &Args, lines: &str) -> Result<()> {
+    if let Some(path) = &args.influx_file {
+        use std::io::Write as _;
+        std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .and_then(|mut file| file.write_all(lines.as_bytes()))
+            .map_err(|e| anyhow::anyhow!("Failed to write {}: {}", path.display(), e))?;
+        tracing::info!("📝 Appended line protocol to {}", path.display());
+    }
+    #[cfg(feature = "http-sinks")]
+    if let Some(url) = &args.influx_url {
+        let mut request = reqwest::Client::new()
+            .post(format!("{}/api/v2/write", url.trim_end_matches('/')))
+            .query(&[("bucket", args.influx_bucket.as_str()), ("org", args.influx_org.as_str()), ("precision", "ms")])
+            .timeout(Duration::from_secs(10))
+            .body(lines.to_string());
+        if let Some(token) = &args.influx_token {
+            request = request.header("Authorization", format!("Token {}", token));
+        }
+        match request.send().await {
+            Ok(response) if response.status().is_success() => tracing::info!("📤 Wrote results to InfluxDB bucket {}", args.influx_bucket),
+            Ok(response) => tracing::warn!(status = %response.status(), "InfluxDB rejected the write"),
+            Err(e) => tracing::warn!(error = %e, "Failed to write to InfluxDB"),
+        }
+    }
+    Ok(())
+}
+
+/// A finished run, as handed to every ResultSink.
+pub(crate) struct RunOutcome<'a> {
+    pub(crate) args: &'a Args,
+    pub(crate) results: &'a serde_json::Value,
+    pub(crate) breaches: &'a [SlaCheck],
+}
+
+/// Destination for the results of a finished run. Sinks are built from the
+/// flags and --sink before the run starts (so a bad target fails fast) and
+/// written in turn once it is over; a new destination is a ResultSink and a
+/// line in `result_sinks`. Network sinks only warn on failure, file sinks
+/// fail the run.
+pub(crate) trait ResultSink: Send + Sync {
+    fn write<'a>(&'a self, outcome: &'a RunOutcome<'a>) -> BoxFuture<'a, Result<()>>;
+
+    /// Told when the run fails before producing results.
+    fn abort<'a>(&'a self, _args: &'a Args, _error: &'a anyhow::Error) -> BoxFuture<'a, ()> {
+        Box::pin(async {})
+    }
+}
+
+/// The CSV rows on stdout.
+pub(crate) struct StdoutSink;
+
+impl ResultSink for StdoutSink {
+    fn write<'a>(&'a self, outcome: &'a RunOutcome<'a>) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            report!();
+            report!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+            report!("📊 Results (CSV)");
+            report!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+            report!("{}", render_results_csv(outcome.args, outcome.results, true)?.trim_end_matches('\n'));
+            Ok(())
+        })
+    }
+}
+
+/// The results in --format, as --output writes them.
+pub(crate) struct FileSink(std::path::PathBuf);
+
+impl ResultSink for FileSink {
+    fn write<'a>(&'a self, outcome: &'a RunOutcome<'a>) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move { write_output_file(&self.0, outcome.args, outcome.results) })
+    }
+}
+
+/// One .hgrm file per result in a directory.
+pub(crate) struct HgrmSink(std::path::PathBuf);
+
+impl ResultSink for HgrmSink {
+    fn write<'a>(&'a self, outcome: &'a RunOutcome<'a>) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move { write_hgrm_files(&self.0, outcome.results) })
+    }
+}
+
+/// The per-interval series as CSV.
+pub(crate) struct TimeseriesCsvSink(std::path::PathBuf);
+
+impl ResultSink for TimeseriesCsvSink {
+    fn write<'a>(&'a self, outcome: &'a RunOutcome<'a>) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            let path = &self.0;
+            let csv = render_timeseries_csv(outcome.results)?.unwrap_or_default();
+            std::fs::write(path, csv).map_err(|e| anyhow::anyhow!("Failed to write time series to {}: {}", path.display(), e))?;
+            tracing::info!("💾 Wrote time series to {}", path.display());
+            Ok(())
+        })
+    }
+}
+
+/// A self-contained HTML page with charts.
+pub(crate) struct HtmlReportSink {
+    path: std::path::PathBuf,
+    title: String,
+}
+
+impl ResultSink for HtmlReportSink {
+    fn write<'a>(&'a self, outcome: &'a RunOutcome<'a>) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            let html = html_report(&self.title, &labelled_results(outcome.results)?);
+            std::fs::write(&self.path, html)
+                .map_err(|e| anyhow::anyhow!("Failed to write HTML report to {}: {}", self.path.display(), e))?;
+            tracing::info!("💾 Wrote HTML report to {}", self.path.display());
+            Ok(())
+        })
+    }
+}
+
+/// results.json, config.json, intervals.csv and the .hgrm files in the run's
+/// directory under --results-dir and/or uploaded under --results-bucket. A
+/// failed upload without --results-dir falls back to a local copy in
+/// `./<run_name>/` so the results aren't lost.
+pub(crate) struct ResultsDirSink {
+    run_dir: Option<std::path::PathBuf>,
+    bucket: Option<String>,
+    run_name: String,
+}
+
+impl ResultSink for ResultsDirSink {
+    fn write<'a>(&'a self, outcome: &'a RunOutcome<'a>) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            let files = results_files(outcome.args, outcome.results)?;
+            if let Some(run_dir) = &self.run_dir {
+                write_results_files(run_dir, &files)?;
+            }
+            if let Some(bucket) = &self.bucket {
+                if let Err(e) = upload_results_files(outcome.args, bucket, &self.run_name, &files).await {
+                    tracing::warn!(error = %e, bucket = %bucket, "Failed to upload results");
+                    if self.run_dir.is_none() {
+                        write_results_files(std::path::Path::new(&self.run_name), &files)?;
+                    }
+                }
+            }
+            Ok(())
+        })
+    }
+}
+
+/// InfluxDB line protocol to --influx-url and/or --influx-file.
+pub(crate) struct InfluxSink;
+
+impl ResultSink for InfluxSink {
+    fn write<'a>(&'a self, outcome: &'a RunOutcome<'a>) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move { write_influx(outcome.args, &render_influx_lines(outcome.args, outcome.results)?).await })
+    }
+}
+
+/// The results as gauges in a Prometheus Pushgateway group.
+pub(crate) struct PushgatewaySink(String);
+
+impl ResultSink for PushgatewaySink {
+    fn write<'a>(&'a self, outcome: &'a RunOutcome<'a>) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            let url = pushgateway_group_url(outcome.args, &self.0);
+            if push_metrics(&url, render_result_metrics(outcome.results)?).await {
+                tracing::info!("📤 Pushed results to {}", url);
+            }
+            Ok(())
+        })
+    }
+}
+
+/// A row per result in a SQLite file, for `trend`.
+pub(crate) struct SqliteSink {
+    path: std::path::PathBuf,
+    scenario: String,
+}
+
+impl ResultSink for SqliteSink {
+    fn write<'a>(&'a self, outcome: &'a RunOutcome<'a>) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move { record_results_db(&self.path, &self.scenario, outcome.results) })
+    }
+}
+
+/// A completion (or abort) notification posted to a webhook.
+pub(crate) struct WebhookSink {
+    url: String,
+    format: String,
+}
+
+impl ResultSink for WebhookSink {
+    fn write<'a>(&'a self, outcome: &'a RunOutcome<'a>) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            let (args, breaches) = (outcome.args, outcome.breaches);
+            let (event, text) = if stopped_early() {
+                ("interrupted", format!("qps-bench {} run interrupted; results are partial", args.mode))
+            } else if breaches.is_empty() {
+                ("completed", format!("qps-bench {} run completed", args.mode))
+            } else {
+                ("sla_breach", format!("qps-bench {} run breached {} SLA threshold(s)", args.mode, breaches.len()))
+            };
+            let payload = match self.format.as_str() {
+                "summary" => {
+                    let summary = webhook_summary(outcome.results)?;
+                    let lines: Vec<String> = summary
+                        .iter()
+                        .map(|entry| format!("• {}: {:.2} QPS, P99 {:.2} ms, {} errors", entry["result"].as_str().unwrap_or_default(),
+                                             entry["qps"].as_f64().unwrap_or_default(), entry["latency_ms_p99"].as_f64().unwrap_or_default(),
+                                             entry["err_ops"]))
+                        .collect();
+                    serde_json::json!({
+                        "event": event,
+                        "text": format!("{}\n{}", text, lines.join("\n")),
+                        "summary": summary,
+                        "sla_breaches": breaches,
+                    })
+                }
+                _ => serde_json::json!({
+                    "event": event,
+                    "text": text,
+                    "results": outcome.results,
+                    "sla_breaches": breaches,
+                }),
+            };
+            notify_webhook(&self.url, &payload).await;
+            Ok(())
+        })
+    }
+
+    fn abort<'a>(&'a self, args: &'a Args, error: &'a anyhow::Error) -> BoxFuture<'a, ()> {
+        Box::pin(async move {
+            let payload = serde_json::json!({
+                "event": "aborted",
+                "text": format!("qps-bench {} run aborted: {}", args.mode, error),
+                "error": error.to_string(),
+            });
+            notify_webhook(&self.url, &payload).await;
+        })
+    }
+}
+
+/// Splits a --sink value into its kind and target, checking the kind is known
+/// and has the target it needs.
+pub(crate) fn parse_sink_spec(spec: &str) -> Result<(&str, &str)> {
+    let (kind, target) = spec.split_once('=').unwrap_or((spec, ""));
+    match (kind, target.is_empty()) {
+        ("stdout", true) => Ok((kind, target)),
+        ("file" | "prometheus" | "webhook" | "sqlite", false) => Ok((kind, target)),
+        ("stdout", false) => anyhow::bail!("--sink stdout takes no target, got {}", spec),
+        ("file" | "prometheus" | "webhook" | "sqlite", true) => anyhow::bail!("--sink {} needs a target, as {}=...", kind, kind),
+        _ => anyhow::bail!("Unknown --sink kind: {} (expected stdout, file, prometheus, webhook or sqlite)", kind),
+    }
+}
+
+/// `<mode>-<hash>`, the hash covering the settings that make runs comparable:
+/// the workload's shape and the backend it ran against.
+pub(crate) fn default_scenario(args: &Args) -> String {
+    let mode = if args.phases.is_empty() { args.mode.as_str() } else { "phases" };
+    let settings = serde_json::json!({
+        "mode": args.mode,
+        "phases": args.phases,
+        "objects": args.objects,
+        "object_size_bytes": args.object_size_bytes,
+        "concurrency": args.concurrency,
+        "duration_seconds": args.duration_seconds,
+        "fanout": args.fanout,
+        "target_rate": args.target_rate,
+        "backend": backend_info(&args.backend),
+    });
+    let hash = fnv1a(settings.to_string().as_bytes());
+    format!("{}-{:08x}", mode, hash as u32)
+}
+
+/// The sinks this run's results go to: those of the individual flags, then
+/// every --sink in order.
+pub(crate) fn result_sinks(args: &Args, run_name: &str, run_dir: Option<&std::path::Path>) -> Result<Vec<Box<dyn ResultSink>>> {
+    let scenario = args.scenario.clone().unwrap_or_else(|| default_scenario(args));
+    let sqlite = |path: &std::path::Path| -> Result<Box<dyn ResultSink>> {
+        if !cfg!(feature = "results-db") {
+            let message = "SQLite result sinks require building with the `results-db` feature";
+            return Err(RunFailure::new(FailureCategory::Config, message).into());
+        }
+        Ok(Box::new(SqliteSink { path: path.to_path_buf(), scenario: scenario.clone() }))
+    };
+    
+    let mut sinks: Vec<Box<dyn ResultSink>> = Vec::new();
+    if args.format == "csv" {
+        sinks.push(Box::new(StdoutSink));
+    }
+    if let Some(path) = &args.output {
+        sinks.push(Box::new(FileSink(path.clone())));
+    }
+    if let Some(dir) = &args.hgrm_dir {
+        sinks.push(Box::new(HgrmSink(dir.clone())));
+    }
+    if let Some(path) = &args.results_db {
+        sinks.push(sqlite(path)?);
+    }
+    if let Some(path) = &args.timeseries_csv {
+        sinks.push(Box::new(TimeseriesCsvSink(path.clone())));
+    }
+    if let Some(path) = &args.html_report {
+        sinks.push(Box::new(HtmlReportSink { path: path.clone(), title: run_name.to_string() }));
+    }
+    if run_dir.is_some() || args.results_bucket.is_some() {
+        sinks.push(Box::new(ResultsDirSink {
+            run_dir: run_dir.map(|dir| dir.to_path_buf()),
+            bucket: args.results_bucket.clone(),
+            run_name: run_name.to_string(),
+        }));
+    }
+    if args.influx_url.is_some() || args.influx_file.is_some() {
+        sinks.push(Box::new(InfluxSink));
+    }
+    if let Some(base) = &args.pushgateway_url {
+        sinks.push(Box::new(PushgatewaySink(base.clone())));
+    }
+    if let Some(url) = &args.webhook_url {
+        sinks.push(Box::new(WebhookSink { url: url.clone(), format: args.webhook_format.clone() }));
+    }
+    for spec in &args.sinks {
+        let (kind, target) = parse_sink_spec(spec).map_err(|e| RunFailure::new(FailureCategory::Config, e.to_string()))?;
+        let sink: Box<dyn ResultSink> = match kind {
+            "stdout" => Box::new(StdoutSink),
+            "file" => Box::new(FileSink(target.into())),
+            "prometheus" => Box::new(PushgatewaySink(target.to_string())),
+            "webhook" => Box::new(WebhookSink { url: target.to_string(), format: args.webhook_format.clone() }),
+            _ => sqlite(std::path::Path::new(target))?,
+        };
+        sinks.push(sink);
+    }
+    Ok(sinks)
+}
+
+/// Sends the live metrics to a StatsD agent: op counts since the last flush
+/// as counters, in-flight ops as a gauge, and the op latencies as timings.
+/// Latencies are histogram-bucketed, each bucket sent once with a sample rate
+/// of 1/count so the agent counts it `count` times, which keeps the packet
+/// count independent of the op rate.
+pub(crate) struct StatsdSink {
+    socket: tokio::net::UdpSocket,
+    tags: String,
+    live: Arc<LiveMetrics>,
+    sent_ok: u64,
+    sent_err: u64,
+}
+
+impl StatsdSink {
+    pub(crate) async fn connect(args: &Args) -> Result<Self> {
+        let addr = args.statsd_addr.as_deref().unwrap_or_default();
+        let socket = tokio::net::UdpSocket::bind("0.0.0.0:0").await?;
+        socket
+            .connect(addr)
+            .await
+            .map_err(|e| RunFailure::new(FailureCategory::Config, format!("Invalid --statsd-addr {}: {}", addr, e)))?;
+        current_run()?.statsd_latencies.get_or_init(|| std::sync::Mutex::new(Histogram::<u64>::new(2).unwrap()));
+        Ok(StatsdSink {
+            socket,
+            tags: format!("backend:{},bucket:{}", statsd_tag(&args.backend.service), statsd_tag(&args.backend.bucket)),
+            live: live_metrics(),
+            sent_ok: 0,
+            sent_err: 0,
+        })
+    }
+
+    pub(crate) async fn flush(&mut self) {
+        let tags = format!("mode:{},{}", statsd_tag(&self.live.mode.lock().unwrap()), self.tags);
+        let ok_ops = self.live.ok_ops.load(std::sync::atomic::Ordering::Relaxed);
+        let err_ops = self.live.err_ops.load(std::sync::atomic::Ordering::Relaxed);
+        let mut lines = vec![
+            format!("qps_bench.ops:{}|c|#{},status:ok", ok_ops - self.sent_ok, tags),
+            format!("qps_bench.ops:{}|c|#{},status:error", err_ops - self.sent_err, tags),
+            format!("qps_bench.in_flight:{}|g|#{}", self.live.in_flight.load(std::sync::atomic::Ordering::Relaxed).max(0), tags),
+        ];
+        self.sent_ok = ok_ops;
+        self.sent_err = err_ops;
+        with_run(|run| {
+            let Some(latencies) = run.statsd_latencies.get() else {
+                return;
+            };
+            let mut latencies = latencies.lock().unwrap();
+            for value in latencies.iter_recorded() {
+                let ms = value.value_iterated_to() as f64 / 1000.0;
+                let count = value.count_at_value();
+                if count == 1 {
+                    lines.push(format!("qps_bench.op_latency:{:.3}|ms|#{}", ms, tags));
+                } else {
+                    lines.push(format!("qps_bench.op_latency:{:.3}|ms|@{}|#{}", ms, 1.0 / count as f64, tags));
+                }
+            }
+            latencies.reset();
+        });
+        
+        // Several metrics per datagram, kept under a typical MTU
+        let mut packet = String::new();
+        for line in lines {
+            if !packet.is_empty() && packet.len() + line.len() + 1 > 1400 {
+                self.send(&packet).await;
+                packet.clear();
+            }
+            if !packet.is_empty() {
+                packet.push('\n');
+            }
+            packet.push_str(&line);
+        }
+        if !packet.is_empty() {
+            self.send(&packet).await;
+        }
+    }
+
+    async fn send(&self, packet: &str) {
+        if let Err(e) = self.socket.send(packet.as_bytes()).await {
+            tracing::warn!(error = %e, "Failed to send StatsD metrics");
+        }
+    }
+}
+
+/// Makes `value` safe as a DogStatsD tag value.
+pub(crate) fn statsd_tag(value: &str) -> String {
+    value.chars().map(|c| if matches!(c, ',' | '|' | '#' | ':') || c.is_whitespace() { '_' } else { c }).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prometheus_label_escapes_quotes_backslashes_and_newlines() {
+        assert_eq!(prometheus_label("read_small"), "read_small");
+        assert_eq!(prometheus_label("a\\b\"c\nd"), "a\\\\b\\\"c\\nd");
+    }
+
+    #[test]
+    fn sink_specs_need_a_known_kind_and_its_target() {
+        assert_eq!(parse_sink_spec("stdout").unwrap(), ("stdout", ""));
+        assert_eq!(parse_sink_spec("file=out/results.json").unwrap(), ("file", "out/results.json"));
+        assert_eq!(parse_sink_spec("webhook=https://example.com/a=b").unwrap(), ("webhook", "https://example.com/a=b"));
+        assert_eq!(parse_sink_spec("stdout=x").unwrap_err().to_string(), "--sink stdout takes no target, got stdout=x");
+        assert_eq!(parse_sink_spec("sqlite").unwrap_err().to_string(), "--sink sqlite needs a target, as sqlite=...");
+        assert!(parse_sink_spec("kafka=topic").unwrap_err().to_string().starts_with("Unknown --sink kind: kafka"));
+    }
+}
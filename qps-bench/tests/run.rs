@@ -0,0 +1,136 @@
+//! Runs the library's `run` against an in-process stand-in for S3.
+
+use std::collections::BTreeMap;
+use std::convert::Infallible;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use bytes::Bytes;
+use http_body_util::{BodyExt, Full};
+use hyper::{Method, Request, Response, StatusCode};
+
+type Objects = Arc<Mutex<BTreeMap<String, Bytes>>>;
+
+/// The values of every `<tag>` element in `xml`.
+fn tag_values<'a>(xml: &'a str, tag: &str) -> Vec<&'a str> {
+    let (open, close) = (format!("<{}>", tag), format!("</{}>", tag));
+    xml.split(open.as_str()).skip(1).filter_map(|rest| rest.split_once(close.as_str()).map(|(value, _)| value)).collect()
+}
+
+/// Answers the requests of a benchmark from objects kept in memory. Requests
+/// are path-style: /BUCKET/KEY.
+async fn handle(objects: Objects, request: Request<hyper::body::Incoming>) -> Result<Response<Full<Bytes>>, Infallible> {
+    let method = request.method().clone();
+    let query = request.uri().query().unwrap_or_default().to_string();
+    let key = request.uri().path().trim_start_matches('/').split_once('/').map_or("", |(_, key)| key).to_string();
+    let body = request.into_body().collect().await.map(|body| body.to_bytes()).unwrap_or_default();
+    let response = Response::builder();
+    let response = match method {
+        Method::PUT => {
+            objects.lock().unwrap().insert(key, body);
+            response.header("etag", "\"mock\"").body(Full::default())
+        }
+        Method::DELETE => {
+            objects.lock().unwrap().remove(&key);
+            response.status(StatusCode::NO_CONTENT).body(Full::default())
+        }
+        Method::POST if query.contains("delete") => {
+            let request = String::from_utf8_lossy(&body);
+            let mut objects = objects.lock().unwrap();
+            let mut result = String::from("<DeleteResult>");
+            for key in tag_values(&request, "Key") {
+                objects.remove(key);
+                result.push_str(&format!("<Deleted><Key>{}</Key></Deleted>", key));
+            }
+            result.push_str("</DeleteResult>");
+            response.body(Full::new(Bytes::from(result)))
+        }
+        Method::GET if query.contains("list-type=2") => {
+            let prefix = query
+                .split('&')
+                .find_map(|pair| pair.strip_prefix("prefix="))
+                .map(|prefix| prefix.replace("%2F", "/"))
+                .unwrap_or_default();
+            let mut result = String::from("<ListBucketResult><IsTruncated>false</IsTruncated>");
+            for (key, content) in objects.lock().unwrap().range(prefix.clone()..).take_while(|(key, _)| key.starts_with(&prefix)) {
+                result.push_str(&format!("<Contents><Key>{}</Key><Size>{}</Size></Contents>", key, content.len()));
+            }
+            result.push_str("</ListBucketResult>");
+            response.body(Full::new(Bytes::from(result)))
+        }
+        Method::GET | Method::HEAD => match objects.lock().unwrap().get(&key) {
+            Some(content) => {
+                let response = response
+                    .header("etag", "\"mock\"")
+                    .header("last-modified", "Thu, 01 Jan 2026 00:00:00 GMT")
+                    .header("content-length", content.len());
+                if method == Method::HEAD {
+                    response.body(Full::default())
+                } else {
+                    response.body(Full::new(content.clone()))
+                }
+            }
+            None if method == Method::HEAD => response.status(StatusCode::NOT_FOUND).body(Full::default()),
+            None => response
+                .status(StatusCode::NOT_FOUND)
+                .body(Full::new(Bytes::from("<Error><Code>NoSuchKey</Code><Message>The specified key does not exist.</Message></Error>"))),
+        },
+        _ => response.status(StatusCode::NOT_IMPLEMENTED).body(Full::default()),
+    };
+    Ok(response.unwrap())
+}
+
+/// Starts the stand-in on a free local port and returns its endpoint.
+async fn serve_s3() -> String {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let objects = Objects::default();
+    tokio::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            let objects = objects.clone();
+            let service = hyper::service::service_fn(move |request| handle(objects.clone(), request));
+            tokio::spawn(hyper::server::conn::http1::Builder::new().serve_connection(hyper_util::rt::TokioIo::new(stream), service));
+        }
+    });
+    format!("http://{}", addr)
+}
+
+fn scenario(endpoint: &str) -> qps_bench::Scenario {
+    qps_bench::Scenario::new("stat")
+        .unwrap()
+        .with_endpoint(endpoint)
+        .with_bucket("bench")
+        .with_region("us-east-1")
+        .with_credentials("access", "secret")
+        .with_concurrency(4)
+        .with_objects(20)
+        .with_object_size(64)
+        .with_duration(Duration::from_secs(1))
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn consecutive_runs_start_from_scratch() {
+    let endpoint = serve_s3().await;
+    let first = qps_bench::run(scenario(&endpoint)).await.unwrap();
+    let second = qps_bench::run(scenario(&endpoint)).await.unwrap();
+    for report in [&first, &second] {
+        assert!(!report.interrupted);
+        assert_eq!(report.results.len(), 1);
+        assert!(report.results[0].ok_ops > 0);
+        assert_eq!(report.results[0].err_ops, 0);
+    }
+    assert_ne!(first.run_id, second.run_id);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn overlapping_runs_keep_apart() {
+    let endpoint = serve_s3().await;
+    let (first, second) = tokio::join!(
+        qps_bench::run(scenario(&endpoint).with_prefix("first")),
+        qps_bench::run(scenario(&endpoint).with_prefix("second")),
+    );
+    let (first, second) = (first.unwrap(), second.unwrap());
+    assert!(!first.interrupted && !second.interrupted);
+    assert!(first.results[0].ok_ops > 0 && second.results[0].ok_ops > 0);
+    assert_ne!(first.run_id, second.run_id);
+}